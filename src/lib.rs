@@ -15,1004 +15,12232 @@
 // It should be noted that the games created in "Hands on Rust" are a Flappy Bird clone,
 // and a roguelike dungeon crawler which I've yet to start on.
 
+use crossterm::style::Stylize;
 use input_handler::InputMode;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::OnceCell,
+    collections::VecDeque,
+    fmt::{self, Write as _},
+    fs, io,
+    ops::Range,
+    path::Path,
+    rc::Rc,
+    str::FromStr,
+    time::Duration,
+};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum GameMode {
     Config,
     Play,
+    Stats,
+    Leaderboard,
+    Debug,
+    Resume,
+    // Today's board, the same for everyone
+    Daily,
+    // A run of ever harder boards, sharing lives and score
+    Campaign,
+    // Back at the menu, with any unfinished game paused
+    Menu,
+    // Leave the program
     Quit,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Difficulty {
     Easy,
     Medium,
     Hard,
+    Custom { width: u32, height: u32, mines: u32 },
 }
 
-// Provide structure to game data
-pub struct GameState {
-    game_mode: GameMode,
-    game_over: bool,
-    game_won: bool,
-    board_width: u32,
-    board_height: u32,
-    starting_mines: u32,
-    mine_count: u32,
-    turn_count: u32,
-    tiles: Vec<Tile>,
-    selected_tile: Option<usize>,
-    input_mode: InputMode,
-}
-
-// Provide type checked names to capture the state of our tiles
-#[derive(Debug, PartialEq)]
-pub enum Tile {
-    Hidden { has_mine: bool, flagged: bool },
-    Revealed { has_mine: bool, hint: u32 },
-}
-
-impl Default for GameState {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl GameState {
+impl Difficulty {
+    // Board width, height, and number of mines for each difficulty
     #[must_use]
-    pub fn new() -> GameState {
-        GameState {
-            game_mode: GameMode::Play,
-            game_over: false,
-            game_won: false,
-            board_height: 0,
-            board_width: 0,
-            starting_mines: 0,
-            mine_count: 0,
-            turn_count: 0,
-            tiles: Vec::new(),
-            selected_tile: None,
-            input_mode: InputMode::Undo,
+    pub fn dimensions(&self) -> (u32, u32, u32) {
+        match *self {
+            Difficulty::Easy => (5, 5, 4),
+            Difficulty::Medium => (8, 8, 14),
+            Difficulty::Hard => (12, 12, 35),
+            Difficulty::Custom {
+                width,
+                height,
+                mines,
+            } => (width, height, mines),
         }
     }
 
+    // A custom board with `percent` of its tiles mined, rounded to the nearest tile. The count
+    // is kept to at least one mine and at least one safe tile. The sums are done in u64 so
+    // oversize dimensions come back as a board for `validate_board` to turn down.
     #[must_use]
-    pub fn get_game_over(&self) -> bool {
-        self.game_over
-    }
-
-    #[must_use]
-    pub fn get_height(&self) -> u32 {
-        self.board_height
-    }
-
-    #[must_use]
-    pub fn get_width(&self) -> u32 {
-        self.board_width
+    pub fn with_density(width: u32, height: u32, percent: u32) -> Difficulty {
+        let tiles = u64::from(width) * u64::from(height);
+        let percent = u64::from(percent.clamp(1, MAX_DENSITY));
+        let mines = ((tiles * percent + 50) / 100).clamp(1, tiles.saturating_sub(1).max(1));
+
+        Difficulty::Custom {
+            width,
+            height,
+            mines: u32::try_from(mines).unwrap_or(u32::MAX),
+        }
     }
 
+    // A suggested time attack budget, about two seconds a tile, within what can be set
     #[must_use]
-    pub fn get_tile(&self, index: usize) -> &Tile {
-        &self.tiles[index]
+    pub fn time_limit(&self) -> Duration {
+        let (width, height, _) = self.dimensions();
+        let seconds = width.saturating_mul(height).saturating_mul(2);
+        Duration::from_secs(
+            seconds
+                .clamp(MIN_TIME_LIMIT_SECS, MAX_TIME_LIMIT_SECS)
+                .into(),
+        )
     }
 
+    // Map a board back to the preset it came from, if any
     #[must_use]
-    pub fn get_selected(&self) -> usize {
-        self.selected_tile
-            .expect("Should always have 'Some' value during normal play.")
+    pub fn from_dimensions(width: u32, height: u32, mines: u32) -> Difficulty {
+        [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
+            .into_iter()
+            .find(|preset| preset.dimensions() == (width, height, mines))
+            .unwrap_or(Difficulty::Custom {
+                width,
+                height,
+                mines,
+            })
     }
+}
 
-    #[must_use]
-    pub fn get_input_mode(&self) -> InputMode {
-        self.input_mode
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Easy => f.pad("Easy"),
+            Difficulty::Medium => f.pad("Medium"),
+            Difficulty::Hard => f.pad("Hard"),
+            Difficulty::Custom {
+                width,
+                height,
+                mines,
+            } => f.pad(&format!("{width}x{height}/{mines}")),
+        }
     }
+}
 
-    #[must_use]
-    pub fn get_won(&self) -> bool {
-        self.game_won
-    }
+// Reasons a board can't be set up
+#[derive(Debug, PartialEq, Clone)]
+pub enum BoardError {
+    ZeroDimension { width: u32, height: u32 },
+    TooLarge { width: u32, height: u32 },
+    UnevenWrap { width: u32, height: u32 },
+    TooManyMines { mines: u32, tiles: u32 },
+    MineOffBoard { index: usize, tiles: u32 },
+}
 
-    #[must_use]
-    pub fn get_mines(&self) -> u32 {
-        self.starting_mines
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardError::ZeroDimension { width, height } => {
+                write!(f, "A {width}x{height} board has no tiles")
+            }
+            BoardError::TooLarge { width, height } => write!(
+                f,
+                "A {width}x{height} board is too big; boards are at most {MAX_WIDTH}x{MAX_HEIGHT}"
+            ),
+            BoardError::UnevenWrap { width, height } => write!(
+                f,
+                "Triangles on a {width}x{height} board can't wrap; that needs an even width and height"
+            ),
+            BoardError::TooManyMines { mines, tiles } => write!(
+                f,
+                "{mines} mines won't fit on {tiles} tiles; there must be fewer mines than tiles"
+            ),
+            BoardError::MineOffBoard { index, tiles } => {
+                write!(f, "A mine at tile {index} is off a board of {tiles} tiles")
+            }
+        }
     }
+}
 
-    #[must_use]
-    pub fn get_game_mode(&self) -> GameMode {
-        self.game_mode
-    }
+impl std::error::Error for BoardError {}
 
-    #[must_use]
-    pub fn get_mine_count(&self) -> u32 {
-        self.mine_count
-    }
+// Shared error for turning user supplied text into one of our enums
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    expected: &'static str,
+    input: String,
+}
 
+impl ParseError {
     #[must_use]
-    pub fn get_turn_count(&self) -> u32 {
-        self.turn_count
-    }
-
-    pub fn set_game_over(&mut self, game_over: bool) {
-        self.game_over = game_over;
+    pub fn new(expected: &'static str, input: &str) -> ParseError {
+        ParseError {
+            expected,
+            input: input.to_string(),
+        }
     }
+}
 
-    pub fn set_height(&mut self, height: u32) {
-        self.board_height = height;
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' is not a valid {}", self.input, self.expected)
     }
+}
 
-    pub fn set_width(&mut self, width: u32) {
-        self.board_width = width;
-    }
+impl std::error::Error for ParseError {}
+
+// Reasons a move isn't allowed, from `GameState::check_move`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MoveError {
+    GameOver,
+    OffBoard,
+    NotHidden,
+    FlaggingOff,
+    NoFlagsLeft,
+    // A chord on a tile that isn't a number with all of its mines flagged
+    NotChordable,
+    NothingToUndo,
+    NoUndosLeft,
+    NothingToRedo,
+    // A hint asked for when every move left would be a guess
+    NoCertainMove,
+    // A hint asked for on a board whose tiles can hold more than one mine, which the solver
+    // can't reason about
+    NotWithStackedMines,
+}
 
-    pub fn set_tile(&mut self, index: usize, tile_state: Tile) {
-        self.tiles[index] = tile_state;
-    }
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            MoveError::GameOver => "The game is already over",
+            MoveError::OffBoard => "Column and row must be within valid bounds",
+            MoveError::NotHidden => "Selected tile must be hidden",
+            MoveError::FlaggingOff => "Flags are turned off for this game",
+            MoveError::NoFlagsLeft => "No flags remaining",
+            MoveError::NotChordable => "Select a number whose flags account for all of its mines",
+            MoveError::NothingToUndo => "No moves to go back on",
+            MoveError::NoUndosLeft => "No undos left this game",
+            MoveError::NothingToRedo => "No moves to redo",
+            MoveError::NoCertainMove => "No move is certain from here, so it's down to a guess",
+            MoveError::NotWithStackedMines => {
+                "Hints aren't available when tiles hold several mines"
+            }
+        };
 
-    pub fn set_selected(&mut self, index: u32) {
-        let selected_tile = index as usize;
-        self.selected_tile = Some(selected_tile);
+        f.write_str(message)
     }
+}
 
-    pub fn set_input_mode(&mut self, input_mode: InputMode) {
-        self.input_mode = input_mode;
-    }
+impl std::error::Error for MoveError {}
+
+// Anything the library can fail at, for callers that would rather handle it than abort
+#[derive(Debug, PartialEq, Clone)]
+pub enum MinesweeperError {
+    Board(BoardError),
+    Parse(ParseError),
+    OutOfBounds { index: usize, tiles: usize },
+    // Asked for the selected tile before any move picked one
+    NoSelection,
+    // Reading the player's input failed, or it ran out
+    Input(io::ErrorKind),
+    Move(MoveError),
+}
 
-    pub fn set_won(&mut self, game_won: bool) {
-        self.game_won = game_won;
+impl fmt::Display for MinesweeperError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MinesweeperError::Board(error) => error.fmt(f),
+            MinesweeperError::Parse(error) => error.fmt(f),
+            MinesweeperError::OutOfBounds { index, tiles } => {
+                write!(f, "Tile {index} is off a board of {tiles} tiles")
+            }
+            MinesweeperError::NoSelection => write!(f, "No tile has been selected yet"),
+            MinesweeperError::Input(kind) => write!(f, "Unable to read input: {kind}"),
+            MinesweeperError::Move(error) => error.fmt(f),
+        }
     }
+}
 
-    pub fn set_mines(&mut self, num_mines: u32) {
-        self.starting_mines = num_mines;
+impl std::error::Error for MinesweeperError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MinesweeperError::Board(error) => Some(error),
+            MinesweeperError::Parse(error) => Some(error),
+            MinesweeperError::Move(error) => Some(error),
+            _ => None,
+        }
     }
+}
 
-    pub fn set_game_mode(&mut self, game_mode: GameMode) {
-        self.game_mode = game_mode;
+impl From<BoardError> for MinesweeperError {
+    fn from(error: BoardError) -> Self {
+        MinesweeperError::Board(error)
     }
+}
 
-    pub fn set_mine_count(&mut self, mine_count: u32) {
-        self.mine_count = mine_count;
+impl From<ParseError> for MinesweeperError {
+    fn from(error: ParseError) -> Self {
+        MinesweeperError::Parse(error)
     }
+}
 
-    pub fn board_setup(&mut self, width: u32, height: u32, num_mines: u32) {
-        self.board_width = width;
-        self.board_height = height;
-        self.starting_mines = num_mines;
-        self.mine_count = num_mines;
-        self.turn_count = 0;
-        self.game_won = false;
-        self.game_over = false;
-        self.clear_tiles();
+impl From<MoveError> for MinesweeperError {
+    fn from(error: MoveError) -> Self {
+        MinesweeperError::Move(error)
     }
+}
 
-    pub fn add_tile(&mut self, tile_state: Tile) {
-        self.tiles.push(tile_state);
+impl From<io::Error> for MinesweeperError {
+    fn from(error: io::Error) -> Self {
+        MinesweeperError::Input(error.kind())
     }
+}
 
-    pub fn represent_tile(&mut self, index: u32) -> String {
-        let tile = index as usize;
-
-        match self.tiles.get(tile) {
-            Some(Tile::Hidden {
-                has_mine: _,
-                flagged: true,
-            }) => String::from(" F "),
-            Some(Tile::Revealed {
-                has_mine: true,
-                hint: _,
-            }) => String::from(" X "),
-            Some(Tile::Revealed {
-                has_mine: false,
-                hint: x,
-            }) => format!(" {x} "),
-            None => String::from(" ? "),
-            _ => String::from(" - "),
+impl FromStr for GameMode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "play" | "p" => Ok(GameMode::Play),
+            "configure" | "c" => Ok(GameMode::Config),
+            "stats" | "s" => Ok(GameMode::Stats),
+            "leaderboard" | "l" => Ok(GameMode::Leaderboard),
+            "resume" | "r" => Ok(GameMode::Resume),
+            "menu" | "m" => Ok(GameMode::Menu),
+            "daily" | "d" => Ok(GameMode::Daily),
+            "campaign" | "a" => Ok(GameMode::Campaign),
+            // Hidden from the menu prompt, and only available in debug builds
+            "debug" if cfg!(debug_assertions) => Ok(GameMode::Debug),
+            "quit" | "q" => Ok(GameMode::Quit),
+            _ => Err(ParseError::new("game mode", s)),
         }
     }
+}
 
-    pub fn clear_tiles(&mut self) {
-        self.tiles = Vec::new();
-    }
-
-    pub fn increment_turn_count(&mut self) {
-        self.turn_count += 1;
-    }
+impl FromStr for Difficulty {
+    type Err = ParseError;
 
-    pub fn increment_mine_count(&mut self) {
-        self.mine_count += 1;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "easy" | "e" => Ok(Difficulty::Easy),
+            "medium" | "m" => Ok(Difficulty::Medium),
+            "hard" | "h" => Ok(Difficulty::Hard),
+            _ => Err(ParseError::new("difficulty", s)),
+        }
     }
+}
 
-    pub fn decrement_mine_count(&mut self) {
-        self.mine_count -= 1;
-    }
+// Everything a move can change, kept so the move can be taken back
+#[derive(Clone)]
+struct Snapshot {
+    tiles: Vec<Tile>,
+    mine_counts: Vec<u8>,
+    flag_counts: Vec<u8>,
+    mine_count: u32,
+    turn_count: u32,
+    lives: u32,
+    game_over: bool,
+    game_won: bool,
+    moves: Vec<(usize, InputMode)>,
+    move_times: Vec<Duration>,
 }
 
-pub mod game_loop {
+// How many moves can be undone unless set otherwise
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
 
-    use super::{input_handler, input_handler::InputMode, Difficulty, GameMode, GameState, Tile};
-    use crossterm::{execute, terminal};
-    use rand::Rng;
-    use std::io::stdout;
+// Time added to the clock for each move taken back, and the most undos a game can allow
+pub const UNDO_PENALTY: Duration = Duration::from_secs(10);
+pub const MAX_UNDOS: u32 = 9;
 
-    pub fn play() {
-        loop {
-            let mut state = GameState::new();
-            let (mode, difficulty) = menu();
+// Fewest and most seconds a time attack game can be given
+pub const MIN_TIME_LIMIT_SECS: u32 = 10;
+pub const MAX_TIME_LIMIT_SECS: u32 = 3600;
 
-            if mode == GameMode::Quit {
-                clear_screen();
-                break;
-            }
+// Provide structure to game data.
+// Serializing leaves out the theme, which is up to whoever shows the game, along with the
+// undo history, the event listener, and tables that are rebuilt from the board on demand.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[allow(clippy::struct_excessive_bools)]
+pub struct GameState {
+    game_mode: GameMode,
+    game_over: bool,
+    game_won: bool,
+    board: Board,
+    starting_mines: u32,
+    mine_count: u32,
+    turn_count: u32,
+    selected_tile: Option<Selection>,
+    input_mode: InputMode,
+    seed: Option<u64>,
+    lives: u32,
+    starting_lives: u32,
+    show_probabilities: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    theme: Theme,
+    moves: Vec<(usize, InputMode)>,
+    // The game clock as each move was made, alongside `moves`
+    move_times: Vec<Duration>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    // Built when first needed, from the board and its wrap and adjacency options
+    neighbors: OnceCell<Rc<[Vec<usize>]>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hints: Vec<u32>,
+    elapsed: Duration,
+    wrap: bool,
+    adjacency: Adjacency,
+    // The outline the board was dealt in, with the tiles outside it inactive
+    #[cfg_attr(feature = "serde", serde(default))]
+    shape: Shape,
+    confirm_risky_clears: bool,
+    first_click_zero: bool,
+    first_click_safe: bool,
+    no_guess: bool,
+    practice: bool,
+    // Most mines a tile can hold. Above 1, each mined tile holds between 1 and this many, dealt
+    // on the first clear into `mine_counts`, and numbers add them all up.
+    mines_per_tile: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    mine_counts: Vec<u8>,
+    // How many mines each flag on a multi-mine board stands for, where the player has flagged a
+    // tile more than once. Tiles not in it, or at 0, have a flag for a single mine.
+    #[cfg_attr(feature = "serde", serde(default))]
+    flag_counts: Vec<u8>,
+    unicode_grid: bool,
+    compact: bool,
+    auto_flag: bool,
+    flagging_allowed: bool,
+    // Flags that can be out at once, when capped below the mine count
+    flag_limit: Option<u32>,
+    question_marks: bool,
+    win_condition: WinCondition,
+    flag_operations: u32,
+    hints_used: u32,
+    clicks: u32,
+    time_limit: Option<Duration>,
+    timed_out: bool,
+    daily: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    history: VecDeque<Snapshot>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    redo_stack: Vec<Snapshot>,
+    history_limit: usize,
+    // Undos allowed each game, with no limit when None
+    undo_limit: Option<u32>,
+    undos_used: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    on_event: EventHook,
+}
 
-            // Reset the game state after a game over
-            setup(&mut state, &difficulty);
+// Something that happened to the board, for a listener set with `set_event_callback`.
+// Each carries the tile it happened to, as it is afterwards.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GameEvent {
+    Revealed { index: usize, tile: Tile },
+    // A flood fill out of the zero at `index` finished, having revealed this many more tiles
+    FloodFilled { index: usize, revealed: usize },
+    // A mine was revealed, whether or not a spare life saves the game
+    Exploded { index: usize, tile: Tile },
+    // A flag or question mark placed or taken away
+    Flagged { index: usize, tile: Tile },
+    // The move that won or lost the game
+    Won { index: usize, tile: Tile },
+    Lost { index: usize, tile: Tile },
+}
 
-            // Draw the initial game state
-            draw(&mut state);
+// A move or command for `GameState::apply`, so any frontend can drive the game the same way
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Action {
+    Reveal(Coord),
+    // Place or take away a flag
+    Flag(Coord),
+    // Reveal around a number with as many flags as its hint, trusting the flags to be right
+    Chord(Coord),
+    // As a chord, but refused rather than risking a mine when the flags are wrong
+    SafeReveal(Coord),
+    // Make one move that is certain from the numbers
+    Hint,
+    Undo,
+    Redo,
+    // Start the same board over
+    Restart,
+}
 
-            loop {
-                let game_over = state.get_game_over();
-                let won = state.get_won();
+// What an action did to the board
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ActionResult {
+    // False when the action wasn't allowed, leaving the game untouched
+    pub applied: bool,
+    // Tiles that look different afterwards
+    pub changed: Vec<usize>,
+    pub game_over: bool,
+    pub game_won: bool,
+}
 
-                if game_over || won {
-                    input_handler::enter_to_continue();
-                    break;
-                }
+// Where a game stands, for code playing it without a frontend
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GameOutcome {
+    Won,
+    // Out of lives or out of time
+    Lost,
+    InProgress,
+}
 
-                // Process console input
-                process_input(&mut state);
+// The listener for game events, if any. Copies of a game start without one, so trying moves
+// out on a snapshot doesn't report them.
+type EventCallback = Box<dyn FnMut(&GameEvent)>;
 
-                // Update the game state
-                update(&mut state);
+#[derive(Default)]
+struct EventHook(Option<EventCallback>);
 
-                // Redraw game state after each update
-                draw(&mut state);
-            }
-        }
+impl Clone for EventHook {
+    fn clone(&self) -> Self {
+        EventHook(None)
     }
+}
 
-    fn print_title() {
-        let title_menu = r#"
-  __  __ _____ _   _ ______  _______          ________ ______ _____  ______ _____  
- |  \/  |_   _| \ | |  ____|/ ____\ \        / /  ____|  ____|  __ \|  ____|  __ \ 
- | \  / | | | |  \| | |__  | (___  \ \  /\  / /| |__  | |__  | |__) | |__  | |__) |
- | |\/| | | | | . ` |  __|  \___ \  \ \/  \/ / |  __| |  __| |  ___/|  __| |  _  / 
- | |  | |_| |_| |\  | |____ ____) |  \  /\  /  | |____| |____| |    | |____| | \ \ 
- |_|  |_|_____|_| \_|______|_____/    \/  \/   |______|______|_|    |______|_|  \_\
-
-
-   __  ___  __  _           
-  / / | _ \ \ \| |__ _ _  _ 
- | |  |  _/  | | / _` | || |
- | |  |_|    | |_\__,_|\_, |
-  \_\       /_/        |__/ 
+// How a game turned out, for logging outside the game
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ResultSummary {
+    pub won: bool,
+    pub turn_count: u32,
+    pub hints_used: u32,
+    pub elapsed_secs: u64,
+    pub width: u32,
+    pub height: u32,
+    pub starting_mines: u32,
+    pub seed: Option<u64>,
+}
 
-   __   ___  __            __ _                   
-  / /  / __| \ \ ___ _ _  / _(_)__ _ _  _ _ _ ___ 
- | |  | (__   | / _ \ ' \|  _| / _` | || | '_/ -_)
- | |   \___|  | \___/_||_|_| |_\__, |\_,_|_| \___|
-  \_\        /_/               |___/              
+impl ResultSummary {
+    // A single line JSON object, with `null` for a missing seed
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let seed = self
+            .seed
+            .map_or_else(|| String::from("null"), |seed| seed.to_string());
+
+        format!(
+            "{{\"won\":{},\"turn_count\":{},\"hints_used\":{},\"elapsed_secs\":{},\"width\":{},\"height\":{},\"starting_mines\":{},\"seed\":{}}}",
+            self.won,
+            self.turn_count,
+            self.hints_used,
+            self.elapsed_secs,
+            self.width,
+            self.height,
+            self.starting_mines,
+            seed
+        )
+    }
+}
 
-   __   ___   __       _ _   
-  / /  / _ \  \ \ _  _(_) |_ 
- | |  | (_) |  | | || | |  _|
- | |   \__\_\  | |\_,_|_|\__|
-  \_\         /_/            
-      "#;
+// The symbols used to draw each kind of tile
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Theme {
+    pub hidden: &'static str,
+    pub flag: &'static str,
+    pub mine: &'static str,
+    pub empty: &'static str,
+    pub question: &'static str,
+    pub wrong_flag: &'static str,
+}
 
-        println!("{title_menu}");
+impl Theme {
+    #[must_use]
+    pub fn classic() -> Theme {
+        Theme {
+            hidden: "-",
+            flag: "F",
+            mine: "X",
+            empty: "0",
+            question: "?",
+            wrong_flag: "✗",
+        }
     }
 
-    fn config() -> Difficulty {
-        input_handler::read_difficulty(
-            "Enter preferred difficulty level [(e)asy | (m)edium | (h)ard] : ",
-        )
+    #[must_use]
+    pub fn unicode() -> Theme {
+        Theme {
+            hidden: "▢",
+            flag: "🚩",
+            mine: "💣",
+            empty: " ",
+            question: "?",
+            wrong_flag: "❌",
+        }
     }
+}
 
-    fn menu() -> (GameMode, Difficulty) {
-        clear_screen();
-        print_title();
-        let game_mode =
-            input_handler::read_game_mode("Enter choice [(p)lay | (c)onfigure | (q)uit] : ");
-
-        let difficulty;
-        match game_mode {
-            GameMode::Play | GameMode::Quit => (game_mode, Difficulty::Easy),
-            GameMode::Config => {
-                difficulty = config();
-                (GameMode::Play, difficulty)
-            }
-        }
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
     }
+}
 
-    fn place_mines(state: &mut GameState) {
-        let total_tiles = (state.get_width() * state.get_height()) as usize;
-        let num_mines = state.get_mines() as usize;
+// Which tiles count as touching: all eight around a tile, or just the four sharing an edge
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Adjacency {
+    #[default]
+    Eight,
+    Four,
+    // Triangular tiles, pointing up and down in turn, each touching twelve others
+    Triangle,
+}
 
-        // Generate an array of all tile indices
-        let mut indices: Vec<usize> = (0..total_tiles).collect();
+// The outline of the board within its rectangle. Tiles outside it are inactive.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Shape {
+    #[default]
+    Rectangle,
+    Diamond,
+    // The middle third of the columns and of the rows
+    Cross,
+    // A rectangle with the middle third of both cut out
+    Donut,
+}
 
-        // Fisher-Yates shuffle algorithm
-        // idea to use this algorithm came from the following stack overflow question:
-        // https://stackoverflow.com/questions/28891084/minesweeper-mine-generation-algorithm
-        let mut rng = rand::thread_rng();
-        for i in (1..total_tiles).rev() {
-            let j = rng.gen_range(0..=i);
-            indices.swap(i, j);
+impl Shape {
+    // The name saves, replays and the command line use for the shape
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Rectangle => "rectangle",
+            Shape::Diamond => "diamond",
+            Shape::Cross => "cross",
+            Shape::Donut => "donut",
         }
+    }
 
-        // Place mines in the first `num_mines` positions of the shuffled indices
-        for &mine_index in indices.iter().take(num_mines) {
-            let tile = state.get_tile(mine_index);
+    // Whether a place on a `width` by `height` board falls inside the shape
+    #[must_use]
+    pub fn contains(self, width: u32, height: u32, coord: Coord) -> bool {
+        // Positions are doubled so boards of even and odd size are both centered exactly
+        let across = (2 * coord.col).abs_diff(width - 1);
+        let down = (2 * coord.row).abs_diff(height - 1);
+        let middle = |position: u32, length: u32| {
+            3 * position + 1 >= length && 3 * position + 1 < 2 * length
+        };
 
-            if let Tile::Hidden {
-                has_mine: false,
-                flagged,
-            } = tile
-            {
-                state.set_tile(
-                    mine_index,
-                    Tile::Hidden {
-                        has_mine: true,
-                        flagged: *flagged,
-                    },
-                );
+        match self {
+            Shape::Rectangle => true,
+            Shape::Diamond => {
+                u64::from(across) * u64::from(height) + u64::from(down) * u64::from(width)
+                    <= u64::from(width) * u64::from(height)
             }
+            Shape::Cross => middle(coord.col, width) || middle(coord.row, height),
+            Shape::Donut => !(middle(coord.col, width) && middle(coord.row, height)),
         }
     }
+}
 
-    fn setup(state: &mut GameState, difficulty: &Difficulty) {
-        match difficulty {
-            Difficulty::Easy => state.board_setup(5, 5, 4),
-            Difficulty::Medium => state.board_setup(8, 8, 14),
-            Difficulty::Hard => state.board_setup(12, 12, 35),
-        }
-
-        let number_of_tiles = state.get_height() * state.get_width();
+impl FromStr for Shape {
+    type Err = ParseError;
 
-        for _tile in 0..number_of_tiles {
-            state.add_tile(Tile::Hidden {
-                has_mine: false,
-                flagged: false,
-            });
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "rectangle" | "r" => Ok(Shape::Rectangle),
+            "diamond" | "d" => Ok(Shape::Diamond),
+            "cross" | "c" => Ok(Shape::Cross),
+            "donut" | "o" => Ok(Shape::Donut),
+            _ => Err(ParseError::new("shape", s)),
         }
-
-        place_mines(state);
     }
+}
 
-    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
-    fn find_neighbors(state: &GameState, index: usize) -> Vec<usize> {
-        let width = state.get_width() as isize;
-        let height = state.get_height() as isize;
+// What it takes to win a game
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WinCondition {
+    #[default]
+    RevealSafe,
+    // Flags on every mine and nowhere else. Mines revealed on a spare life count as found.
+    FlagMines,
+    Either,
+}
 
-        let index_x = index as isize % width;
-        let index_y = index as isize / width;
+impl FromStr for WinCondition {
+    type Err = ParseError;
 
-        let mut neighbors = Vec::new();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "reveal" | "r" => Ok(WinCondition::RevealSafe),
+            "flag" | "f" => Ok(WinCondition::FlagMines),
+            "either" | "e" => Ok(WinCondition::Either),
+            _ => Err(ParseError::new("win condition", s)),
+        }
+    }
+}
 
-        for row_offset in -1..=1 {
-            for col_offset in -1..=1 {
-                if row_offset == 0 && col_offset == 0 {
-                    continue;
-                }
+// How many terminal columns a string takes up. Emoji and East Asian wide characters take two,
+// and variation selectors take none. Close enough for the symbols a theme is likely to use.
+#[must_use]
+pub fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match u32::from(c) {
+            0xFE00..=0xFE0F | 0x200D => 0,
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F680..=0x1F6FF
+            | 0x1F900..=0x1FAFF => 2,
+            _ => 1,
+        })
+        .sum()
+}
 
-                let neighbor_x = index_x + col_offset;
-                let neighbor_y = index_y + row_offset;
+// Pad a symbol out to a three column tile, matching the original " X " layout
+fn tile_cell(symbol: &str) -> String {
+    let mut cell = format!(" {symbol}");
+    let width = display_width(&cell);
 
-                if neighbor_x >= 0 && neighbor_x < width && neighbor_y >= 0 && neighbor_y < height {
-                    let neighbor_index = (neighbor_y * width + neighbor_x) as usize;
-                    neighbors.push(neighbor_index);
-                }
-            }
+    cell.extend(std::iter::repeat_n(' ', 3_usize.saturating_sub(width)));
+    cell
+}
+
+// Provide type checked names to capture the state of our tiles.
+// Serialized as a flat object tagged by state, like
+// {"state":"hidden","has_mine":true,"flagged":false,"questioned":false}, so saved boards stay
+// readable. A question mark is only a note to the player; it never counts as a flag.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "state", rename_all = "lowercase"))]
+pub enum Tile {
+    Hidden {
+        has_mine: bool,
+        flagged: bool,
+        #[cfg_attr(feature = "serde", serde(default))]
+        questioned: bool,
+    },
+    Revealed {
+        has_mine: bool,
+        hint: u32,
+    },
+    // Outside the board's shape: never mined, never played, and drawn as a gap
+    Inactive,
+}
+
+impl Tile {
+    #[must_use]
+    pub fn has_mine(&self) -> bool {
+        match self {
+            Tile::Hidden { has_mine, .. } | Tile::Revealed { has_mine, .. } => *has_mine,
+            Tile::Inactive => false,
         }
+    }
 
-        neighbors
+    // Whether the tile is part of the board's shape, and so can be played
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        !matches!(self, Tile::Inactive)
     }
 
-    fn calculate_hint(state: &GameState, index: usize) -> u32 {
-        let neighbors = find_neighbors(state, index);
+    // The tile as it was at the start of the game: hidden and unmarked, over the same mine
+    #[must_use]
+    pub fn covered(&self) -> Tile {
+        match self {
+            Tile::Inactive => Tile::Inactive,
+            tile => Tile::Hidden {
+                has_mine: tile.has_mine(),
+                flagged: false,
+                questioned: false,
+            },
+        }
+    }
 
-        let mut count = 0;
+    // The neighboring mine count shown on a revealed safe tile.
+    // Revealed mines store a placeholder hint of 10, which this keeps out of sight.
+    #[must_use]
+    pub fn hint(&self) -> Option<u32> {
+        match *self {
+            Tile::Revealed {
+                has_mine: false,
+                hint,
+            } => Some(hint),
+            Tile::Revealed { has_mine: true, .. } | Tile::Hidden { .. } | Tile::Inactive => None,
+        }
+    }
+}
 
-        for &neighbor_index in &neighbors {
-            if let Tile::Hidden {
-                has_mine: true,
-                flagged: _,
-            } = state.get_tile(neighbor_index)
-            {
-                count += 1;
-            }
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tile::Hidden {
+                has_mine: _,
+                flagged: true,
+                ..
+            } => f.pad(" F "),
+            Tile::Hidden {
+                questioned: true, ..
+            } => f.pad(" ? "),
+            Tile::Revealed { .. } => match self.hint() {
+                Some(x) => f.pad(&format!(" {x} ")),
+                None => f.pad(" X "),
+            },
+            Tile::Hidden { .. } => f.pad(" - "),
+            Tile::Inactive => f.pad("   "),
         }
+    }
+}
 
-        count
+// A place on the board, counted from zero at the top left
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Coord {
+    pub row: u32,
+    pub col: u32,
+}
+
+impl Coord {
+    #[must_use]
+    pub fn new(row: u32, col: u32) -> Coord {
+        Coord { row, col }
     }
 
-    fn process_input(state: &mut GameState) {
-        loop {
-            println!("Select a hidden tile\n");
+    // Where a tile index falls on a board `width` tiles across
+    #[allow(clippy::cast_possible_truncation)] // Tile indices come from u32 board sizes
+    #[must_use]
+    pub fn from_index(index: usize, width: u32) -> Coord {
+        let index = index as u32;
 
-            let (column, row) = input_handler::read_column_row(
-                "Enter column and row: ",
-                1,
-                state.get_width(),
-                state.get_height(),
-            );
+        Coord {
+            row: index / width,
+            col: index % width,
+        }
+    }
 
-            let input_mode =
-                input_handler::read_input_mode("(C)lear, (F)lag, or (U)ndo selection? ");
+    // The tile index of this place on a board `width` tiles across.
+    // Doesn't check the board is big enough; `Board::index_of` does.
+    #[must_use]
+    pub fn to_index(self, width: u32) -> usize {
+        (self.row * width + self.col) as usize
+    }
 
-            if input_mode == InputMode::Undo {
-                continue;
-            }
+    // On a triangle board, whether the tile here points up. The top left one does, and they
+    // alternate along each row and down each column.
+    #[must_use]
+    pub fn points_up(self) -> bool {
+        (self.row + self.col).is_multiple_of(2)
+    }
+}
 
-            state.set_input_mode(input_mode);
-            state.set_selected((row * state.get_width()) + column);
+// A tile picked for the next move. Only handed out for places on the board, so the index it
+// holds is always safe to use on that board.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Selection {
+    index: usize,
+    coord: Coord,
+}
 
-            let index = state.get_selected();
+impl Selection {
+    #[must_use]
+    pub fn index(self) -> usize {
+        self.index
+    }
 
-            let stored_hint = calculate_hint(state, index);
+    #[must_use]
+    pub fn coord(self) -> Coord {
+        self.coord
+    }
+}
 
-            let mine_count = state.get_mine_count();
-            let max_mines = state.get_mines();
+// The tiles of a board, stored row by row
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Board {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+}
 
-            match state.get_tile(index) {
-                Tile::Hidden {
-                    has_mine: x,
-                    flagged: false,
-                } => {
-                    if input_mode == InputMode::Flag {
-                        state.set_tile(
-                            index,
-                            Tile::Hidden {
-                                has_mine: *x,
-                                flagged: true,
-                            },
-                        );
+impl Board {
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
 
-                        if mine_count > 0 {
-                            state.decrement_mine_count();
-                        }
-                    } else {
-                        state.set_tile(
-                            index,
-                            Tile::Revealed {
-                                has_mine: *x,
-                                hint: if *x { 10 } else { stored_hint },
-                            },
-                        );
-                    }
-                    break;
-                }
-                Tile::Hidden {
-                    has_mine: y,
-                    flagged: true,
-                } => {
-                    if input_mode == InputMode::Flag {
-                        state.set_tile(
-                            index,
-                            Tile::Hidden {
-                                has_mine: *y,
-                                flagged: false,
-                            },
-                        );
-                        if mine_count < max_mines {
-                            state.increment_mine_count();
-                        }
-                        break;
-                    }
-                    let clear_anyways =
-                        input_handler::read_as_bool("Tile is flagged, clear anyways? (Y/n): ");
-                    if clear_anyways {
-                        state.set_tile(
-                            index,
-                            Tile::Revealed {
-                                has_mine: *y,
-                                hint: if *y { 10 } else { stored_hint },
-                            },
-                        );
-                        break;
-                    }
-                    continue;
-                }
-                _ => {
-                    println!("Selected tile must be hidden.");
-                    continue;
-                }
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[must_use]
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    #[must_use]
+    pub fn contains(&self, coord: Coord) -> bool {
+        coord.row < self.height && coord.col < self.width
+    }
+
+    // The tile index for a place, if it's on the board
+    #[must_use]
+    pub fn index_of(&self, coord: Coord) -> Option<usize> {
+        self.contains(coord).then(|| coord.to_index(self.width))
+    }
+
+    // The place a tile index refers to, if it's on the board
+    #[must_use]
+    pub fn coord_of(&self, index: usize) -> Option<Coord> {
+        (index < self.tiles.len()).then(|| Coord::from_index(index, self.width))
+    }
+
+    #[must_use]
+    pub fn get(&self, coord: Coord) -> Option<&Tile> {
+        self.tiles.get(self.index_of(coord)?)
+    }
+
+    // A selection of the tile at a place, if it's on the board
+    #[must_use]
+    pub fn select(&self, coord: Coord) -> Option<Selection> {
+        let index = self.index_of(coord)?;
+        self.tiles.get(index)?;
+
+        Some(Selection { index, coord })
+    }
+
+    // Replace the tile at a place. Returns false if it's off the board.
+    pub fn set(&mut self, coord: Coord, tile: Tile) -> bool {
+        match self
+            .index_of(coord)
+            .and_then(|index| self.tiles.get_mut(index))
+        {
+            Some(slot) => {
+                *slot = tile;
+                true
             }
+            None => false,
         }
     }
+}
 
-    fn reveal_neighbors(state: &mut GameState, index: usize) {
-        let neighbors = find_neighbors(state, index);
+// Widest board offered, keeping column labels to two letters (A to Z, then AA to AZ)
+pub const MAX_WIDTH: u32 = 52;
 
-        for neighbor_index in neighbors {
-            let tile = state.get_tile(neighbor_index);
+// Tallest board offered for a custom game, keeping row labels to two digits
+pub const MAX_HEIGHT: u32 = 99;
 
-            if let Tile::Hidden {
-                has_mine: false,
-                flagged: false,
-            } = tile
-            {
-                let hint = calculate_hint(state, neighbor_index);
+// Most lives a game can start with, before mines stop being much of a threat
+pub const MAX_LIVES: u32 = 9;
 
-                state.set_tile(
-                    neighbor_index,
-                    Tile::Revealed {
-                        has_mine: false,
-                        hint,
-                    },
-                );
+// Densest board asked for by percentage, past which the numbers say little
+pub const MAX_DENSITY: u32 = 90;
 
-                if hint == 0 {
-                    reveal_neighbors(state, neighbor_index);
-                }
-            }
+// Most mines a single tile can hold in the multi-mine variant
+pub const MAX_MINES_PER_TILE: u32 = 3;
+
+// Columns are lettered like a spreadsheet's: A to Z, then AA, AB, and so on
+fn column_label(col: u32) -> String {
+    let mut label = String::new();
+    let mut rest = u64::from(col) + 1;
+
+    while rest > 0 {
+        rest -= 1;
+        #[allow(clippy::cast_possible_truncation)] // Always below 26
+        label.insert(0, char::from(b'A' + (rest % 26) as u8));
+        rest /= 26;
+    }
+
+    label
+}
+
+// Render the board as a labeled grid, one row per line
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_window(
+            f,
+            0..self.board.width,
+            0..self.board.height,
+            self.grid_style(),
+        )
+    }
+}
+
+// How the grid is laid out in the terminal
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GridStyle {
+    // Three columns per tile, space padded
+    Plain,
+    // Each tile boxed in with box drawing borders
+    Boxed,
+    // A single character per tile, for fitting big boards on small terminals
+    Compact,
+    // Tiles between slanted edges, for triangle boards
+    Triangle,
+}
+
+// Terminal columns and lines a grid of this size takes up, labels included
+#[must_use]
+pub fn grid_size(width: u32, height: u32, style: GridStyle) -> (u32, u32) {
+    match style {
+        GridStyle::Plain => (5 + 3 * width, 1 + height),
+        // Each tile also takes a border column and a separator line
+        GridStyle::Boxed => (6 + 4 * width, 2 + 2 * height),
+        // Columns past Z get a second line of labels
+        GridStyle::Compact => (4 + width, 1 + u32::from(width > 26) + height),
+        // Each tile also takes the edge to its left, plus one closing off the row
+        GridStyle::Triangle => (6 + 4 * width, 1 + height),
+    }
+}
+
+// A horizontal border line across `count` cells
+fn write_border(
+    f: &mut impl fmt::Write,
+    count: usize,
+    [left, middle, right]: [char; 3],
+) -> fmt::Result {
+    write!(f, "     {left}")?;
+    for position in 0..count {
+        if position > 0 {
+            write!(f, "{middle}")?;
+        }
+        write!(f, "───")?;
+    }
+    writeln!(f, "{right}")
+}
+
+// The slanted edge on one side of a triangle tile: `/` then `\\` around one pointing up
+fn triangle_edge(coord: Coord, left: bool) -> char {
+    if coord.points_up() == left {
+        '/'
+    } else {
+        '\\'
+    }
+}
+
+impl GameState {
+    // The layout the grid is drawn in, by the board's own options
+    #[must_use]
+    pub fn grid_style(&self) -> GridStyle {
+        if self.compact {
+            GridStyle::Compact
+        } else if self.adjacency == Adjacency::Triangle {
+            GridStyle::Triangle
+        } else if self.unicode_grid {
+            GridStyle::Boxed
+        } else {
+            GridStyle::Plain
         }
     }
 
-    fn check_for_win(state: &mut GameState) {
-        let mut winner = true;
+    // Just the given columns and rows of the grid, labeled with their place on the whole board
+    #[must_use]
+    pub fn render_window(&self, columns: Range<u32>, rows: Range<u32>, style: GridStyle) -> String {
+        let mut window = String::new();
+        // Writing to a String can't fail
+        let _ = self.write_window(&mut window, columns, rows, style);
+        window
+    }
 
-        for tile in &state.tiles {
-            match tile {
-                Tile::Revealed {
-                    has_mine: false,
-                    hint: _,
+    fn write_window(
+        &self,
+        f: &mut impl fmt::Write,
+        columns: Range<u32>,
+        rows: Range<u32>,
+        style: GridStyle,
+    ) -> fmt::Result {
+        if style == GridStyle::Compact {
+            return self.write_compact_window(f, columns, rows);
+        }
+
+        let boxed = style == GridStyle::Boxed;
+        let triangles = style == GridStyle::Triangle;
+
+        // Print the column letters, over the middle of each cell
+        write!(f, "     ")?;
+        for col in columns.clone() {
+            if boxed || triangles {
+                write!(f, " {}", tile_cell(&column_label(col)))?;
+            } else {
+                write!(f, "{:3}", column_label(col))?;
+            }
+        }
+        writeln!(f)?;
+
+        // Hidden tiles show their odds of being a mine when the assist overlay is on
+        let probabilities = if self.show_probabilities && !self.game_over {
+            self.mine_probabilities()
+        } else {
+            Vec::new()
+        };
+
+        let count = columns.len();
+
+        if boxed {
+            write_border(f, count, ['┌', '┬', '┐'])?;
+        }
+
+        for row in rows.clone() {
+            if boxed && row > rows.start {
+                write_border(f, count, ['├', '┼', '┤'])?;
+            }
+
+            write!(f, "{:4}", row + 1)?; // Print the row number
+            if boxed || triangles {
+                write!(f, " ")?;
+            }
+
+            for col in columns.clone() {
+                let index = row * self.board.width + col;
+
+                if boxed {
+                    write!(f, "│")?;
                 }
-                | Tile::Hidden {
-                    has_mine: true,
-                    flagged: _,
-                } => continue,
-                _ => {
-                    winner = false;
-                    break;
+                if triangles {
+                    write!(f, "{}", triangle_edge(Coord::new(row, col), true))?;
+                }
+
+                // Cells are already padded by display width, which `{:3}` can't account for
+                match probabilities.get(index as usize) {
+                    // Practice mines are on show, so they don't need odds
+                    Some(Some(chance)) if !self.is_practice_mine(index as usize) => {
+                        write!(f, "{}", solver::format_probability(*chance))?;
+                    }
+                    _ => write!(f, "{}", self.represent_tile(index))?,
                 }
             }
+
+            if boxed {
+                write!(f, "│")?;
+            }
+            // Rows start on alternate edges, which staggers the triangles from one row to the next
+            if triangles && !columns.is_empty() {
+                write!(
+                    f,
+                    "{}",
+                    triangle_edge(Coord::new(row, columns.end - 1), false)
+                )?;
+            }
+            writeln!(f)?;
         }
-        if winner {
-            state.set_won(winner);
+
+        if boxed {
+            write_border(f, count, ['└', '┴', '┘'])?;
         }
+
+        Ok(())
     }
 
-    fn update(state: &mut GameState) {
-        state.increment_turn_count();
+    // One glyph per tile with no padding. Themes and the probability overlay need more room
+    // than that, so this has fixed symbols of its own.
+    fn write_compact_window(
+        &self,
+        f: &mut impl fmt::Write,
+        columns: Range<u32>,
+        rows: Range<u32>,
+    ) -> fmt::Result {
+        // Labels past Z are stacked a letter to a line, lined up on their last letter
+        let depth = columns.clone().map(|col| column_label(col).len()).max();
+        for line in 0..depth.unwrap_or(1) {
+            write!(f, "    ")?;
+            for col in columns.clone() {
+                let label = column_label(col);
+                let letter = (line + label.len())
+                    .checked_sub(depth.unwrap_or(1))
+                    .and_then(|at| label.chars().nth(at));
+                write!(f, "{}", letter.unwrap_or(' '))?;
+            }
+            writeln!(f)?;
+        }
 
-        let index = state.get_selected();
-        let stored_hint = calculate_hint(state, index);
+        let visible = columns.start as usize..columns.end as usize;
 
-        if let Tile::Revealed {
-            has_mine: true,
-            hint: _,
-        } = state.get_tile(index)
+        for (number, row) in self
+            .rows()
+            .enumerate()
+            .take(rows.end as usize)
+            .skip(rows.start as usize)
         {
-            state.set_game_over(true);
+            write!(f, "{:3} ", number + 1)?;
 
-            state.tiles.iter_mut().for_each(|tile| {
-                if let Tile::Hidden {
-                    has_mine: true,
-                    flagged: _,
-                } = tile
-                {
-                    *tile = Tile::Revealed {
+            for tile in &row[visible.clone()] {
+                match tile {
+                    Tile::Hidden {
                         has_mine: true,
-                        hint: 10,
-                    };
+                        flagged: false,
+                        questioned: false,
+                    } if self.practice => write!(f, "{}", '*'.dim())?,
+                    _ => write!(f, "{}", self.compact_glyph(*tile))?,
                 }
-            });
-        } else if state.get_input_mode() == InputMode::Clear && stored_hint == 0 {
-            reveal_neighbors(state, index);
+            }
+            writeln!(f)?;
         }
 
-        check_for_win(state);
+        Ok(())
     }
 
-    fn clear_screen() {
-        let mut stdout = stdout();
+    fn compact_glyph(&self, tile: Tile) -> char {
+        match tile {
+            Tile::Hidden {
+                has_mine: false,
+                flagged: true,
+                ..
+            } if self.game_over => '✗',
+            Tile::Hidden { flagged: true, .. } => '⚑',
+            Tile::Hidden {
+                questioned: true, ..
+            } => '?',
+            Tile::Hidden { .. } => '·',
+            Tile::Inactive => ' ',
+            Tile::Revealed { .. } => match tile.hint() {
+                None => '*',
+                Some(0) => ' ',
+                // Numbers past 9 only come up with stacked mines
+                Some(hint) => char::from_digit(hint, 36).unwrap_or('?'),
+            },
+        }
+    }
+}
 
-        execute!(stdout, terminal::Clear(terminal::ClearType::All))
-            .expect("Failed to clear screen");
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameState {
+    #[must_use]
+    pub fn new() -> GameState {
+        GameState {
+            game_mode: GameMode::Play,
+            game_over: false,
+            game_won: false,
+            board: Board::default(),
+            starting_mines: 0,
+            mine_count: 0,
+            turn_count: 0,
+            selected_tile: None,
+            input_mode: InputMode::Cancel,
+            seed: None,
+            lives: 1,
+            starting_lives: 1,
+            show_probabilities: false,
+            theme: Theme::classic(),
+            moves: Vec::new(),
+            move_times: Vec::new(),
+            neighbors: OnceCell::new(),
+            hints: Vec::new(),
+            elapsed: Duration::ZERO,
+            wrap: false,
+            adjacency: Adjacency::Eight,
+            shape: Shape::Rectangle,
+            confirm_risky_clears: false,
+            first_click_zero: false,
+            first_click_safe: true,
+            no_guess: false,
+            practice: false,
+            mines_per_tile: 1,
+            mine_counts: Vec::new(),
+            flag_counts: Vec::new(),
+            unicode_grid: false,
+            compact: false,
+            auto_flag: false,
+            flagging_allowed: true,
+            flag_limit: None,
+            question_marks: false,
+            win_condition: WinCondition::RevealSafe,
+            flag_operations: 0,
+            hints_used: 0,
+            clicks: 0,
+            time_limit: None,
+            timed_out: false,
+            daily: false,
+            on_event: EventHook::default(),
+            history: VecDeque::new(),
+            redo_stack: Vec::new(),
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            undo_limit: None,
+            undos_used: 0,
+        }
     }
 
-    #[allow(clippy::cast_possible_truncation)] // Our column number will never go above u8.
-                                               // May refactor to be u8 by default?
-    fn column_to_letter(col: u32) -> char {
-        ((col as u8) + b'A') as char
+    #[must_use]
+    pub fn get_game_over(&self) -> bool {
+        self.game_over
     }
 
-    fn draw(state: &mut GameState) {
-        clear_screen();
+    #[must_use]
+    pub fn get_height(&self) -> u32 {
+        self.board.height
+    }
 
-        let mine_count = state.get_mine_count();
-        let turn_count = state.get_turn_count() + 1;
+    #[must_use]
+    pub fn get_width(&self) -> u32 {
+        self.board.width
+    }
 
-        println!("Turns: {turn_count}\nMines: {mine_count}\n");
-        // Print the column numbers
-        print!("     ");
-        for col in 0..state.get_width() {
-            print!("{:3}", column_to_letter(col));
-        }
-        println!();
+    #[must_use]
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `index` is off the board; `tile_at` and `try_get_tile` check first.
+    #[must_use]
+    pub fn get_tile(&self, index: usize) -> &Tile {
+        &self.board.tiles[index]
+    }
 
-        for row in 0..state.get_height() {
-            print!("{:4}", row + 1); // Print the row number
+    // Like `get_tile`, or None for an index off the board
+    #[must_use]
+    pub fn tile_at(&self, index: usize) -> Option<&Tile> {
+        self.board.tiles.get(index)
+    }
 
-            for col in 0..state.get_width() {
-                let index = row * state.get_width() + col;
-                let tile_representation = state.represent_tile(index);
-                print!("{tile_representation:3}");
-            }
+    /// Like `tile_at`, with an error saying how far off the board `index` is.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::OutOfBounds` if `index` is off the board.
+    pub fn try_get_tile(&self, index: usize) -> Result<&Tile, MinesweeperError> {
+        self.tile_at(index).ok_or(MinesweeperError::OutOfBounds {
+            index,
+            tiles: self.board.tiles.len(),
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::NoSelection` if no tile has been selected yet.
+    pub fn get_selected(&self) -> Result<usize, MinesweeperError> {
+        self.selected_tile
+            .map(Selection::index)
+            .ok_or(MinesweeperError::NoSelection)
+    }
+
+    #[must_use]
+    pub fn get_selection(&self) -> Option<Selection> {
+        self.selected_tile
+    }
+
+    #[must_use]
+    pub fn get_input_mode(&self) -> InputMode {
+        self.input_mode
+    }
+
+    #[must_use]
+    pub fn get_won(&self) -> bool {
+        self.game_won
+    }
+
+    #[must_use]
+    pub fn get_mines(&self) -> u32 {
+        self.starting_mines
+    }
+
+    #[must_use]
+    pub fn get_game_mode(&self) -> GameMode {
+        self.game_mode
+    }
+
+    #[must_use]
+    pub fn get_mine_count(&self) -> u32 {
+        self.mine_count
+    }
+
+    #[must_use]
+    pub fn get_turn_count(&self) -> u32 {
+        self.turn_count
+    }
+
+    #[must_use]
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    #[must_use]
+    pub fn get_lives(&self) -> u32 {
+        self.lives
+    }
+
+    // Time spent playing so far, not counting time spent paused
+    #[must_use]
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    // Whether the board wraps around, so opposite edges are adjacent. Triangles only wrap where
+    // they meet the right way up across the seam, so otherwise the board keeps its edges.
+    #[must_use]
+    pub fn get_wrap(&self) -> bool {
+        self.wrap && engine::wraps_evenly(self.board.width, self.board.height, self.adjacency)
+    }
+
+    // Which tiles count as neighbors, for hints and flood fills alike
+    #[must_use]
+    pub fn get_adjacency(&self) -> Adjacency {
+        self.adjacency
+    }
+
+    // The shape the board was dealt in. Set when dealing, since it decides where mines can go.
+    #[must_use]
+    pub fn get_shape(&self) -> Shape {
+        self.shape
+    }
+
+    // Whether clears that aren't provably safe ask for confirmation first
+    #[must_use]
+    pub fn get_confirm_risky_clears(&self) -> bool {
+        self.confirm_risky_clears
+    }
+
+    // Whether the first clear moves mines away so it always opens up an area
+    #[must_use]
+    pub fn get_first_click_zero(&self) -> bool {
+        self.first_click_zero
+    }
+
+    // Whether a mine under the first clear moves away, as in standard minesweeper. On by default.
+    #[must_use]
+    pub fn get_first_click_safe(&self) -> bool {
+        self.first_click_safe
+    }
+
+    // Whether the first clear re-deals the mines until the board can be solved without guessing
+    #[must_use]
+    pub fn get_no_guess(&self) -> bool {
+        self.no_guess
+    }
+
+    // Whether this is a practice game, with its mines drawn dimly and left out of stats and
+    // leaderboards
+    #[must_use]
+    pub fn get_practice(&self) -> bool {
+        self.practice
+    }
+
+    #[must_use]
+    pub fn get_mines_per_tile(&self) -> u32 {
+        self.mines_per_tile
+    }
+
+    // How many mines are on a tile: 0 or 1, except on multi-mine boards once they're dealt
+    #[must_use]
+    pub fn mines_at(&self, index: usize) -> u32 {
+        match self.mine_counts.get(index) {
+            Some(&count) => u32::from(count),
+            None => u32::from(self.board.tiles.get(index).is_some_and(Tile::has_mine)),
+        }
+    }
+
+    // How many mines the flag on a tile stands for, or 0 when it isn't flagged
+    #[must_use]
+    pub fn flags_at(&self, index: usize) -> u32 {
+        match self.board.tiles.get(index) {
+            Some(Tile::Hidden { flagged: true, .. }) => u32::from(
+                self.flag_counts
+                    .get(index)
+                    .copied()
+                    .unwrap_or_default()
+                    .max(1),
+            ),
+            _ => 0,
+        }
+    }
+
+    // Every mine on the board, which is more than the mined tiles once mines are stacked
+    #[must_use]
+    pub fn total_mines(&self) -> u32 {
+        (0..self.board.tiles.len())
+            .map(|index| self.mines_at(index))
+            .sum()
+    }
+
+    // How many moves back `undo` can go
+    #[must_use]
+    pub fn get_history_limit(&self) -> usize {
+        self.history_limit
+    }
+
+    #[must_use]
+    pub fn get_undo_limit(&self) -> Option<u32> {
+        self.undo_limit
+    }
+
+    #[must_use]
+    pub fn get_undos_used(&self) -> u32 {
+        self.undos_used
+    }
+
+    // Undos left this game, or None when they're unlimited
+    #[must_use]
+    pub fn undos_left(&self) -> Option<u32> {
+        self.undo_limit
+            .map(|limit| limit.saturating_sub(self.undos_used))
+    }
+
+    // Whether flags can be placed at all. Turned off for a no-flag challenge.
+    #[must_use]
+    pub fn get_flagging_allowed(&self) -> bool {
+        self.flagging_allowed
+    }
+
+    #[must_use]
+    pub fn get_flag_limit(&self) -> Option<u32> {
+        self.flag_limit
+    }
+
+    // Flags that can still be placed: one for each mine not yet flagged, or fewer when the
+    // game caps them
+    #[must_use]
+    pub fn flags_left(&self) -> u32 {
+        let Some(limit) = self.flag_limit else {
+            return self.mine_count;
+        };
+        let placed = self
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| matches!(tile, Tile::Hidden { flagged: true, .. }))
+            .count();
+
+        self.mine_count
+            .min(limit.saturating_sub(u32::try_from(placed).unwrap_or(u32::MAX)))
+    }
+
+    // Whether taking a flag away leaves a question mark, for one more press to clear
+    #[must_use]
+    pub fn get_question_marks(&self) -> bool {
+        self.question_marks
+    }
+
+    // How many times a flag has been placed or taken away this game
+    #[must_use]
+    pub fn get_flag_operations(&self) -> u32 {
+        self.flag_operations
+    }
+
+    // How many moves this game were made by asking for a hint
+    #[must_use]
+    pub fn get_hints_used(&self) -> u32 {
+        self.hints_used
+    }
+
+    // How many clears, chords, and safe reveals the player made this game. A chord is one click,
+    // however many tiles it opens, and undoing a move doesn't take its click back.
+    #[must_use]
+    pub fn get_clicks(&self) -> u32 {
+        self.clicks
+    }
+
+    // The time allowed to clear the board in time attack mode
+    #[must_use]
+    pub fn get_time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    // Whether the game was lost to the clock running out
+    #[must_use]
+    pub fn get_timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    // Whether this is the daily board, seeded from the date
+    #[must_use]
+    pub fn get_daily(&self) -> bool {
+        self.daily
+    }
+
+    // Whether tiles certain to be mines get flagged automatically after each clear
+    #[must_use]
+    pub fn get_auto_flag(&self) -> bool {
+        self.auto_flag
+    }
+
+    #[must_use]
+    pub fn get_win_condition(&self) -> WinCondition {
+        self.win_condition
+    }
+
+    // Whether the grid is drawn with a single character per tile
+    #[must_use]
+    pub fn get_compact(&self) -> bool {
+        self.compact
+    }
+
+    // Whether the grid is drawn with box drawing borders
+    #[must_use]
+    pub fn get_unicode_grid(&self) -> bool {
+        self.unicode_grid
+    }
+
+    #[must_use]
+    pub fn get_show_probabilities(&self) -> bool {
+        self.show_probabilities
+    }
+
+    #[must_use]
+    pub fn get_theme(&self) -> Theme {
+        self.theme
+    }
+
+    #[must_use]
+    pub fn get_moves(&self) -> &[(usize, InputMode)] {
+        &self.moves
+    }
+
+    // How far into the game each of `get_moves` was made
+    #[must_use]
+    pub fn get_move_times(&self) -> &[Duration] {
+        &self.move_times
+    }
+
+    #[must_use]
+    pub fn get_difficulty(&self) -> Difficulty {
+        Difficulty::from_dimensions(self.board.width, self.board.height, self.starting_mines)
+    }
+
+    // Call `callback` with every reveal, flag, win, and loss from now on, in the order they happen
+    pub fn set_event_callback(&mut self, callback: impl FnMut(&GameEvent) + 'static) {
+        self.on_event = EventHook(Some(Box::new(callback)));
+    }
+
+    pub fn clear_event_callback(&mut self) {
+        self.on_event = EventHook(None);
+    }
+
+    pub(crate) fn emit(&mut self, event: GameEvent) {
+        if let Some(callback) = &mut self.on_event.0 {
+            callback(&event);
+        }
+    }
+
+    pub fn set_game_over(&mut self, game_over: bool) {
+        self.game_over = game_over;
+    }
+
+    pub fn set_height(&mut self, height: u32) {
+        self.board.height = height;
+    }
+
+    pub fn set_width(&mut self, width: u32) {
+        self.board.width = width;
+    }
+
+    pub fn set_tile(&mut self, index: usize, tile_state: Tile) {
+        // Moving a mine makes the precomputed hints stale
+        if self.board.tiles[index].has_mine() != tile_state.has_mine() {
+            self.hints.clear();
+        }
+
+        self.board.tiles[index] = tile_state;
+    }
+
+    // An index off the board clears the selection rather than keeping one that can't be used
+    pub fn set_selected(&mut self, index: u32) {
+        self.selected_tile = self
+            .board
+            .coord_of(index as usize)
+            .and_then(|coord| self.board.select(coord));
+    }
+
+    // Select the tile at a place for the next move, handing back the selection.
+    // Leaves the current selection alone and returns None for a place off the board.
+    pub fn select(&mut self, coord: Coord) -> Option<Selection> {
+        let selection = self.board.select(coord)?;
+        self.selected_tile = Some(selection);
+
+        Some(selection)
+    }
+
+    pub fn set_input_mode(&mut self, input_mode: InputMode) {
+        self.input_mode = input_mode;
+    }
+
+    pub fn set_won(&mut self, game_won: bool) {
+        self.game_won = game_won;
+    }
+
+    pub fn set_mines(&mut self, num_mines: u32) {
+        self.starting_mines = num_mines;
+    }
+
+    pub fn set_game_mode(&mut self, game_mode: GameMode) {
+        self.game_mode = game_mode;
+    }
+
+    pub fn set_mine_count(&mut self, mine_count: u32) {
+        self.mine_count = mine_count;
+    }
+
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    // Also what a restart hands back
+    pub fn set_lives(&mut self, lives: u32) {
+        self.lives = lives;
+        self.starting_lives = lives;
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+
+        // Wrapping changes who neighbors who, and so every hint
+        self.neighbors = OnceCell::new();
+        self.hints.clear();
+    }
+
+    pub fn set_adjacency(&mut self, adjacency: Adjacency) {
+        self.adjacency = adjacency;
+
+        // As with wrapping, every hint depends on who counts as a neighbor
+        self.neighbors = OnceCell::new();
+        self.hints.clear();
+    }
+
+    pub fn set_confirm_risky_clears(&mut self, confirm_risky_clears: bool) {
+        self.confirm_risky_clears = confirm_risky_clears;
+    }
+
+    pub fn set_first_click_zero(&mut self, first_click_zero: bool) {
+        self.first_click_zero = first_click_zero;
+    }
+
+    pub fn set_first_click_safe(&mut self, first_click_safe: bool) {
+        self.first_click_safe = first_click_safe;
+    }
+
+    pub fn set_no_guess(&mut self, no_guess: bool) {
+        self.no_guess = no_guess;
+    }
+
+    pub fn set_practice(&mut self, practice: bool) {
+        self.practice = practice;
+    }
+
+    pub fn set_mines_per_tile(&mut self, mines_per_tile: u32) {
+        self.mines_per_tile = mines_per_tile.clamp(1, MAX_MINES_PER_TILE);
+    }
+
+    pub fn set_undo_limit(&mut self, undo_limit: Option<u32>) {
+        self.undo_limit = undo_limit;
+    }
+
+    // Lowering the limit forgets the oldest moves beyond it
+    pub fn set_history_limit(&mut self, history_limit: usize) {
+        self.history_limit = history_limit;
+
+        while self.history.len() > history_limit {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn set_flagging_allowed(&mut self, flagging_allowed: bool) {
+        self.flagging_allowed = flagging_allowed;
+    }
+
+    pub fn set_flag_limit(&mut self, flag_limit: Option<u32>) {
+        self.flag_limit = flag_limit;
+    }
+
+    pub fn set_question_marks(&mut self, question_marks: bool) {
+        self.question_marks = question_marks;
+    }
+
+    pub fn set_time_limit(&mut self, time_limit: Option<Duration>) {
+        self.time_limit = time_limit;
+    }
+
+    pub fn set_daily(&mut self, daily: bool) {
+        self.daily = daily;
+    }
+
+    pub fn set_auto_flag(&mut self, auto_flag: bool) {
+        self.auto_flag = auto_flag;
+    }
+
+    pub fn set_win_condition(&mut self, win_condition: WinCondition) {
+        self.win_condition = win_condition;
+    }
+
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+    }
+
+    pub fn set_unicode_grid(&mut self, unicode_grid: bool) {
+        self.unicode_grid = unicode_grid;
+    }
+
+    pub fn set_show_probabilities(&mut self, show_probabilities: bool) {
+        self.show_probabilities = show_probabilities;
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Check that a board of these dimensions can be played.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BoardError` for zero width or height, dimensions over [`MAX_WIDTH`] or
+    /// [`MAX_HEIGHT`], or a mine count that doesn't leave at least one safe tile.
+    pub fn validate_board(width: u32, height: u32, num_mines: u32) -> Result<(), BoardError> {
+        if width == 0 || height == 0 {
+            return Err(BoardError::ZeroDimension { width, height });
+        }
+
+        let too_large = BoardError::TooLarge { width, height };
+        if width > MAX_WIDTH || height > MAX_HEIGHT {
+            return Err(too_large);
+        }
+
+        let tiles = width.checked_mul(height).ok_or(too_large)?;
+
+        if num_mines >= tiles {
+            return Err(BoardError::TooManyMines {
+                mines: num_mines,
+                tiles,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Reset the board to the given dimensions, ready for tiles to be added.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BoardError`, leaving the state untouched, if the board can't be played.
+    pub fn board_setup(
+        &mut self,
+        width: u32,
+        height: u32,
+        num_mines: u32,
+    ) -> Result<(), BoardError> {
+        GameState::validate_board(width, height, num_mines)?;
+
+        // Neighbors only depend on the dimensions, so they're worked out once per board size
+        if width != self.board.width || height != self.board.height {
+            self.neighbors = OnceCell::new();
+        }
+
+        self.board.width = width;
+        self.board.height = height;
+        self.starting_mines = num_mines;
+        self.mine_count = num_mines;
+        self.turn_count = 0;
+        self.game_won = false;
+        self.game_over = false;
+        self.moves = Vec::new();
+        self.move_times = Vec::new();
+        self.elapsed = Duration::ZERO;
+        self.timed_out = false;
+        self.flag_operations = 0;
+        self.hints_used = 0;
+        self.clicks = 0;
+        self.undos_used = 0;
+        self.mine_counts.clear();
+        self.flag_counts.clear();
+        self.history.clear();
+        self.redo_stack.clear();
+        self.clear_tiles();
+
+        Ok(())
+    }
+
+    pub fn add_tile(&mut self, tile_state: Tile) {
+        self.board.tiles.push(tile_state);
+        self.hints.clear();
+    }
+
+    // The hint a tile shows once revealed, with 10 marking a mine.
+    // Read from the table built after mines are placed, or worked out on the spot without one.
+    #[must_use]
+    pub fn get_hint(&self, index: usize) -> u32 {
+        if self.hints.len() == self.board.tiles.len() {
+            self.hints[index]
+        } else {
+            engine::tile_hint(self, index)
+        }
+    }
+
+    /// The tiles touching `index`, following the board's wrap and adjacency options.
+    /// Empty for an index off the board.
+    ///
+    /// ```
+    /// use minesweeper::{engine, Difficulty};
+    ///
+    /// let state = engine::new_game(&Difficulty::Easy, Some(1)).unwrap();
+    ///
+    /// // A corner of the 5x5 board
+    /// assert_eq!(3, state.neighbors(0).len());
+    /// assert_eq!(8, state.neighbor_tiles(12).count());
+    /// ```
+    #[must_use]
+    pub fn neighbors(&self, index: usize) -> &[usize] {
+        self.neighbor_table().get(index).map_or(&[], Vec::as_slice)
+    }
+
+    // As `neighbors`, with each neighbor's place and tile
+    pub fn neighbor_tiles(&self, index: usize) -> impl Iterator<Item = (Coord, &Tile)> {
+        let width = self.board.width;
+
+        self.neighbors(index).iter().map(move |&neighbor| {
+            (
+                Coord::from_index(neighbor, width),
+                &self.board.tiles[neighbor],
+            )
+        })
+    }
+
+    // As `iter_tiles`, with each tile's place as a `Coord`
+    pub fn tiles(&self) -> impl Iterator<Item = (Coord, &Tile)> {
+        let width = self.board.width.max(1);
+
+        self.board
+            .tiles
+            .iter()
+            .enumerate()
+            .map(move |(index, tile)| (Coord::from_index(index, width), tile))
+    }
+
+    // The board a row at a time, from the top
+    pub fn rows(&self) -> impl Iterator<Item = &[Tile]> {
+        self.board.tiles.chunks(self.board.width.max(1) as usize)
+    }
+
+    // Every tile's neighbors, indexed by tile
+    pub(crate) fn neighbor_table(&self) -> &Rc<[Vec<usize>]> {
+        self.neighbors.get_or_init(|| {
+            engine::neighbor_table(
+                self.board.width,
+                self.board.height,
+                self.get_wrap(),
+                self.adjacency,
+            )
+        })
+    }
+
+    /// Walk the board row by row, yielding each tile with its column and row.
+    ///
+    /// ```
+    /// use minesweeper::{engine, Difficulty, Tile};
+    ///
+    /// let mut state = engine::new_game(&Difficulty::Easy, Some(1)).unwrap();
+    /// let safe = state.iter_tiles().position(|(_, _, tile)| !tile.has_mine()).unwrap();
+    /// state.reveal(safe);
+    ///
+    /// let revealed = state
+    ///     .iter_tiles()
+    ///     .filter(|(_, _, tile)| matches!(tile, Tile::Revealed { .. }))
+    ///     .count();
+    ///
+    /// assert!(revealed >= 1);
+    /// ```
+    pub fn iter_tiles(&self) -> impl Iterator<Item = (u32, u32, &Tile)> {
+        self.tiles()
+            .map(|(coord, tile)| (coord.col, coord.row, tile))
+    }
+
+    // As `iter_tiles`, for changing tiles in bulk.
+    // Tiles may gain or lose mines along the way, so the stored hints are worked out afresh.
+    pub fn iter_tiles_mut(&mut self) -> impl Iterator<Item = (u32, u32, &mut Tile)> {
+        let width = self.board.width.max(1) as usize;
+        self.hints.clear();
+
+        self.board
+            .tiles
+            .iter_mut()
+            .enumerate()
+            .map(move |(index, tile)| {
+                #[allow(clippy::cast_possible_truncation)] // Boards are indexed by u32 coordinates
+                let (column, row) = ((index % width) as u32, (index / width) as u32);
+                (column, row, tile)
+            })
+    }
+
+    // A three column cell for the tile, drawn with the current theme
+    #[must_use]
+    pub fn represent_tile(&self, index: u32) -> String {
+        let tile = index as usize;
+
+        match self.board.tiles.get(tile) {
+            // Once the game is lost, show which flags were guarded safe tiles
+            Some(Tile::Hidden {
+                has_mine: false,
+                flagged: true,
+                ..
+            }) if self.game_over => tile_cell(self.theme.wrong_flag),
+            // A flag standing for a stack shows how many mines it marks, as the stack itself does
+            Some(Tile::Hidden { flagged: true, .. }) if self.flags_at(tile) > 1 => {
+                tile_cell(&format!("{}{}", self.theme.flag, self.flags_at(tile)))
+            }
+            Some(Tile::Hidden {
+                has_mine: _,
+                flagged: true,
+                ..
+            }) => tile_cell(self.theme.flag),
+            Some(Tile::Hidden {
+                questioned: true, ..
+            })
+            | None => tile_cell(self.theme.question),
+            // Practice games show where the mines are, dimly enough to tell from revealed ones
+            _ if self.is_practice_mine(tile) => tile_cell(self.theme.mine).dim().to_string(),
+            Some(Tile::Hidden { .. }) => tile_cell(self.theme.hidden),
+            Some(Tile::Inactive) => tile_cell(""),
+            Some(revealed @ Tile::Revealed { .. }) => match revealed.hint() {
+                // Stacked mines show how many there were
+                None if self.mines_at(tile) > 1 => {
+                    tile_cell(&format!("{}{}", self.theme.mine, self.mines_at(tile)))
+                }
+                None => tile_cell(self.theme.mine),
+                Some(0) => tile_cell(self.theme.empty),
+                Some(hint) => tile_cell(&hint.to_string()),
+            },
+        }
+    }
+
+    // A hidden mine with nothing on it, which practice games draw dimly
+    fn is_practice_mine(&self, index: usize) -> bool {
+        self.practice
+            && matches!(
+                self.board.tiles.get(index),
+                Some(Tile::Hidden {
+                    has_mine: true,
+                    flagged: false,
+                    questioned: false,
+                })
+            )
+    }
+
+    // Human readable encoding of the board for pasting into an issue.
+    // The header holds `WIDTHxHEIGHT MINES SEED` (`-` for no seed), followed by one line per row:
+    // `.` hidden, `*` hidden mine, `f` flag, `F` flagged mine, digits for hints, `X` revealed mine,
+    // `#` outside the board's shape.
+    #[must_use]
+    pub fn to_ascii(&self) -> String {
+        let seed = self
+            .seed
+            .map_or_else(|| String::from("-"), |seed| seed.to_string());
+
+        let mut ascii = format!(
+            "{}x{} {} {seed}\n",
+            self.board.width, self.board.height, self.starting_mines
+        );
+
+        for row in self.board.tiles.chunks(self.board.width.max(1) as usize) {
+            for tile in row {
+                ascii.push(match *tile {
+                    Tile::Hidden {
+                        has_mine: false,
+                        flagged: false,
+                        ..
+                    } => '.',
+                    Tile::Hidden {
+                        has_mine: true,
+                        flagged: false,
+                        ..
+                    } => '*',
+                    Tile::Hidden {
+                        has_mine: false,
+                        flagged: true,
+                        ..
+                    } => 'f',
+                    Tile::Hidden {
+                        has_mine: true,
+                        flagged: true,
+                        ..
+                    } => 'F',
+                    Tile::Revealed {
+                        has_mine: false,
+                        hint,
+                    } => char::from_digit(hint, 10).unwrap_or('?'),
+                    Tile::Revealed { has_mine: true, .. } => 'X',
+                    Tile::Inactive => '#',
+                });
+            }
+            ascii.push('\n');
+        }
+
+        ascii
+    }
+
+    /// Rebuild a board from the output of `to_ascii`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` for a malformed header, rows that don't match the header
+    /// dimensions, unknown tile characters, or a mine count that disagrees with the board.
+    pub fn from_ascii(ascii: &str) -> Result<GameState, ParseError> {
+        let mut lines = ascii.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        let header = lines
+            .next()
+            .ok_or_else(|| ParseError::new("board", ascii))?;
+        let bad_header = || ParseError::new("board header", header);
+
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let [size, mines, seed] = fields[..] else {
+            return Err(bad_header());
+        };
+        let (width, height) = size.split_once('x').ok_or_else(bad_header)?;
+        let width: u32 = width.parse().map_err(|_| bad_header())?;
+        let height: u32 = height.parse().map_err(|_| bad_header())?;
+        let mines: u32 = mines.parse().map_err(|_| bad_header())?;
+        let seed = match seed {
+            "-" => None,
+            seed => Some(seed.parse().map_err(|_| bad_header())?),
+        };
+
+        let mut state = GameState::new();
+        state
+            .board_setup(width, height, mines)
+            .map_err(|_| bad_header())?;
+        state.set_seed(seed);
+
+        let mut flags = 0;
+
+        for line in lines {
+            if line.chars().count() != width as usize {
+                return Err(ParseError::new("board row", line));
+            }
+
+            for symbol in line.chars() {
+                let tile = match symbol {
+                    '.' | '*' => Tile::Hidden {
+                        has_mine: symbol == '*',
+                        flagged: false,
+                        questioned: false,
+                    },
+                    'f' | 'F' => Tile::Hidden {
+                        has_mine: symbol == 'F',
+                        flagged: true,
+                        questioned: false,
+                    },
+                    'X' => Tile::Revealed {
+                        has_mine: true,
+                        hint: 10,
+                    },
+                    '#' => Tile::Inactive,
+                    digit => Tile::Revealed {
+                        has_mine: false,
+                        hint: digit
+                            .to_digit(10)
+                            .ok_or_else(|| ParseError::new("tile", &digit.to_string()))?,
+                    },
+                };
+
+                if let Tile::Hidden { flagged: true, .. } = tile {
+                    flags += 1;
+                }
+
+                state.add_tile(tile);
+            }
+        }
+
+        let placed = state
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| tile.has_mine())
+            .count();
+
+        if state.board.tiles.len() != (width * height) as usize {
+            return Err(ParseError::new("board", ascii));
+        }
+
+        if placed != mines as usize {
+            return Err(bad_header());
+        }
+
+        engine::compute_hints(&mut state);
+
+        state.set_mine_count(mines.saturating_sub(flags));
+
+        Ok(state)
+    }
+
+    // Clear a hidden tile, flagged or not, and resolve everything that follows from it.
+    // Returns false when there was nothing to clear.
+    pub fn reveal(&mut self, index: usize) -> bool {
+        if self.game_over || self.game_won {
+            return false;
+        }
+
+        if !matches!(self.board.tiles.get(index), Some(Tile::Hidden { .. })) {
+            return false;
+        }
+
+        // Moving mines for the first clear is part of the move, so undoing it puts them back
+        self.remember_move();
+
+        if !self.has_started() {
+            if self.first_click_zero {
+                engine::open_first_click(self, index, true);
+            } else if self.first_click_safe {
+                engine::open_first_click(self, index, false);
+            }
+            // The solver only knows about one mine a tile, so no-guess boards can't stack them
+            if self.no_guess && self.mines_per_tile == 1 {
+                engine::deal_no_guess(self, index);
+            }
+            if self.mines_per_tile > 1 && self.mine_counts.is_empty() {
+                engine::stack_mines(self, index);
+            }
+        }
+
+        let Tile::Hidden {
+            has_mine, flagged, ..
+        } = self.board.tiles[index]
+        else {
+            return false;
+        };
+
+        // Clearing a flagged tile hands its flag back
+        if flagged {
+            self.increment_mine_count();
+        }
+
+        self.board.tiles[index] = Tile::Revealed {
+            has_mine,
+            hint: self.get_hint(index),
+        };
+        self.emit(GameEvent::Revealed {
+            index,
+            tile: self.board.tiles[index],
+        });
+
+        self.input_mode = InputMode::Clear;
+        self.selected_tile = self
+            .board
+            .coord_of(index)
+            .and_then(|coord| self.board.select(coord));
+        self.record_move(index, InputMode::Clear);
+        engine::resolve(self, index);
+
+        true
+    }
+
+    // Place or remove a flag on a hidden tile. With question marks on, a removed flag leaves one
+    // behind, and the next toggle clears it.
+    // Returns false when the tile can't take a flag, no flags remain, or flagging is off.
+    pub fn toggle_flag(&mut self, index: usize) -> bool {
+        if self.game_over || self.game_won || !self.flagging_allowed {
+            return false;
+        }
+
+        let before = self.checkpoint();
+
+        match self.board.tiles.get(index) {
+            // On multi-mine boards, flagging a tile again marks one more mine on it
+            Some(Tile::Hidden { flagged: true, .. })
+                if self.flags_at(index) < self.mines_per_tile =>
+            {
+                let count = self.flags_at(index) + 1;
+                self.flag_counts.resize(self.board.tiles.len(), 0);
+                self.flag_counts[index] = u8::try_from(count).unwrap_or(u8::MAX);
+            }
+            Some(&Tile::Hidden {
+                has_mine,
+                flagged: true,
+                ..
+            }) => {
+                self.board.tiles[index] = Tile::Hidden {
+                    has_mine,
+                    flagged: false,
+                    questioned: self.question_marks,
+                };
+                self.increment_mine_count();
+            }
+            Some(&Tile::Hidden {
+                has_mine,
+                questioned: true,
+                ..
+            }) => {
+                self.board.tiles[index] = Tile::Hidden {
+                    has_mine,
+                    flagged: false,
+                    questioned: false,
+                };
+            }
+            Some(Tile::Hidden { flagged: false, .. }) => {
+                if !engine::place_flag(self, index) {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+
+        self.record_snapshot(before);
+        self.flag_operations += 1;
+        self.emit(GameEvent::Flagged {
+            index,
+            tile: self.board.tiles[index],
+        });
+
+        self.input_mode = InputMode::Flag;
+        self.selected_tile = self
+            .board
+            .coord_of(index)
+            .and_then(|coord| self.board.select(coord));
+        self.record_move(index, InputMode::Flag);
+        engine::resolve(self, index);
+
+        true
+    }
+
+    // An independent copy of the whole game, for trying moves out without touching this one.
+    // The neighbor table is shared, since it never changes once built.
+    #[must_use]
+    pub fn snapshot(&self) -> GameState {
+        self.clone()
+    }
+
+    fn checkpoint(&self) -> Snapshot {
+        Snapshot {
+            tiles: self.board.tiles.clone(),
+            mine_counts: self.mine_counts.clone(),
+            flag_counts: self.flag_counts.clone(),
+            mine_count: self.mine_count,
+            turn_count: self.turn_count,
+            lives: self.lives,
+            game_over: self.game_over,
+            game_won: self.game_won,
+            moves: self.moves.clone(),
+            move_times: self.move_times.clone(),
+        }
+    }
+
+    // Put the board back as it was in the snapshot, handing back how it is now
+    fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+        let current = self.checkpoint();
+
+        self.board.tiles = snapshot.tiles;
+        self.mine_counts = snapshot.mine_counts;
+        self.flag_counts = snapshot.flag_counts;
+        self.mine_count = snapshot.mine_count;
+        self.turn_count = snapshot.turn_count;
+        self.lives = snapshot.lives;
+        self.game_over = snapshot.game_over;
+        self.game_won = snapshot.game_won;
+        self.moves = snapshot.moves;
+        self.move_times = snapshot.move_times;
+
+        // Undoing the first clear can move mines back
+        engine::compute_hints(self);
+
+        current
+    }
+
+    // A new move makes anything undone unreachable, like in a text editor
+    fn record_snapshot(&mut self, snapshot: Snapshot) {
+        self.redo_stack.clear();
+
+        if self.history_limit == 0 {
+            return;
+        }
+
+        if self.history.len() == self.history_limit {
+            self.history.pop_front();
+        }
+        self.history.push_back(snapshot);
+    }
+
+    fn record_move(&mut self, index: usize, input_mode: InputMode) {
+        self.moves.push((index, input_mode));
+        self.move_times.push(self.elapsed);
+    }
+
+    // Each move as `mode index milliseconds`, the format `replay::parse_move` reads
+    fn write_moves(&self, log: &mut String) {
+        for (&(index, input_mode), time) in self.moves.iter().zip(&self.move_times) {
+            // Writing to a String can't fail
+            let _ = writeln!(log, "{input_mode} {index} {}", time.as_millis());
+        }
+    }
+
+    fn remember_move(&mut self) {
+        let snapshot = self.checkpoint();
+        self.record_snapshot(snapshot);
+    }
+
+    /// Whether an action is allowed right now. The move methods hold to the same rules on their
+    /// own; this lets a frontend explain a refusal instead of just seeing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `MoveError` saying why the action would be refused.
+    pub fn check_move(&self, action: Action) -> Result<(), MoveError> {
+        let coord = match action {
+            Action::Reveal(coord)
+            | Action::Flag(coord)
+            | Action::Chord(coord)
+            | Action::SafeReveal(coord) => coord,
+            Action::Hint if self.game_over || self.game_won => return Err(MoveError::GameOver),
+            Action::Hint if self.mines_per_tile > 1 => return Err(MoveError::NotWithStackedMines),
+            Action::Hint if solver::certain_move(self).is_none() => {
+                return Err(MoveError::NoCertainMove)
+            }
+            Action::Undo if self.undos_left() == Some(0) => return Err(MoveError::NoUndosLeft),
+            Action::Undo if !self.can_undo() => return Err(MoveError::NothingToUndo),
+            Action::Redo if !self.can_redo() => return Err(MoveError::NothingToRedo),
+            Action::Hint | Action::Undo | Action::Redo | Action::Restart => return Ok(()),
+        };
+
+        if self.game_over || self.game_won {
+            return Err(MoveError::GameOver);
+        }
+
+        let index = self.board.index_of(coord).ok_or(MoveError::OffBoard)?;
+
+        match (action, self.board.tiles[index]) {
+            // Tiles outside the board's shape aren't part of the game
+            (_, Tile::Inactive) => Err(MoveError::OffBoard),
+            (Action::Chord(_), _) if engine::chord_targets(self, index).is_none() => {
+                Err(MoveError::NotChordable)
+            }
+            (Action::SafeReveal(_), _) if engine::safe_neighbors(self, index).is_none() => {
+                Err(MoveError::NotChordable)
+            }
+            (Action::Chord(_) | Action::SafeReveal(_), _) => Ok(()),
+            (Action::Flag(_), _) if !self.flagging_allowed => Err(MoveError::FlaggingOff),
+            (_, Tile::Revealed { .. }) => Err(MoveError::NotHidden),
+            (
+                Action::Flag(_),
+                Tile::Hidden {
+                    flagged: false,
+                    questioned: false,
+                    ..
+                },
+            ) if self.flags_left() == 0 => Err(MoveError::NoFlagsLeft),
+            _ => Ok(()),
+        }
+    }
+
+    // Carry out an action, with auto-flagging after reveals when it's turned on
+    pub fn apply(&mut self, action: Action) -> ActionResult {
+        let before = self.board.tiles.clone();
+
+        let applied = match action {
+            Action::Reveal(coord) => self.board.index_of(coord).is_some_and(|index| {
+                let revealed = self.reveal(index);
+                if revealed && self.auto_flag {
+                    self.auto_flag_into_move();
+                }
+                revealed
+            }),
+            Action::Flag(coord) => self
+                .board
+                .index_of(coord)
+                .is_some_and(|index| self.toggle_flag(index)),
+            Action::Chord(coord) => self.board.index_of(coord).is_some_and(|index| {
+                let revealed = self.chord(index);
+                if revealed && self.auto_flag {
+                    self.auto_flag_into_move();
+                }
+                revealed
+            }),
+            Action::SafeReveal(coord) => self.board.index_of(coord).is_some_and(|index| {
+                let revealed = self.safe_reveal(index);
+                if revealed && self.auto_flag {
+                    self.auto_flag_into_move();
+                }
+                revealed
+            }),
+            Action::Hint => {
+                let hinted = self.hint().is_some();
+                if hinted && self.auto_flag {
+                    self.auto_flag_into_move();
+                }
+                hinted
+            }
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Restart => self.restart(),
+        };
+
+        // Hints aren't the player's clicks, and undo or restart aren't clicks on the board
+        if applied
+            && matches!(
+                action,
+                Action::Reveal(_) | Action::Chord(_) | Action::SafeReveal(_)
+            )
+        {
+            self.clicks += 1;
+        }
+
+        let changed = (0..self.board.tiles.len())
+            .filter(|&index| before.get(index) != self.board.tiles.get(index))
+            .collect();
+
+        ActionResult {
+            applied,
+            changed,
+            game_over: self.game_over,
+            game_won: self.game_won,
+        }
+    }
+
+    // Put every tile back to hidden over the same mines, with the lives the game started with.
+    // Returns false when there's no board to start over.
+    pub fn restart(&mut self) -> bool {
+        let layout: Vec<Tile> = self.board.tiles.iter().map(Tile::covered).collect();
+        let hints = std::mem::take(&mut self.hints);
+        let mine_counts = std::mem::take(&mut self.mine_counts);
+
+        if self
+            .board_setup(self.board.width, self.board.height, self.starting_mines)
+            .is_err()
+        {
+            self.hints = hints;
+            self.mine_counts = mine_counts;
+            return false;
+        }
+
+        for tile in layout {
+            self.add_tile(tile);
+        }
+
+        // The mines haven't moved
+        self.hints = hints;
+        self.mine_counts = mine_counts;
+        self.lives = self.starting_lives;
+
+        true
+    }
+
+    // Whether a tile has been cleared yet, which is when the game clock starts
+    #[must_use]
+    pub fn has_started(&self) -> bool {
+        self.moves.iter().any(|&(_, mode)| mode == InputMode::Clear)
+    }
+
+    // Take back the last move, even one that set off a mine, for `UNDO_PENALTY` on the clock.
+    // Returns false when there's nothing left to undo, or no undos left to do it with.
+    pub fn undo(&mut self) -> bool {
+        if self.undos_left() == Some(0) {
+            return false;
+        }
+        let Some(previous) = self.history.pop_back() else {
+            return false;
+        };
+
+        let undone = self.restore(previous);
+        self.redo_stack.push(undone);
+        self.undos_used += 1;
+        self.elapsed += UNDO_PENALTY;
+
+        true
+    }
+
+    // Make the most recently undone move again. Returns false when there's nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(undone) = self.redo_stack.pop() else {
+            return false;
+        };
+
+        let previous = self.restore(undone);
+        self.history.push_back(previous);
+
+        true
+    }
+
+    #[must_use]
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    #[must_use]
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// A hand made puzzle with every tile hidden: one line per row, `*` for a mine, `.` for a
+    /// safe tile and `#` for a gap outside the board's shape. Blank lines are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` for rows of different widths or wider than [`MAX_WIDTH`],
+    /// characters other than `*`, `.` and `#`, or a layout with no tiles or no safe tiles.
+    pub fn from_layout(layout: &str) -> Result<GameState, ParseError> {
+        let rows: Vec<&str> = layout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+
+        let mut mines = Vec::new();
+
+        for row in &rows {
+            if row.chars().count() != width {
+                return Err(ParseError::new("layout row", row));
+            }
+
+            // Columns past the last letter couldn't be selected
+            if width > MAX_WIDTH as usize {
+                return Err(ParseError::new("layout row (at most 26 tiles)", row));
+            }
+
+            for symbol in row.chars() {
+                match symbol {
+                    '*' | '.' => mines.push(Some(symbol == '*')),
+                    '#' => mines.push(None),
+                    _ => return Err(ParseError::new("layout tile", &symbol.to_string())),
+                }
+            }
+        }
+
+        let bad_layout = |error: BoardError| ParseError::new("layout", &error.to_string());
+        let width = u32::try_from(width).map_err(|_| ParseError::new("layout", layout))?;
+        let height = u32::try_from(rows.len()).map_err(|_| ParseError::new("layout", layout))?;
+        let mine_count = u32::try_from(mines.iter().filter(|&&tile| tile == Some(true)).count())
+            .map_err(|_| ParseError::new("layout", layout))?;
+        let active = u32::try_from(mines.iter().flatten().count())
+            .map_err(|_| ParseError::new("layout", layout))?;
+
+        let mut state = GameState::new();
+        state
+            .board_setup(width, height, mine_count)
+            .map_err(bad_layout)?;
+
+        // Gaps don't count as room for the mines
+        if mine_count >= active {
+            return Err(bad_layout(BoardError::TooManyMines {
+                mines: mine_count,
+                tiles: active,
+            }));
+        }
+
+        for tile in mines {
+            state.add_tile(match tile {
+                Some(has_mine) => Tile::Hidden {
+                    has_mine,
+                    flagged: false,
+                    questioned: false,
+                },
+                None => Tile::Inactive,
+            });
+        }
+
+        engine::compute_hints(&mut state);
+
+        Ok(state)
+    }
+
+    /// Load a puzzle written in the `from_layout` format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, or doesn't hold a valid layout.
+    pub fn from_layout_file(path: &Path) -> io::Result<GameState> {
+        let layout = fs::read_to_string(path)?;
+
+        GameState::from_layout(&layout)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    // Everything needed to pick the game up again after the program closes: the board, counters,
+    // clock, options, and moves so far. Read back by `from_save`. The undo history isn't kept.
+    #[must_use]
+    pub fn to_save(&self) -> String {
+        let optional =
+            |value: Option<u64>| value.map_or_else(|| String::from("-"), |value| value.to_string());
+        let status = if self.timed_out {
+            "timeout"
+        } else if self.game_won {
+            "won"
+        } else if self.game_over {
+            "lost"
+        } else {
+            "playing"
+        };
+
+        let mut save = String::from("minesweeper save\n");
+
+        // Writing to a String can't fail
+        let _ = writeln!(save, "size {}x{}", self.board.width, self.board.height);
+        let _ = writeln!(save, "mines {} {}", self.starting_mines, self.mine_count);
+        let _ = writeln!(
+            save,
+            "turns {} {} {} {} {}",
+            self.turn_count, self.flag_operations, self.hints_used, self.clicks, self.undos_used
+        );
+        let _ = writeln!(save, "lives {} {}", self.lives, self.starting_lives);
+        let _ = writeln!(save, "seed {}", optional(self.seed));
+        let _ = writeln!(save, "elapsed {}", self.elapsed.as_millis());
+        let _ = writeln!(
+            save,
+            "limit {} {} {}",
+            optional(self.time_limit.map(|limit| limit.as_secs())),
+            optional(self.undo_limit.map(u64::from)),
+            optional(self.flag_limit.map(u64::from))
+        );
+        let _ = writeln!(save, "status {status}");
+        let _ = writeln!(
+            save,
+            "{}",
+            format!("options {}", self.save_options().join(" ")).trim_end()
+        );
+
+        save.push_str("board\n");
+        for row in self.board.tiles.chunks(self.board.width.max(1) as usize) {
+            save.extend(row.iter().map(|tile| match *tile {
+                Tile::Hidden {
+                    has_mine: true,
+                    questioned: true,
+                    ..
+                } => 'Q',
+                Tile::Hidden {
+                    has_mine: false,
+                    questioned: true,
+                    ..
+                } => 'q',
+                Tile::Hidden {
+                    has_mine: true,
+                    flagged: false,
+                    ..
+                } => '*',
+                Tile::Hidden {
+                    has_mine: false,
+                    flagged: false,
+                    ..
+                } => '.',
+                Tile::Hidden {
+                    has_mine: true,
+                    flagged: true,
+                    ..
+                } => 'F',
+                Tile::Hidden {
+                    has_mine: false,
+                    flagged: true,
+                    ..
+                } => 'f',
+                Tile::Revealed { has_mine: true, .. } => 'X',
+                Tile::Revealed {
+                    has_mine: false, ..
+                } => 'o',
+                Tile::Inactive => '#',
+            }));
+            save.push('\n');
+        }
+
+        save.push_str("moves\n");
+        self.write_moves(&mut save);
+
+        save
+    }
+
+    /// Rebuild a game written by `to_save`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` naming the first line that doesn't fit the format, or if the
+    /// board it describes can't be played.
+    pub fn from_save(save: &str) -> Result<GameState, ParseError> {
+        let mut lines = save.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        if lines.next() != Some("minesweeper save") {
+            return Err(ParseError::new(
+                "save",
+                save.lines().next().unwrap_or_default(),
+            ));
+        }
+
+        let pair = |name: &'static str, value: &str, separator: char| {
+            value
+                .split_once(separator)
+                .and_then(|(first, second)| Some((first.parse().ok()?, second.parse().ok()?)))
+                .ok_or_else(|| ParseError::new(name, value))
+        };
+        let optional = |name: &'static str, value: &str| match value {
+            "-" => Ok(None),
+            _ => value
+                .parse()
+                .map(Some)
+                .map_err(|_| ParseError::new(name, value)),
+        };
+
+        let (width, height) = pair("size", GameState::save_field(&mut lines, "size")?, 'x')?;
+        let (starting_mines, mine_count) =
+            pair("mines", GameState::save_field(&mut lines, "mines")?, ' ')?;
+
+        let mut state = GameState::new();
+        state
+            .board_setup(width, height, starting_mines)
+            .map_err(|error| ParseError::new("save board", &error.to_string()))?;
+
+        state.mine_count = mine_count;
+        [
+            state.turn_count,
+            state.flag_operations,
+            state.hints_used,
+            state.clicks,
+            state.undos_used,
+        ] = GameState::save_turns(GameState::save_field(&mut lines, "turns")?)?;
+        (state.lives, state.starting_lives) =
+            pair("lives", GameState::save_field(&mut lines, "lives")?, ' ')?;
+        state.seed = optional("seed", GameState::save_field(&mut lines, "seed")?)?;
+        state.elapsed = Duration::from_millis(
+            optional("elapsed", GameState::save_field(&mut lines, "elapsed")?)?
+                .ok_or_else(|| ParseError::new("elapsed", "-"))?,
+        );
+        state.set_save_limits(GameState::save_field(&mut lines, "limit")?)?;
+
+        match GameState::save_field(&mut lines, "status")? {
+            "playing" => {}
+            "won" => state.game_won = true,
+            "lost" => state.game_over = true,
+            "timeout" => (state.game_over, state.timed_out) = (true, true),
+            status => return Err(ParseError::new("status", status)),
+        }
+
+        for option in GameState::save_field(&mut lines, "options")?.split_whitespace() {
+            if !state.set_save_option(option) {
+                return Err(ParseError::new("save option", option));
+            }
+        }
+
+        GameState::save_field(&mut lines, "board")?;
+        for _ in 0..height {
+            let row = lines.next().unwrap_or_default();
+
+            if row.chars().count() != width as usize {
+                return Err(ParseError::new("save board row", row));
+            }
+
+            for symbol in row.chars() {
+                let tile = GameState::save_symbol_tile(symbol)
+                    .ok_or_else(|| ParseError::new("save tile", &symbol.to_string()))?;
+                state.add_tile(tile);
+            }
+        }
+
+        let mine_tiles = state
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| tile.has_mine())
+            .count();
+        if mine_tiles != starting_mines as usize {
+            return Err(ParseError::new(
+                "save board",
+                &format!("{mine_tiles} mines"),
+            ));
+        }
+
+        GameState::save_field(&mut lines, "moves")?;
+        for line in lines {
+            let (index, input_mode, time) =
+                replay::parse_move(line).ok_or_else(|| ParseError::new("save move", line))?;
+            state.moves.push((index, input_mode));
+            state.move_times.push(time.unwrap_or_default());
+        }
+
+        // Hints come from the mines, so revealed tiles get theirs once the board is complete
+        engine::compute_hints(&mut state);
+        for index in 0..state.board.tiles.len() {
+            if let Tile::Revealed { has_mine, .. } = state.board.tiles[index] {
+                state.board.tiles[index] = Tile::Revealed {
+                    has_mine,
+                    hint: state.get_hint(index),
+                };
+            }
+        }
+
+        Ok(state)
+    }
+
+    // The value of the next line in a save, which must start with `name`
+    fn save_field<'a>(
+        lines: &mut impl Iterator<Item = &'a str>,
+        name: &'static str,
+    ) -> Result<&'a str, ParseError> {
+        let line = lines.next().unwrap_or_default();
+        let value = line
+            .strip_prefix(name)
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '));
+
+        value
+            .map(str::trim)
+            .ok_or_else(|| ParseError::new(name, line))
+    }
+
+    // The `options` line of a save, as read back by `set_save_option` and `set_save_value`
+    fn save_options(&self) -> Vec<String> {
+        let mut options: Vec<String> = [
+            (self.wrap, "wrap"),
+            (self.adjacency == Adjacency::Four, "four"),
+            (self.adjacency == Adjacency::Triangle, "triangles"),
+            (self.first_click_zero, "opening"),
+            (!self.first_click_safe, "risky"),
+            (self.no_guess, "no-guess"),
+            (self.practice, "practice"),
+            (self.auto_flag, "auto-flag"),
+            (!self.flagging_allowed, "no-flags"),
+            (self.question_marks, "questions"),
+            (self.confirm_risky_clears, "confirm"),
+            (self.show_probabilities, "probabilities"),
+            (self.unicode_grid, "boxed"),
+            (self.compact, "compact"),
+            (self.theme == Theme::unicode(), "emoji"),
+            (self.daily, "daily"),
+            (self.win_condition == WinCondition::FlagMines, "win-flags"),
+            (self.win_condition == WinCondition::Either, "win-either"),
+        ]
+        .into_iter()
+        .filter(|&(enabled, _)| enabled)
+        .map(|(_, name)| name.to_string())
+        .collect();
+
+        // Multi-mine boards keep each tile's count, one digit a tile
+        if self.mines_per_tile > 1 {
+            options.push(format!("stacks={}", self.mines_per_tile));
+        }
+        if !self.mine_counts.is_empty() {
+            let counts: String = self.mine_counts.iter().map(u8::to_string).collect();
+            options.push(format!("stack-counts={counts}"));
+        }
+        if self.flag_counts.iter().any(|&count| count > 1) {
+            let counts: String = self.flag_counts.iter().map(u8::to_string).collect();
+            options.push(format!("flag-counts={counts}"));
+        }
+        // The board rows already mark which tiles are inactive; this keeps the shape's name
+        if self.shape != Shape::Rectangle {
+            options.push(format!("shape={}", self.shape.name()));
+        }
+
+        options
+    }
+
+    // Turns, flag operations, hints used, clicks, and undos used. Saves from before the later
+    // ones were counted stop short of them, after at least the flag operations.
+    fn save_turns(value: &str) -> Result<[u32; 5], ParseError> {
+        let mut counts: Vec<u32> = value
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| ParseError::new("turns", value))?;
+
+        if !(2..=5).contains(&counts.len()) {
+            return Err(ParseError::new("turns", value));
+        }
+        counts.resize(5, 0);
+
+        counts
+            .try_into()
+            .map_err(|_| ParseError::new("turns", value))
+    }
+
+    // The time limit in seconds, undo limit, and flag limit, with `-` for none. Saves from before
+    // undos or flags were limited stop short of them.
+    fn set_save_limits(&mut self, value: &str) -> Result<(), ParseError> {
+        let bad = || ParseError::new("limit", value);
+        let fields: Vec<&str> = value.split_whitespace().collect();
+        if !(1..=3).contains(&fields.len()) {
+            return Err(bad());
+        }
+
+        let field = |position: usize| match fields.get(position).copied().unwrap_or("-") {
+            "-" => Ok(None),
+            field => field.parse::<u64>().map(Some).map_err(|_| bad()),
+        };
+        let count = |position| {
+            field(position)?
+                .map(|count| u32::try_from(count).map_err(|_| bad()))
+                .transpose()
+        };
+
+        self.time_limit = field(0)?.map(Duration::from_secs);
+        self.undo_limit = count(1)?;
+        self.flag_limit = count(2)?;
+
+        Ok(())
+    }
+
+    // The tile a board symbol in a save stands for, before its hint is known
+    fn save_symbol_tile(symbol: char) -> Option<Tile> {
+        match symbol {
+            '*' | '.' | 'F' | 'f' | 'Q' | 'q' => Some(Tile::Hidden {
+                has_mine: matches!(symbol, '*' | 'F' | 'Q'),
+                flagged: matches!(symbol, 'F' | 'f'),
+                questioned: matches!(symbol, 'Q' | 'q'),
+            }),
+            'X' | 'o' => Some(Tile::Revealed {
+                has_mine: symbol == 'X',
+                hint: 0,
+            }),
+            '#' => Some(Tile::Inactive),
+            _ => None,
+        }
+    }
+
+    // Turn on one of the options named in a save. Returns false for a name it doesn't know.
+    fn set_save_option(&mut self, option: &str) -> bool {
+        match option {
+            "wrap" => self.wrap = true,
+            "four" => self.adjacency = Adjacency::Four,
+            "triangles" => self.adjacency = Adjacency::Triangle,
+            "opening" => self.first_click_zero = true,
+            "risky" => self.first_click_safe = false,
+            "no-guess" => self.no_guess = true,
+            "practice" => self.practice = true,
+            "auto-flag" => self.auto_flag = true,
+            "no-flags" => self.flagging_allowed = false,
+            "questions" => self.question_marks = true,
+            "confirm" => self.confirm_risky_clears = true,
+            "probabilities" => self.show_probabilities = true,
+            "boxed" => self.unicode_grid = true,
+            "compact" => self.compact = true,
+            "emoji" => self.theme = Theme::unicode(),
+            "daily" => self.daily = true,
+            "win-flags" => self.win_condition = WinCondition::FlagMines,
+            "win-either" => self.win_condition = WinCondition::Either,
+            _ => return self.set_save_value(option),
+        }
+
+        true
+    }
+
+    // As `set_save_option`, for the options that carry a `name=value`
+    fn set_save_value(&mut self, option: &str) -> bool {
+        match option.split_once('=') {
+            Some(("stacks", most)) => match most.parse() {
+                Ok(most) => self.set_mines_per_tile(most),
+                Err(_) => return false,
+            },
+            Some((name @ ("stack-counts" | "flag-counts"), counts)) => {
+                let Some(counts) = counts
+                    .chars()
+                    .map(|count| {
+                        count
+                            .to_digit(10)
+                            .and_then(|count| u8::try_from(count).ok())
+                    })
+                    .collect::<Option<Vec<u8>>>()
+                else {
+                    return false;
+                };
+                if counts.len() != (self.board.width * self.board.height) as usize {
+                    return false;
+                }
+                if name == "stack-counts" {
+                    self.mine_counts = counts;
+                } else {
+                    self.flag_counts = counts;
+                }
+            }
+            Some(("shape", shape)) => match shape.parse() {
+                Ok(shape) => self.shape = shape,
+                Err(_) => return false,
+            },
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Write the game to `path` in the `to_save` format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, self.to_save())
+    }
+
+    /// Load a game written by `save_to`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, or doesn't hold a valid save.
+    pub fn load_from(path: &Path) -> io::Result<GameState> {
+        let save = fs::read_to_string(path)?;
+
+        GameState::from_save(&save)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    // Reveal every unflagged hidden neighbor of a number with as many flags around it as its
+    // hint. The flags are trusted, so a wrong one sets off the mine it left uncovered.
+    // Each tile is cleared as its own move. Returns false when there was nothing to chord.
+    pub fn chord(&mut self, index: usize) -> bool {
+        let Some(targets) = engine::chord_targets(self, index) else {
+            return false;
+        };
+
+        self.reveal_each(targets)
+    }
+
+    // Like `chord`, except that only the neighbors the numbers prove safe are cleared, so wrong
+    // flags never set off a mine. Returns false when nothing could be safely revealed.
+    pub fn safe_reveal(&mut self, index: usize) -> bool {
+        let Some(targets) = engine::safe_neighbors(self, index) else {
+            return false;
+        };
+
+        self.reveal_each(targets)
+    }
+
+    fn reveal_each(&mut self, targets: Vec<usize>) -> bool {
+        let mut revealed = false;
+
+        for target in targets {
+            // Earlier reveals may have flood filled over this one already, or ended the game
+            revealed |= self.reveal(target);
+        }
+
+        revealed
+    }
+
+    // Hidden tiles that must be mines: the unflagged neighbors of any number that has exactly
+    // as many of them as it's missing mines
+    fn forced_mines(&self) -> Vec<usize> {
+        let mut forced_mines = Vec::new();
+
+        for index in 0..self.board.tiles.len() {
+            let Some(hint) = self.board.tiles[index].hint() else {
+                continue;
+            };
+
+            let neighbors = engine::find_neighbors(self, index);
+            let unknown: Vec<usize> = neighbors
+                .iter()
+                .copied()
+                .filter(|&neighbor| {
+                    matches!(
+                        self.board.tiles[neighbor],
+                        Tile::Hidden { flagged: false, .. }
+                    )
+                })
+                .collect();
+
+            if !unknown.is_empty()
+                && engine::marked_mines(self, index) + unknown.len() == hint as usize
+            {
+                forced_mines.extend(unknown);
+            }
+        }
+
+        forced_mines.sort_unstable();
+        forced_mines.dedup();
+
+        forced_mines
+    }
+
+    // Flag every tile that's certain to be a mine, for as long as flags last. Never clears
+    // anything. Returns whether any flags were placed.
+    pub fn flag_forced_mines(&mut self) -> bool {
+        if self.game_over || self.game_won {
+            return false;
+        }
+
+        let mut changed = false;
+
+        for index in self.forced_mines() {
+            changed |= self.toggle_flag(index);
+        }
+
+        changed
+    }
+
+    // Auto-flags belong to the move that made them certain, so a single undo takes back both
+    fn auto_flag_into_move(&mut self) {
+        let history = std::mem::take(&mut self.history);
+        self.flag_forced_mines();
+        self.history = history;
+    }
+
+    // Clear a tile the numbers prove safe, or failing that flag one they prove is a mine.
+    // The player's own flags aren't trusted, so a wrong one can't lead a hint onto a mine.
+    // Returns the tile, or None when every move left would be a guess. The solver counts a mine
+    // to a tile, so there are no hints on boards with stacked mines.
+    pub fn hint(&mut self) -> Option<usize> {
+        if self.game_over || self.game_won || self.mines_per_tile > 1 {
+            return None;
+        }
+
+        let (index, has_mine) = solver::certain_move(self)?;
+        let made = if has_mine {
+            self.toggle_flag(index)
+        } else {
+            self.reveal(index)
+        };
+
+        made.then(|| {
+            self.hints_used += 1;
+            index
+        })
+    }
+
+    // One round of forced moves across the whole board: flag the hidden neighbors of any number
+    // that has exactly as many hidden neighbors as missing mines, then clear around any number
+    // its flags now satisfy. Never guesses, and never runs on stacked mines, which the solver
+    // can't count. Returns whether anything changed.
+    pub fn auto_solve_step(&mut self) -> bool {
+        if self.game_over || self.game_won || self.mines_per_tile > 1 {
+            return false;
+        }
+
+        let mut changed = self.flag_forced_mines();
+
+        // A tile proven safe stays safe as others are cleared, so one proof does for the round
+        let probabilities = solver::unflagged_probabilities(self);
+
+        for index in 0..self.board.tiles.len() {
+            if self.board.tiles[index].hint().is_some() {
+                if let Some(targets) = engine::proven_safe_neighbors(self, index, &probabilities) {
+                    changed |= self.reveal_each(targets);
+                }
+            }
+        }
+
+        changed
+    }
+
+    // The move log with a header describing the board, in the format read by `replay::Replay`
+    #[must_use]
+    pub fn export_moves(&self) -> String {
+        let seed = self
+            .seed
+            .map_or_else(|| String::from("-"), |seed| seed.to_string());
+
+        let mut log = format!(
+            "{}x{} {} {seed} {}",
+            self.board.width, self.board.height, self.starting_mines, self.starting_lives
+        );
+
+        // The first clear moves mines unless both are off, so replays need to know
+        if self.first_click_zero {
+            log.push_str(" opening");
+        }
+        if !self.first_click_safe {
+            log.push_str(" risky");
+        }
+        if self.no_guess {
+            log.push_str(" no-guess");
+        }
+        if self.practice {
+            log.push_str(" practice");
+        }
+        // Flagging a flag again marks it with a question rather than clearing it
+        if self.question_marks {
+            log.push_str(" questions");
+        }
+        // Who neighbors who decides every hint
+        match self.adjacency {
+            Adjacency::Eight => {}
+            Adjacency::Four => log.push_str(" four"),
+            Adjacency::Triangle => log.push_str(" triangles"),
+        }
+        if self.wrap {
+            log.push_str(" wrap");
+        }
+        // The shape decides where the seed's mines land
+        if self.shape != Shape::Rectangle {
+            let _ = write!(log, " shape={}", self.shape.name());
+        }
+        // Mines are stacked from the seed on the first clear, like the rest of the deal
+        if self.mines_per_tile > 1 {
+            let _ = write!(log, " stacks={}", self.mines_per_tile);
+        }
+        // Flag wins end the game somewhere else than clearing the board does
+        match self.win_condition {
+            WinCondition::RevealSafe => {}
+            WinCondition::FlagMines => log.push_str(" win=flag"),
+            WinCondition::Either => log.push_str(" win=either"),
+        }
+        // Time attack games can end on the clock rather than on a move
+        if let Some(limit) = self.time_limit {
+            let _ = write!(
+                log,
+                " limit={} elapsed={}",
+                limit.as_secs(),
+                self.elapsed.as_millis()
+            );
+        }
+        // Chords are logged as the clears they made, so the clicks can't be counted from the moves
+        let _ = write!(log, " clicks={}", self.clicks);
+        log.push('\n');
+        self.write_moves(&mut log);
+
+        log
+    }
+
+    // Fraction of the safe tiles revealed so far, reaching 1.0 on a win
+    #[allow(clippy::cast_precision_loss)] // Tile counts are far below f32 precision
+    #[must_use]
+    pub fn progress(&self) -> f32 {
+        let safe = self
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| tile.is_active() && !tile.has_mine())
+            .count();
+        let revealed = self.tiles_cleared() as usize;
+
+        if safe == 0 {
+            return 0.0;
+        }
+
+        revealed as f32 / safe as f32
+    }
+
+    // Safe tiles revealed so far
+    #[must_use]
+    pub fn tiles_cleared(&self) -> u32 {
+        let cleared = self
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| {
+                matches!(
+                    tile,
+                    Tile::Revealed {
+                        has_mine: false,
+                        ..
+                    }
+                )
+            })
+            .count();
+
+        u32::try_from(cleared).unwrap_or(u32::MAX)
+    }
+
+    // Mines set off this game, each of which cost a life
+    #[must_use]
+    pub fn mines_hit(&self) -> u32 {
+        self.starting_lives.saturating_sub(self.lives)
+    }
+
+    // Time left on the clock in time attack mode
+    #[must_use]
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.time_limit
+            .map(|limit| limit.saturating_sub(self.elapsed))
+    }
+
+    // End the game if the clock has run out on an unfinished board.
+    // Returns whether it has.
+    pub fn check_time_limit(&mut self) -> bool {
+        if self.game_over || self.game_won || self.time_remaining() != Some(Duration::ZERO) {
+            return self.timed_out;
+        }
+
+        self.timed_out = true;
+        self.game_over = true;
+
+        true
+    }
+
+    // How many safe tiles are still hidden, reaching 0 on a win
+    #[allow(clippy::cast_possible_truncation)] // Tile counts fit in u32 like the board size
+    #[must_use]
+    pub fn remaining_safe(&self) -> u32 {
+        self.board
+            .tiles
+            .iter()
+            .filter(|tile| {
+                matches!(
+                    tile,
+                    Tile::Hidden {
+                        has_mine: false,
+                        ..
+                    }
+                )
+            })
+            .count() as u32
+    }
+
+    // The board's 3BV done so far for each click, as competitive clones rate efficiency. Above
+    // 1.0 means chording saved clicks. None before the first click.
+    #[allow(clippy::cast_precision_loss)] // 3BV and click counts are far below f64 precision
+    #[must_use]
+    pub fn efficiency(&self) -> Option<f64> {
+        (self.clicks > 0).then(|| f64::from(engine::solved_3bv(self)) / f64::from(self.clicks))
+    }
+
+    // Points for the game so far, by the default `scoring::ScoreFormula`
+    #[must_use]
+    pub fn score(&self) -> i64 {
+        scoring::ScoreFormula::default().score(self)
+    }
+
+    #[must_use]
+    pub fn outcome(&self) -> GameOutcome {
+        if self.game_won {
+            GameOutcome::Won
+        } else if self.game_over {
+            GameOutcome::Lost
+        } else {
+            GameOutcome::InProgress
+        }
+    }
+
+    #[must_use]
+    pub fn result_summary(&self) -> ResultSummary {
+        ResultSummary {
+            won: self.game_won,
+            turn_count: self.turn_count,
+            hints_used: self.hints_used,
+            elapsed_secs: self.elapsed.as_secs(),
+            width: self.board.width,
+            height: self.board.height,
+            starting_mines: self.starting_mines,
+            seed: self.seed,
+        }
+    }
+
+    // Estimated chance of each tile hiding a mine, or None for tiles that aren't hidden
+    #[must_use]
+    pub fn mine_probabilities(&self) -> Vec<Option<f64>> {
+        solver::mine_probabilities(self)
+    }
+
+    // Diagnostic view of the whole board. Unlike a loss, this does not end the game.
+    pub fn reveal_all(&mut self) {
+        for index in 0..self.board.tiles.len() {
+            // Gaps in the board's shape stay gaps
+            if !self.board.tiles[index].is_active() {
+                continue;
+            }
+            self.board.tiles[index] = Tile::Revealed {
+                has_mine: self.board.tiles[index].has_mine(),
+                hint: self.get_hint(index),
+            };
+        }
+    }
+
+    pub fn clear_tiles(&mut self) {
+        self.board.tiles = Vec::new();
+        self.hints = Vec::new();
+    }
+
+    pub fn increment_turn_count(&mut self) {
+        self.turn_count += 1;
+    }
+
+    // Hand a flag back, never going past the number of mines on the board.
+    // Returns whether the count changed.
+    pub fn increment_mine_count(&mut self) -> bool {
+        if self.mine_count >= self.starting_mines {
+            return false;
+        }
+
+        self.mine_count += 1;
+        true
+    }
+
+    // Use up a flag, stopping at zero. Returns whether the count changed.
+    pub fn decrement_mine_count(&mut self) -> bool {
+        if self.mine_count == 0 {
+            return false;
+        }
+
+        self.mine_count -= 1;
+        true
+    }
+
+    pub fn lose_life(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+    }
+
+    #[must_use]
+    pub fn builder(width: u32, height: u32) -> GameStateBuilder {
+        GameStateBuilder::new(width, height)
+    }
+}
+
+/// Set up a ready to play game in one go, with everything checked when it's built.
+///
+/// ```
+/// use minesweeper::GameState;
+///
+/// let state = GameState::builder(3, 2).mine_positions([0, 5]).build().unwrap();
+///
+/// assert_eq!(2, state.get_mines());
+/// assert!(state.get_tile(5).has_mine());
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct GameStateBuilder {
+    width: u32,
+    height: u32,
+    mines: u32,
+    // Placed exactly here instead of at random
+    mine_positions: Option<Vec<usize>>,
+    seed: Option<u64>,
+    first_click_zero: bool,
+    first_click_safe: bool,
+    no_guess: bool,
+}
+
+impl GameStateBuilder {
+    // A board with no mines until some are asked for
+    #[must_use]
+    pub fn new(width: u32, height: u32) -> GameStateBuilder {
+        GameStateBuilder {
+            width,
+            height,
+            mines: 0,
+            mine_positions: None,
+            seed: None,
+            first_click_zero: false,
+            first_click_safe: true,
+            no_guess: false,
+        }
+    }
+
+    // Scatter this many mines, replacing any positions given before
+    #[must_use]
+    pub fn mines(mut self, mines: u32) -> GameStateBuilder {
+        self.mines = mines;
+        self.mine_positions = None;
+        self
+    }
+
+    // Put mines on exactly these tiles, replacing any count given before
+    #[must_use]
+    pub fn mine_positions(
+        mut self,
+        positions: impl IntoIterator<Item = usize>,
+    ) -> GameStateBuilder {
+        let mut positions: Vec<usize> = positions.into_iter().collect();
+        positions.sort_unstable();
+        positions.dedup();
+
+        self.mine_positions = Some(positions);
+        self
+    }
+
+    // Pins down where scattered mines go, and where the first clear moves them to
+    #[must_use]
+    pub fn seed(mut self, seed: u64) -> GameStateBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    #[must_use]
+    pub fn first_click_zero(mut self, first_click_zero: bool) -> GameStateBuilder {
+        self.first_click_zero = first_click_zero;
+        self
+    }
+
+    #[must_use]
+    pub fn first_click_safe(mut self, first_click_safe: bool) -> GameStateBuilder {
+        self.first_click_safe = first_click_safe;
+        self
+    }
+
+    #[must_use]
+    pub fn no_guess(mut self, no_guess: bool) -> GameStateBuilder {
+        self.no_guess = no_guess;
+        self
+    }
+
+    /// # Errors
+    ///
+    /// Returns a `BoardError` if the board has no tiles, is over [`MAX_WIDTH`] or
+    /// [`MAX_HEIGHT`], has too many mines, or has a mine position off the board.
+    pub fn build(self) -> Result<GameState, BoardError> {
+        // The dimensions come first, so the tile count below can't overflow
+        GameState::validate_board(self.width, self.height, 0)?;
+
+        let mut state = match &self.mine_positions {
+            None => engine::new_game(
+                &Difficulty::from_dimensions(self.width, self.height, self.mines),
+                self.seed,
+            )?,
+            Some(positions) => {
+                let tiles = self.width * self.height;
+
+                if let Some(&index) = positions.iter().find(|&&index| index >= tiles as usize) {
+                    return Err(BoardError::MineOffBoard { index, tiles });
+                }
+
+                // All different and on the board, so no more of them than there are tiles
+                #[allow(clippy::cast_possible_truncation)]
+                let mines = positions.len() as u32;
+
+                let mut state = GameState::new();
+                state.board_setup(self.width, self.height, mines)?;
+                state.set_seed(self.seed);
+
+                for index in 0..tiles as usize {
+                    state.add_tile(Tile::Hidden {
+                        has_mine: positions.binary_search(&index).is_ok(),
+                        flagged: false,
+                        questioned: false,
+                    });
+                }
+
+                engine::compute_hints(&mut state);
+                state
+            }
+        };
+
+        state.set_first_click_zero(self.first_click_zero);
+        state.set_first_click_safe(self.first_click_safe);
+        state.set_no_guess(self.no_guess);
+
+        Ok(state)
+    }
+}
+
+pub mod game_loop {
+
+    use super::{
+        campaign::{self, Progress},
+        column_label,
+        engine::{
+            board_3bv, compute_hints, daily_seed, is_risky_clear, new_shaped_game, solved_3bv,
+            to_action,
+        },
+        grid_size, input_handler,
+        input_handler::{EndChoice, InputMode, InputSource},
+        leaderboard::{Entry, Leaderboard, Ranking},
+        replay::Replay,
+        scoring::{self, ScoreFormula},
+        stats::{self, data_dir, Record, Stats},
+        Action, BoardError, Coord, Difficulty, GameMode, GameState, GridStyle, MinesweeperError,
+        Shape, Theme, Tile, WinCondition, MAX_LIVES, MAX_MINES_PER_TILE, MAX_TIME_LIMIT_SECS,
+        MAX_UNDOS, MIN_TIME_LIMIT_SECS, UNDO_PENALTY,
+    };
+    use crossterm::{cursor, execute, style, terminal};
+    use std::{
+        env, fs,
+        io::{self, stdout},
+        ops::Range,
+        path::{Path, PathBuf},
+        process,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    };
+
+    // Choices made on the configuration screen, applied to every new game
+    #[derive(Debug, Clone, PartialEq)]
+    #[allow(clippy::struct_excessive_bools)] // Each is an independent on/off option
+    pub struct Settings {
+        pub difficulty: Difficulty,
+        // Lays out the next game's mines, to play a board someone else shared
+        pub seed: Option<u64>,
+        pub lives: u32,
+        pub show_probabilities: bool,
+        pub confirm_risky_clears: bool,
+        pub first_click_zero: bool,
+        pub first_click_safe: bool,
+        pub no_guess: bool,
+        pub practice: bool,
+        pub mines_per_tile: u32,
+        pub shape: Shape,
+        pub auto_flag: bool,
+        pub flagging_allowed: bool,
+        pub flag_limit: Option<u32>,
+        pub question_marks: bool,
+        pub win_condition: WinCondition,
+        pub time_limit: Option<Duration>,
+        pub undo_limit: Option<u32>,
+        pub theme: Theme,
+        pub unicode_grid: bool,
+        pub compact: bool,
+    }
+
+    impl Default for Settings {
+        fn default() -> Self {
+            Settings {
+                difficulty: Difficulty::Easy,
+                seed: None,
+                lives: 1,
+                show_probabilities: false,
+                confirm_risky_clears: false,
+                first_click_zero: false,
+                first_click_safe: true,
+                no_guess: false,
+                practice: false,
+                mines_per_tile: 1,
+                shape: Shape::Rectangle,
+                auto_flag: false,
+                flagging_allowed: true,
+                flag_limit: None,
+                question_marks: false,
+                win_condition: WinCondition::RevealSafe,
+                time_limit: None,
+                undo_limit: None,
+                theme: Theme::classic(),
+                unicode_grid: false,
+                compact: false,
+            }
+        }
+    }
+
+    impl Settings {
+        /// # Errors
+        ///
+        /// Returns a `BoardError` if the chosen difficulty can't be played.
+        pub fn new_game(&self, seed: Option<u64>) -> Result<GameState, BoardError> {
+            let mut state = new_shaped_game(&self.difficulty, seed, self.shape)?;
+            self.apply(&mut state);
+
+            Ok(state)
+        }
+
+        /// A fresh start on the same mines as `state`, for boards without a seed to rebuild
+        /// them from, such as loaded puzzles.
+        ///
+        /// # Errors
+        ///
+        /// Returns a `BoardError` if `state` doesn't hold a playable board.
+        pub fn restart(&self, state: &GameState) -> Result<GameState, BoardError> {
+            let mut restarted = GameState::new();
+            restarted.board_setup(state.get_width(), state.get_height(), state.starting_mines)?;
+
+            for (_, _, tile) in state.iter_tiles() {
+                restarted.add_tile(tile.covered());
+            }
+
+            // Stacked mines stay as they were dealt, on the same shape of tiles
+            restarted.shape = state.get_shape();
+            restarted.mine_counts.clone_from(&state.mine_counts);
+            restarted.set_adjacency(state.get_adjacency());
+            restarted.set_wrap(state.get_wrap());
+            compute_hints(&mut restarted);
+            self.apply(&mut restarted);
+
+            Ok(restarted)
+        }
+
+        fn apply(&self, state: &mut GameState) {
+            state.set_lives(self.lives);
+            state.set_show_probabilities(self.show_probabilities);
+            state.set_confirm_risky_clears(self.confirm_risky_clears);
+            state.set_first_click_zero(self.first_click_zero);
+            state.set_first_click_safe(self.first_click_safe);
+            state.set_no_guess(self.no_guess);
+            state.set_practice(self.practice);
+            state.set_mines_per_tile(self.mines_per_tile);
+            state.set_theme(self.theme);
+            state.set_unicode_grid(self.unicode_grid);
+            state.set_compact(self.compact);
+            state.set_auto_flag(self.auto_flag);
+            state.set_flagging_allowed(self.flagging_allowed);
+            state.set_flag_limit(self.flag_limit);
+            state.set_question_marks(self.question_marks);
+            state.set_win_condition(self.win_condition);
+            state.set_time_limit(self.time_limit);
+            state.set_undo_limit(self.undo_limit);
+        }
+    }
+
+    // Where a session keeps what outlasts it: the game saved on the way out, stats, the
+    // leaderboard, the scoring formula, campaign progress and the last game's replay
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct DataPaths {
+        pub saved_game: PathBuf,
+        pub stats: PathBuf,
+        pub leaderboard: PathBuf,
+        pub scoring: PathBuf,
+        pub campaign: PathBuf,
+        pub replay: PathBuf,
+    }
+
+    impl DataPaths {
+        // Every file in `dir`, under the names they have in the data directory
+        #[must_use]
+        pub fn in_dir(dir: &Path) -> DataPaths {
+            DataPaths {
+                saved_game: dir.join("saved_game.txt"),
+                stats: dir.join("stats.txt"),
+                leaderboard: dir.join("leaderboard.txt"),
+                scoring: dir.join("scoring.txt"),
+                campaign: dir.join("campaign.txt"),
+                replay: dir.join("last_game.log"),
+            }
+        }
+    }
+
+    impl Default for DataPaths {
+        fn default() -> Self {
+            DataPaths::in_dir(&data_dir())
+        }
+    }
+
+    // With `json` set, each finished game also prints a one line JSON summary.
+    // A game saved on the way out last time waits at the menu to be resumed.
+    pub fn play(json: bool) {
+        let input = &mut io::stdin().lock();
+        let played = play_with_frontend(&mut ConsoleRenderer, input, json, &DataPaths::default());
+
+        exit_on_error(played);
+    }
+
+    /// As `play`, showing everything through `renderer`, reading the player's choices from
+    /// `input` instead of the terminal, and keeping saves, stats and the like at `paths`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading from `input` fails or it runs out, which
+    /// ends the session where it was.
+    pub fn play_with_frontend(
+        renderer: &mut dyn Renderer,
+        input: &mut dyn InputSource,
+        json: bool,
+        paths: &DataPaths,
+    ) -> Result<(), MinesweeperError> {
+        let paused = load_saved_game(input, paths)?;
+        play_with(renderer, input, paused, Settings::default(), json, paths)
+    }
+
+    // The terminal can't go on without input, so input that's broken or runs out ends the program
+    fn exit_on_error(played: Result<(), MinesweeperError>) {
+        if let Err(error) = played {
+            println!();
+            eprintln!("{error}");
+            process::exit(1);
+        }
+    }
+
+    // Take the saved game off disk, so it's only resumed once unless saved again
+    fn load_saved_game(
+        input: &mut dyn InputSource,
+        paths: &DataPaths,
+    ) -> Result<Option<GameState>, MinesweeperError> {
+        let path = &paths.saved_game;
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let loaded = GameState::load_from(path);
+        let _ = fs::remove_file(path);
+
+        match loaded {
+            Ok(state) => Ok(Some(state)),
+            Err(error) => {
+                println!("Unable to load saved game {}: {error}", path.display());
+                input_handler::enter_to_continue(input)?;
+                Ok(None)
+            }
+        }
+    }
+
+    // The menu loop, holding on to a game paused from the action prompt until it's resumed.
+    // The settings last chosen carry over between games until reconfigured.
+    fn play_with(
+        renderer: &mut dyn Renderer,
+        input: &mut dyn InputSource,
+        mut paused: Option<GameState>,
+        mut settings: Settings,
+        json: bool,
+        paths: &DataPaths,
+    ) -> Result<(), MinesweeperError> {
+        loop {
+            let mode = menu(
+                renderer,
+                input,
+                paused.is_some(),
+                &settings.difficulty,
+                paths,
+            )?;
+
+            match mode {
+                GameMode::Quit => {
+                    // Nothing to lose without a paused game
+                    if let Some(state) = &paused {
+                        if input_handler::read_as_bool(
+                            input,
+                            "Save the game to resume next time? (y/n): ",
+                        )? {
+                            if let Err(error) = state.save_to(&paths.saved_game) {
+                                println!("Unable to save game: {error}");
+                                continue;
+                            }
+                        } else if !input_handler::read_as_bool(
+                            input,
+                            "Quit and lose progress? (y/n): ",
+                        )? {
+                            continue;
+                        }
+                    }
+
+                    clear_screen();
+                    restore_terminal();
+                    break Ok(());
+                }
+                // Already here, so just show it again
+                GameMode::Menu => continue,
+                GameMode::Stats => {
+                    clear_screen();
+                    println!("{}", Stats::load(&paths.stats));
+                    input_handler::enter_to_continue(input)?;
+                    continue;
+                }
+                GameMode::Leaderboard => {
+                    clear_screen();
+                    println!("{}", Leaderboard::load(&paths.leaderboard));
+                    input_handler::enter_to_continue(input)?;
+                    continue;
+                }
+                // Show a freshly generated board with everything revealed, for checking mine placement
+                GameMode::Debug => {
+                    let Ok(mut state) = settings.new_game(None) else {
+                        continue;
+                    };
+                    state.reveal_all();
+                    renderer.draw_game(&state, &ScoreFormula::load(&paths.scoring));
+                    println!("Seed: {}", state.get_seed().unwrap_or_default());
+                    input_handler::enter_to_continue(input)?;
+                    continue;
+                }
+                GameMode::Campaign => {
+                    play_campaign(renderer, input, &settings, json, paths)?;
+                    continue;
+                }
+                GameMode::Resume => {
+                    if let Some(mut state) = paused.take() {
+                        state.set_game_mode(GameMode::Play);
+                        paused = play_session(renderer, input, state, &mut settings, json, paths)?;
+                    }
+                    continue;
+                }
+                GameMode::Config => settings = config(input)?,
+                GameMode::Play | GameMode::Daily => {}
+            }
+
+            // Starting over abandons the paused game
+            if let Some(state) = paused.take() {
+                if input_handler::read_as_bool(
+                    input,
+                    "Save the paused game's moves before starting a new one? (y/n): ",
+                )? {
+                    save_replay(&state, paths);
+                }
+            }
+
+            // A seed from the config screen is only for the game it was entered for
+            let seed = if mode == GameMode::Daily {
+                Some(today_seed())
+            } else {
+                settings.seed.take()
+            };
+
+            match settings.new_game(seed) {
+                Ok(mut state) => {
+                    state.set_daily(mode == GameMode::Daily);
+                    paused = play_session(renderer, input, state, &mut settings, json, paths)?;
+                }
+                Err(error) => {
+                    println!("{error}");
+                    input_handler::enter_to_continue(input)?;
+                }
+            }
+        }
+    }
+
+    // Play games back to back from the end of game prompt, keeping any change of settings.
+    // Returns the game if the player paused it to go back to the menu.
+    fn play_session(
+        renderer: &mut dyn Renderer,
+        input: &mut dyn InputSource,
+        mut state: GameState,
+        settings: &mut Settings,
+        json: bool,
+        paths: &DataPaths,
+    ) -> Result<Option<GameState>, MinesweeperError> {
+        loop {
+            let next = match run(renderer, input, &mut state, json, paths)? {
+                None => return Ok(Some(state)),
+                Some(EndChoice::Quit) => return Ok(None),
+                // The stored seed lays the mines out exactly as before
+                Some(EndChoice::Replay) => match state.get_seed() {
+                    Some(seed) => settings.new_game(Some(seed)),
+                    None => settings.restart(&state),
+                },
+                Some(EndChoice::NewGame) => settings.new_game(None),
+                Some(EndChoice::Configure) => {
+                    *settings = config(input)?;
+                    let seed = settings.seed.take();
+                    settings.new_game(seed)
+                }
+            };
+
+            match next {
+                Ok(next) => state = next,
+                Err(error) => {
+                    println!("{error}");
+                    input_handler::enter_to_continue(input)?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    // Play the campaign's levels in turn from wherever it was left, saving progress after each.
+    // The display options chosen on the config screen still apply.
+    fn play_campaign(
+        renderer: &mut dyn Renderer,
+        input: &mut dyn InputSource,
+        settings: &Settings,
+        json: bool,
+        paths: &DataPaths,
+    ) -> Result<(), MinesweeperError> {
+        let path = &paths.campaign;
+        let mut progress = Progress::load(path);
+
+        if progress.is_complete() {
+            println!(
+                "Campaign complete, with a score of {}! Starting it over.",
+                progress.score
+            );
+            progress = Progress::default();
+        }
+
+        while let Some(difficulty) = progress.difficulty() {
+            clear_screen();
+            println!(
+                "Campaign level {} of {}: {difficulty}\nLives: {}\nScore: {}\n",
+                progress.level + 1,
+                campaign::LEVELS.len(),
+                progress.lives,
+                progress.score
+            );
+            input_handler::enter_to_continue(input)?;
+
+            let level = Settings {
+                difficulty,
+                lives: progress.lives,
+                ..settings.clone()
+            };
+            let mut state = match level.new_game(None) {
+                Ok(state) => state,
+                Err(error) => {
+                    println!("{error}");
+                    input_handler::enter_to_continue(input)?;
+                    return Ok(());
+                }
+            };
+            state.set_game_mode(GameMode::Campaign);
+
+            let choice = run(renderer, input, &mut state, json, paths)?;
+            if choice.is_some() {
+                let score = ScoreFormula::load(&paths.scoring).score(&state);
+                progress.finish_level(state.get_won(), state.get_lives(), score);
+                if !state.get_won() {
+                    println!("Out of lives. The campaign starts over from level 1.");
+                    input_handler::enter_to_continue(input)?;
+                }
+            } else {
+                progress.leave_level(state.get_lives());
+            }
+
+            if let Err(error) = progress.save(path) {
+                println!("Unable to save campaign progress: {error}");
+            }
+
+            if choice != Some(EndChoice::NewGame) {
+                return Ok(());
+            }
+        }
+
+        println!(
+            "Every level cleared! Final campaign score: {}",
+            progress.score
+        );
+        input_handler::enter_to_continue(input)
+    }
+
+    // Jump straight into a game configured elsewhere (IE, from the command line),
+    // then carry on to the menu as usual once it ends
+    pub fn play_from(state: GameState, json: bool) {
+        let mut settings = Settings {
+            difficulty: state.get_difficulty(),
+            ..Settings::default()
+        };
+
+        let (renderer, input) = (&mut ConsoleRenderer, &mut io::stdin().lock());
+        let paths = &DataPaths::default();
+        let played = play_session(renderer, input, state, &mut settings, json, paths)
+            .and_then(|paused| play_with(renderer, input, paused, settings, json, paths));
+
+        exit_on_error(played);
+    }
+
+    // Play until the game ends, returning what the player wants to do next,
+    // or None if they paused and left for the menu
+    fn run(
+        renderer: &mut dyn Renderer,
+        input: &mut dyn InputSource,
+        state: &mut GameState,
+        json: bool,
+        paths: &DataPaths,
+    ) -> Result<Option<EndChoice>, MinesweeperError> {
+        let formula = ScoreFormula::load(&paths.scoring);
+
+        // The clock starts with the first clear, as in standard minesweeper, and adds up the
+        // time since it last ticked. Resumed games pick it up from where they were paused.
+        let mut clock = state.has_started().then(Instant::now);
+        let tick = |state: &mut GameState, clock: &mut Option<Instant>| {
+            if let Some(last) = clock {
+                let now = Instant::now();
+                state.set_elapsed(state.get_elapsed() + (now - *last));
+                *last = now;
+            }
+        };
+
+        // Draw the initial game state
+        renderer.draw_game(state, &formula);
+
+        loop {
+            tick(state, &mut clock);
+
+            // Input blocks, so the clock can only be checked between moves
+            if state.check_time_limit() {
+                renderer.draw_game(state, &formula);
+            }
+
+            let game_over = state.get_game_over();
+            let won = state.get_won();
+
+            // A fatal mine can be taken back like any other move, while undos last
+            if game_over
+                && !won
+                && !state.get_timed_out()
+                && state.check_move(Action::Undo).is_ok()
+                && input_handler::read_as_bool(
+                    input,
+                    &format!(
+                        "Undo the mine for {}s on the clock? (y/n): ",
+                        UNDO_PENALTY.as_secs()
+                    ),
+                )?
+            {
+                state.apply(Action::Undo);
+                renderer.draw_game(state, &formula);
+                continue;
+            }
+
+            if game_over || won {
+                if json {
+                    println!("{}", state.result_summary().to_json());
+                }
+
+                record_stats(state, state.get_elapsed(), paths);
+                record_leaderboard(input, state, paths)?;
+                save_replay(state, paths);
+
+                // Campaign levels follow on from each other, so there's only going on or stopping
+                let choice = if state.get_game_mode() == GameMode::Campaign {
+                    input_handler::read_campaign_choice(
+                        input,
+                        "(n)ext in the campaign, or (q)uit to the menu? ",
+                    )
+                } else {
+                    input_handler::read_end_choice(
+                        input,
+                        "(r)eplay same board, (n)ew game same difficulty, (c)onfigure, or (q)uit to the menu? ",
+                    )
+                };
+
+                return choice.map(Some);
+            }
+
+            // Process console input
+            let action = process_input(input, state)?;
+
+            let Some(action) = action else {
+                tick(state, &mut clock);
+
+                // The board stays hidden and the clock stopped until the player is back
+                if state.get_input_mode() == InputMode::Pause {
+                    renderer.draw_paused(state);
+                    input_handler::enter_to_continue(input)?;
+
+                    if clock.is_some() {
+                        clock = Some(Instant::now());
+                    }
+                    state.set_input_mode(InputMode::Cancel);
+                    renderer.draw_game(state, &formula);
+                    continue;
+                }
+
+                // The player has paused and left for the menu mid-game
+                return Ok(None);
+            };
+
+            // A move made after the clock ran out doesn't count
+            tick(state, &mut clock);
+            if state.check_time_limit() {
+                continue;
+            }
+
+            // Update the game state
+            state.apply(action);
+            if clock.is_none() && state.has_started() {
+                clock = Some(Instant::now());
+            }
+
+            // Redraw game state after each update
+            renderer.draw_game(state, &formula);
+        }
+    }
+
+    // Keep the last game's moves around so it can be played back with `--replay`
+    fn save_replay(state: &GameState, paths: &DataPaths) {
+        let path = &paths.replay;
+
+        let saved = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(path, state.export_moves()));
+
+        match saved {
+            Ok(()) => println!("Replay saved to {}", path.display()),
+            Err(error) => println!("Unable to save replay: {error}"),
+        }
+    }
+
+    // Play back a recorded game one move at a time
+    pub fn play_replay(replay: &Replay) {
+        let mut state = match replay.new_game() {
+            Ok(state) => state,
+            Err(error) => {
+                println!("{error}");
+                return;
+            }
+        };
+        let formula = ScoreFormula::load(&DataPaths::default().scoring);
+        ConsoleRenderer.draw_game(&state, &formula);
+        let input = &mut io::stdin().lock();
+
+        for (step, &(index, input_mode)) in replay.moves.iter().enumerate() {
+            exit_on_error(input_handler::enter_to_continue(input));
+            replay.set_clock(&mut state, step);
+            Replay::apply(&mut state, index, input_mode);
+            ConsoleRenderer.draw_game(&state, &formula);
+
+            let place = state
+                .board
+                .coord_of(index)
+                .map_or_else(String::new, |coord| {
+                    format!("{}{}", column_label(coord.col), coord.row + 1)
+                });
+            print!(
+                "Move {} of {}: {input_mode} {place}",
+                step + 1,
+                replay.moves.len()
+            );
+            match replay.times.get(step) {
+                Some(Some(time)) => println!(" at {:.1}s", time.as_secs_f64()),
+                _ => println!(),
+            }
+        }
+
+        if replay.elapsed.is_some() {
+            exit_on_error(input_handler::enter_to_continue(input));
+            replay.stop_clock(&mut state);
+            ConsoleRenderer.draw_game(&state, &formula);
+        }
+
+        if let Some(clicks) = replay.clicks {
+            state.clicks = clicks;
+            print_efficiency(&state);
+        }
+        println!("End of replay.");
+    }
+
+    // 3BV done per click, which the end screen and replays both show
+    fn print_efficiency(state: &GameState) {
+        if let Some(efficiency) = state.efficiency() {
+            println!(
+                "Efficiency: {:.0}% ({} of {} 3BV in {} clicks)",
+                efficiency * 100.0,
+                solved_3bv(state),
+                board_3bv(state),
+                state.get_clicks()
+            );
+        }
+    }
+
+    // The key a game's stats and records are kept under. Daily boards are kept apart, so they
+    // can be compared with other players.
+    fn record_key(state: &GameState) -> String {
+        if state.get_daily() {
+            format!("daily-{}", stats::key(&state.get_difficulty()))
+        } else {
+            stats::key(&state.get_difficulty())
+        }
+    }
+
+    // Fold a finished game into the stats file, without letting a bad file end the program
+    fn record_stats(state: &GameState, elapsed: Duration, paths: &DataPaths) {
+        // With the mines on show, practice games say nothing about how well someone plays
+        if state.get_practice() {
+            println!("Practice games aren't counted in stats or leaderboards.");
+            return;
+        }
+
+        let path = &paths.stats;
+        let mut records = Stats::load(path);
+        let key = record_key(state);
+
+        records.record_game_as(
+            key.clone(),
+            state.get_won(),
+            state.get_turn_count(),
+            elapsed,
+        );
+        records.record_score(&key, ScoreFormula::load(&paths.scoring).score(state));
+        records.record_tiles(&key, state.tiles_cleared(), state.mines_hit());
+
+        if let Some(record) = records.get_key(&key) {
+            println!(
+                "Win streak: {} (best {})",
+                record.current_streak, record.best_streak
+            );
+        }
+
+        if let Err(error) = records.save(path) {
+            println!("Unable to save stats: {error}");
+        }
+    }
+
+    // Put a win on the leaderboard if it's among the best, asking who made it
+    fn record_leaderboard(
+        input: &mut dyn InputSource,
+        state: &GameState,
+        paths: &DataPaths,
+    ) -> Result<(), MinesweeperError> {
+        if !state.get_won() || state.get_practice() {
+            return Ok(());
+        }
+
+        let path = &paths.leaderboard;
+        let mut leaderboard = Leaderboard::load(path);
+        let key = record_key(state);
+
+        let time = i64::try_from(state.get_elapsed().as_millis()).unwrap_or(i64::MAX);
+        let score = ScoreFormula::load(&paths.scoring).score(state);
+        let records = [(Ranking::Time, time), (Ranking::Score, score)];
+
+        if records
+            .iter()
+            .all(|&(ranking, value)| leaderboard.place(&key, ranking, value).is_none())
+        {
+            return Ok(());
+        }
+
+        let name = input_handler::read_name(input, "New record! Enter your name: ")?;
+        for (ranking, value) in records {
+            let entry = Entry {
+                name: name.clone(),
+                value,
+            };
+            if let Some(place) = leaderboard.insert(&key, ranking, entry) {
+                println!("Number {place} on the {key} {} leaderboard", ranking.name());
+            }
+        }
+
+        if let Err(error) = leaderboard.save(path) {
+            println!("Unable to save leaderboard: {error}");
+        }
+
+        Ok(())
+    }
+
+    #[allow(clippy::needless_raw_string_hashes)]
+    fn print_title() {
+        let title_menu = r#"
+  __  __ _____ _   _ ______  _______          ________ ______ _____  ______ _____  
+ |  \/  |_   _| \ | |  ____|/ ____\ \        / /  ____|  ____|  __ \|  ____|  __ \ 
+ | \  / | | | |  \| | |__  | (___  \ \  /\  / /| |__  | |__  | |__) | |__  | |__) |
+ | |\/| | | | | . ` |  __|  \___ \  \ \/  \/ / |  __| |  __| |  ___/|  __| |  _  / 
+ | |  | |_| |_| |\  | |____ ____) |  \  /\  /  | |____| |____| |    | |____| | \ \ 
+ |_|  |_|_____|_| \_|______|_____/    \/  \/   |______|______|_|    |______|_|  \_\
+
+
+   __  ___  __  _           
+  / / | _ \ \ \| |__ _ _  _ 
+ | |  |  _/  | | / _` | || |
+ | |  |_|    | |_\__,_|\_, |
+  \_\       /_/        |__/ 
+
+   __   ___  __            __ _                   
+  / /  / __| \ \ ___ _ _  / _(_)__ _ _  _ _ _ ___ 
+ | |  | (__   | / _ \ ' \|  _| / _` | || | '_/ -_)
+ | |   \___|  | \___/_||_|_| |_\__, |\_,_|_| \___|
+  \_\        /_/               |___/              
+
+   __   ___   __       _ _   
+  / /  / _ \  \ \ _  _(_) |_ 
+ | |  | (_) |  | | || | |  _|
+ | |   \__\_\  | |\_,_|_|\__|
+  \_\         /_/            
+      "#;
+
+        println!("{title_menu}");
+    }
+
+    fn config(input: &mut dyn InputSource) -> Result<Settings, MinesweeperError> {
+        let difficulty = input_handler::read_difficulty(
+            input,
+            "Enter preferred difficulty level [(e)asy | (m)edium | (h)ard | (c)ustom] : ",
+        )?;
+        let shape = input_handler::read_shape(
+            input,
+            "Board shape [(r)ectangle | (d)iamond | (c)ross | d(o)nut] : ",
+        )?;
+        let seed = input_handler::read_seed(
+            input,
+            "Seed of a board to play, or leave blank for a random one: ",
+        )?;
+
+        let lives = input_handler::read_as_int(
+            input,
+            &format!("Lives, each one surviving a mine (1-{MAX_LIVES}): "),
+            1,
+            MAX_LIVES,
+        )?;
+        let time_limit = read_time_limit(input, &difficulty)?;
+        let undo_limit = read_undo_limit(input)?;
+        let practice = input_handler::read_as_bool(
+            input,
+            "Practice with the mines shown, leaving the game out of stats? (y/n): ",
+        )?;
+        let mines_per_tile = read_mines_per_tile(input)?;
+        // The solver reads every mine as one, so its aids are off once they stack
+        let solver_aids = mines_per_tile == 1;
+        let show_probabilities = solver_aids
+            && input_handler::read_as_bool(
+                input,
+                "Show mine probabilities on hidden tiles? (y/n): ",
+            )?;
+        let confirm_risky_clears = input_handler::read_as_bool(
+            input,
+            "Confirm clears next to unsatisfied numbers? (y/n): ",
+        )?;
+        let no_guess = solver_aids
+            && input_handler::read_as_bool(
+                input,
+                "Only deal boards that can be solved without guessing? (y/n): ",
+            )?;
+        // No-guess boards are dealt around an opening, so there's something to work from
+        let first_click_zero = no_guess
+            || input_handler::read_as_bool(
+                input,
+                "Always open up an area on the first clear? (y/n): ",
+            )?;
+        // An opening already keeps the first clear safe
+        let first_click_safe = first_click_zero
+            || input_handler::read_as_bool(input, "Keep the first clear safe from mines? (y/n): ")?;
+        let flagging_allowed = !input_handler::read_as_bool(
+            input,
+            "No-flag challenge, with flags turned off? (y/n): ",
+        )?;
+        let flag_limit = if flagging_allowed {
+            read_flag_limit(input, &difficulty)?
+        } else {
+            None
+        };
+        // A game without flags for every mine can only be won by clearing
+        let win_condition = if flagging_allowed && flag_limit.is_none() {
+            input_handler::read_win_condition(
+                input,
+                "Win by (r)evealing every safe tile, (f)lagging every mine, or (e)ither? ",
+            )?
+        } else {
+            WinCondition::RevealSafe
+        };
+        let question_marks = flagging_allowed
+            && input_handler::read_as_bool(
+                input,
+                "Leave a question mark when taking a flag away? (y/n): ",
+            )?;
+        // Nothing to flag automatically without flags
+        let auto_flag = flagging_allowed
+            && solver_aids
+            && input_handler::read_as_bool(
+                input,
+                "Flag tiles that must be mines automatically? (y/n): ",
+            )?;
+        let (theme, compact, unicode_grid) = read_display(input)?;
+
+        Ok(Settings {
+            difficulty,
+            seed,
+            lives,
+            show_probabilities,
+            confirm_risky_clears,
+            first_click_zero,
+            first_click_safe,
+            no_guess,
+            practice,
+            mines_per_tile,
+            shape,
+            auto_flag,
+            flagging_allowed,
+            flag_limit,
+            question_marks,
+            win_condition,
+            time_limit,
+            undo_limit,
+            theme,
+            unicode_grid,
+            compact,
+        })
+    }
+
+    // Tile symbols, compact mode and borders, in that order
+    fn read_display(input: &mut dyn InputSource) -> Result<(Theme, bool, bool), MinesweeperError> {
+        let emoji = input_handler::read_as_bool(input, "Use emoji tile symbols? (y/n): ")?;
+        let compact = input_handler::read_as_bool(
+            input,
+            "Compact board with one character per tile? (y/n): ",
+        )?;
+        // Borders need the room compact mode saves
+        let unicode_grid =
+            !compact && input_handler::read_as_bool(input, "Draw borders around tiles? (y/n): ")?;
+        let theme = if emoji {
+            Theme::unicode()
+        } else {
+            Theme::classic()
+        };
+
+        Ok((theme, compact, unicode_grid))
+    }
+
+    fn read_time_limit(
+        input: &mut dyn InputSource,
+        difficulty: &Difficulty,
+    ) -> Result<Option<Duration>, MinesweeperError> {
+        input_handler::read_as_bool(input, "Time attack against the clock? (y/n): ")?
+            .then(|| {
+                let suggested = difficulty.time_limit().as_secs();
+                let seconds = input_handler::read_as_int(
+                    input,
+                    &format!("Seconds allowed, {suggested} suggested for this difficulty: "),
+                    MIN_TIME_LIMIT_SECS,
+                    MAX_TIME_LIMIT_SECS,
+                )?;
+                Ok(Duration::from_secs(seconds.into()))
+            })
+            .transpose()
+    }
+
+    // A cap on flags below the mine count, so some mines have to be worked around unflagged
+    fn read_flag_limit(
+        input: &mut dyn InputSource,
+        difficulty: &Difficulty,
+    ) -> Result<Option<u32>, MinesweeperError> {
+        let (_, _, mines) = difficulty.dimensions();
+        let max = mines.saturating_sub(1);
+
+        (max > 0
+            && input_handler::read_as_bool(input, "Limit flags to fewer than the mines? (y/n): ")?)
+        .then(|| input_handler::read_as_int(input, &format!("Flags allowed (0-{max}): "), 0, max))
+        .transpose()
+    }
+
+    fn read_mines_per_tile(input: &mut dyn InputSource) -> Result<u32, MinesweeperError> {
+        input_handler::read_as_int(
+            input,
+            &format!("Mines a single tile can hold (1-{MAX_MINES_PER_TILE}): "),
+            1,
+            MAX_MINES_PER_TILE,
+        )
+    }
+
+    fn read_undo_limit(input: &mut dyn InputSource) -> Result<Option<u32>, MinesweeperError> {
+        let prompt = format!(
+            "Limit undos, which add {}s to the clock each? (y/n): ",
+            UNDO_PENALTY.as_secs()
+        );
+
+        input_handler::read_as_bool(input, &prompt)?
+            .then(|| {
+                input_handler::read_as_int(
+                    input,
+                    &format!("Undos allowed per game (0-{MAX_UNDOS}): "),
+                    0,
+                    MAX_UNDOS,
+                )
+            })
+            .transpose()
+    }
+
+    fn menu(
+        renderer: &mut dyn Renderer,
+        input: &mut dyn InputSource,
+        paused: bool,
+        difficulty: &Difficulty,
+        paths: &DataPaths,
+    ) -> Result<GameMode, MinesweeperError> {
+        renderer.draw_menu(
+            paused,
+            difficulty,
+            Stats::load(&paths.stats).get(difficulty),
+        );
+
+        input_handler::read_game_mode(
+            input,
+            if paused {
+                "Enter choice [(r)esume | (p)lay | (d)aily | c(a)mpaign | (c)onfigure | (s)tats | (l)eaderboard | (q)uit] : "
+            } else {
+                "Enter choice [(p)lay | (d)aily | c(a)mpaign | (c)onfigure | (s)tats | (l)eaderboard | (q)uit] : "
+            },
+        )
+    }
+
+    // Today's daily seed. Days roll over at midnight UTC, so it's the same everywhere at once.
+    #[must_use]
+    pub fn today_seed() -> u64 {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        daily_seed(since_epoch.as_secs() / 86_400)
+    }
+
+    // Ask for a move until a valid one is entered. Returns None when the player pauses for the menu.
+    fn process_input(
+        input: &mut dyn InputSource,
+        state: &mut GameState,
+    ) -> Result<Option<Action>, MinesweeperError> {
+        loop {
+            println!("Select a hidden tile\n");
+
+            let (column, row) = input_handler::read_column_row(
+                input,
+                "Enter column and row: ",
+                1,
+                state.get_width(),
+                state.get_height(),
+            )?;
+
+            let input_mode = input_handler::read_input_mode(
+                input,
+                "(C)lear, (F)lag, c(H)ord around a number, (S)afe reveal around a number \
+                 (unlike a chord, only clears tiles the numbers prove safe), h(I)nt, (U)ndo the last move, (R)edo, cancel the selection (X), (P)ause, or pause and return to (M)enu? ",
+            )?;
+
+            if input_mode == InputMode::Cancel {
+                continue;
+            }
+
+            if input_mode == InputMode::Menu {
+                state.set_game_mode(GameMode::Menu);
+                return Ok(None);
+            }
+
+            if input_mode == InputMode::Pause {
+                state.set_input_mode(InputMode::Pause);
+                return Ok(None);
+            }
+
+            let Some(action) = to_action(input_mode, Coord::new(row, column)) else {
+                continue;
+            };
+
+            if let Err(error) = state.check_move(action) {
+                println!("{error}.");
+                continue;
+            }
+
+            // The rest are allowed, but worth a second thought before clearing
+            let index = (row * state.get_width() + column) as usize;
+
+            if input_mode == InputMode::Clear {
+                if matches!(state.get_tile(index), Tile::Hidden { flagged: true, .. })
+                    && !input_handler::read_as_bool(
+                        input,
+                        "Tile is flagged, clear anyways? (Y/n): ",
+                    )?
+                {
+                    continue;
+                }
+
+                // Guard against mistyped coordinates landing next to a number still missing mines
+                if state.get_confirm_risky_clears()
+                    && is_risky_clear(state, index)
+                    && !input_handler::read_as_bool(
+                        input,
+                        "A neighboring number still needs mines, clear anyways? (y/n): ",
+                    )?
+                {
+                    continue;
+                }
+            }
+
+            return Ok(Some(action));
+        }
+    }
+
+    fn clear_screen() {
+        let mut stdout = stdout();
+
+        execute!(stdout, terminal::Clear(terminal::ClearType::All))
+            .expect("Failed to clear screen");
+    }
+
+    // Hand the terminal back the way we found it, even if a raw mode cursor session was cut short
+    fn restore_terminal() {
+        // Fails when raw mode was never enabled, which is fine
+        let _ = terminal::disable_raw_mode();
+
+        let mut stdout = stdout();
+        execute!(stdout, cursor::Show, style::ResetColor).expect("Failed to restore terminal");
+    }
+
+    // Lines taken up around the grid by the counters, messages, and prompts
+    const RESERVED_LINES: u16 = 14;
+
+    // The columns and rows of the board that fit in a terminal of the given size, kept as
+    // close to centered on the selected tile as the board's edges allow.
+    // None when not even a single tile fits.
+    #[allow(clippy::cast_possible_truncation)] // Boards are indexed by u32 coordinates
+    fn visible_window(
+        state: &GameState,
+        (terminal_columns, terminal_rows): (u16, u16),
+        style: GridStyle,
+    ) -> Option<(Range<u32>, Range<u32>)> {
+        let (width, height) = (state.get_width(), state.get_height());
+
+        // Work backwards from the space an empty grid needs, and what each extra tile adds. Column
+        // labels can take more lines on wider boards, so the rows are measured at full width.
+        let (base_columns, base_rows) = (grid_size(0, 0, style).0, grid_size(width, 0, style).1);
+        let per_column = grid_size(1, 0, style).0 - base_columns;
+        let per_row = grid_size(width, 1, style).1 - base_rows;
+
+        let columns = u32::from(terminal_columns).saturating_sub(base_columns) / per_column;
+        let rows = u32::from(terminal_rows).saturating_sub(u32::from(RESERVED_LINES) + base_rows)
+            / per_row;
+
+        if columns == 0 || rows == 0 {
+            return None;
+        }
+
+        let (column, row) = state.get_selection().map_or((0, 0), |selection| {
+            (selection.coord().col, selection.coord().row)
+        });
+
+        Some((span(column, columns, width), span(row, rows, height)))
+    }
+
+    // Up to `length` positions out of `total`, centered on `focus` without running off either end
+    fn span(focus: u32, length: u32, total: u32) -> Range<u32> {
+        let length = length.min(total);
+        let start = focus.saturating_sub(length / 2).min(total - length);
+
+        start..start + length
+    }
+
+    // Box drawing characters only come out right on terminals speaking UTF-8.
+    // Windows terminals generally cope without advertising it.
+    fn supports_unicode() -> bool {
+        cfg!(windows)
+            || ["LC_ALL", "LC_CTYPE", "LANG"].iter().any(|name| {
+                env::var(name).is_ok_and(|value| {
+                    let value = value.to_lowercase();
+                    value.contains("utf-8") || value.contains("utf8")
+                })
+            })
+    }
+
+    // Everything the game loop shows. `ConsoleRenderer` draws to the terminal; another
+    // implementation can stand in for it through `play_with_frontend`. Prompts and messages
+    // still go to stdout.
+    pub trait Renderer {
+        // The counters shown above the board, starting a fresh screen
+        fn draw_status(&mut self, state: &GameState);
+        fn draw_board(&mut self, state: &GameState);
+        // How the game ended, once it has, scored by the formula the game's records use
+        fn draw_result(&mut self, state: &GameState, formula: &ScoreFormula);
+        // The title screen, before asking for a menu choice, with the record on this difficulty
+        fn draw_menu(&mut self, paused: bool, difficulty: &Difficulty, record: Option<&Record>);
+        // Shown in place of the board while the game is paused, so it can't be studied
+        fn draw_paused(&mut self, state: &GameState);
+
+        fn draw_game(&mut self, state: &GameState, formula: &ScoreFormula) {
+            self.draw_status(state);
+            self.draw_board(state);
+            self.draw_result(state, formula);
+        }
+    }
+
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct ConsoleRenderer;
+
+    impl Renderer for ConsoleRenderer {
+        fn draw_status(&mut self, state: &GameState) {
+            clear_screen();
+
+            let mine_count = state.get_mine_count();
+            let turn_count = state.get_turn_count() + 1;
+
+            let lives = state.get_lives();
+
+            // Rounded down, so 100% only shows once every safe tile is clear
+            let progress = (state.progress() * 100.0).floor();
+            let remaining_safe = state.remaining_safe();
+
+            if state.get_daily() {
+                let seed = state.get_seed().unwrap_or_default();
+                println!(
+                    "Daily board for {}-{:02}-{:02} (seed {seed})",
+                    seed / 10_000,
+                    seed / 100 % 100,
+                    seed % 100
+                );
+            }
+
+            if state.get_practice() {
+                println!("Practice game: mines are shown, and it won't count in stats");
+            }
+
+            if state.get_wrap() {
+                println!("Wrapping board: each edge touches the one opposite");
+            }
+
+            if state.get_shape() != Shape::Rectangle {
+                println!("Board shape: {}", state.get_shape().name());
+            }
+
+            if let Some(remaining) = state.time_remaining() {
+                println!("Time left: {}s", remaining.as_secs());
+            }
+
+            if let Some(undos) = state.undos_left() {
+                println!("Undos left: {undos}");
+            }
+
+            if state.get_flag_limit().is_some() {
+                println!("Flags left: {}", state.flags_left());
+            }
+
+            // Stacks are dealt on the first clear, so the total settles then
+            if state.get_mines_per_tile() > 1 {
+                println!(
+                    "Up to {} mines per tile, {} in all; flag a tile again to mark more",
+                    state.get_mines_per_tile(),
+                    state.total_mines()
+                );
+            }
+
+            let elapsed = state.get_elapsed().as_secs();
+
+            println!(
+                "Turns: {turn_count}\nTime: {elapsed}s\nMines: {mine_count}\nSafe tiles left: {remaining_safe}\nLives: {lives}\nProgress: {progress:.0}%\n"
+            );
+        }
+
+        fn draw_board(&mut self, state: &GameState) {
+            let (width, height) = (state.get_width(), state.get_height());
+            // Box drawing falls back to the plain grid where it wouldn't come out right
+            let style = match state.grid_style() {
+                GridStyle::Boxed if !supports_unicode() => GridStyle::Plain,
+                style => style,
+            };
+
+            match terminal::size() {
+                // Not drawing to a terminal, so there's no edge to run past
+                Err(_) => print!("{}", state.render_window(0..width, 0..height, style)),
+                Ok(size) => match visible_window(state, size, style) {
+                    Some((columns, rows)) if columns == (0..width) && rows == (0..height) => {
+                        print!("{}", state.render_window(columns, rows, style));
+                    }
+                    Some((columns, rows)) => {
+                        println!(
+                            "Showing columns {}-{} and rows {}-{} of the board",
+                            column_label(columns.start),
+                            column_label(columns.end - 1),
+                            rows.start + 1,
+                            rows.end
+                        );
+                        print!("{}", state.render_window(columns, rows, style));
+                    }
+                    None => {
+                        let (columns, rows) = grid_size(width, height, style);
+                        println!(
+                            "Board too large for terminal (needs {columns}x{})",
+                            rows + u32::from(RESERVED_LINES)
+                        );
+                    }
+                },
+            }
+        }
+
+        fn draw_result(&mut self, state: &GameState, formula: &ScoreFormula) {
+            if state.get_timed_out() {
+                println!("Time's up!");
+            } else if state.get_game_over() {
+                // Consider adding end of game stats
+                println!("Game over!");
+            }
+
+            if state.get_won() {
+                println!("Congratulations, you found all of the mines!");
+                println!("Cleared in {:.1}s", state.get_elapsed().as_secs_f32());
+            }
+
+            if state.get_game_over() || state.get_won() {
+                println!(
+                    "Score: {} ({} mistakes)",
+                    formula.score(state),
+                    scoring::mistakes(state)
+                );
+
+                println!("3BV: {}", board_3bv(state));
+                print_efficiency(state);
+
+                // Anyone entering this seed with the same difficulty gets the same board
+                if let Some(seed) = state.get_seed() {
+                    println!("Board seed: {seed}");
+                }
+
+                if state.get_hints_used() > 0 {
+                    println!("Hints used: {}", state.get_hints_used());
+                }
+            }
+        }
+
+        fn draw_paused(&mut self, state: &GameState) {
+            clear_screen();
+            print_title();
+
+            println!(
+                "Paused at {}s, with the board hidden until you're back.\n",
+                state.get_elapsed().as_secs()
+            );
+        }
+
+        fn draw_menu(&mut self, _paused: bool, difficulty: &Difficulty, record: Option<&Record>) {
+            clear_screen();
+            print_title();
+
+            // Nothing to show before the first game on this difficulty
+            if let Some(record) = record {
+                println!(
+                    "{difficulty} win streak: {} (best {})\n",
+                    record.current_streak, record.best_streak
+                );
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn replaying_keeps_the_board_layout() {
+            let settings = Settings {
+                difficulty: Difficulty::Medium,
+                ..Settings::default()
+            };
+            let first = settings.new_game(None).unwrap();
+            let replayed = settings.new_game(first.get_seed()).unwrap();
+
+            assert_eq!(first.board.tiles, replayed.board.tiles);
+        }
+
+        #[test]
+        fn windows_large_boards_around_the_selected_tile() {
+            let layout =
+                format!("*{}\n", ".".repeat(25)) + &format!("{}\n", ".".repeat(26)).repeat(39);
+            let mut state = GameState::from_layout(&layout).unwrap();
+
+            // Big enough for the whole board
+            assert_eq!(
+                Some((0..26, 0..40)),
+                visible_window(&state, (100, 60), GridStyle::Plain)
+            );
+
+            // Room for 10 columns and 9 rows, starting from the top left
+            assert_eq!(
+                Some((0..10, 0..9)),
+                visible_window(&state, (35, 24), GridStyle::Plain)
+            );
+
+            state.set_selected(20 * 26 + 13);
+            assert_eq!(
+                Some((8..18, 16..25)),
+                visible_window(&state, (35, 24), GridStyle::Plain)
+            );
+
+            // Held against the far edges rather than scrolling past them
+            state.set_selected(39 * 26 + 25);
+            assert_eq!(
+                Some((16..26, 31..40)),
+                visible_window(&state, (35, 24), GridStyle::Plain)
+            );
+
+            assert_eq!(None, visible_window(&state, (7, 24), GridStyle::Plain));
+            assert_eq!(None, visible_window(&state, (80, 15), GridStyle::Plain));
+
+            // Borders take up room too
+            assert_eq!(
+                Some((16..26, 35..40)),
+                visible_window(&state, (46, 26), GridStyle::Boxed)
+            );
+
+            // While single characters fit much more
+            assert_eq!(
+                Some((0..26, 14..40)),
+                visible_window(&state, (30, 41), GridStyle::Compact)
+            );
+
+            // A 30x30 board fits compactly, with a second line for the labels past Z
+            let state =
+                GameState::from_layout(&format!("*{}\n", ".".repeat(29)).repeat(30)).unwrap();
+            let lines = 32 + RESERVED_LINES;
+            assert_eq!(
+                Some((0..30, 0..30)),
+                visible_window(&state, (34, lines), GridStyle::Compact)
+            );
+            assert_eq!(
+                Some((0..30, 0..29)),
+                visible_window(&state, (34, lines - 1), GridStyle::Compact)
+            );
+        }
+
+        #[test]
+        fn restarts_boards_without_a_seed() {
+            let mut state = GameState::from_layout("*..\n...").unwrap();
+            state.reveal(5);
+            state.toggle_flag(0);
+
+            let restarted = Settings::default().restart(&state).unwrap();
+
+            assert_eq!(
+                GameState::from_layout("*..\n...").unwrap().board.tiles,
+                restarted.board.tiles
+            );
+            assert_eq!(1, restarted.get_mine_count());
+            assert!(!restarted.get_won());
+        }
+
+        // Writes down what it was asked to draw
+        #[derive(Default)]
+        struct Recorder(Vec<String>);
+
+        impl Renderer for Recorder {
+            fn draw_status(&mut self, state: &GameState) {
+                self.0.push(format!("status {}", state.get_turn_count()));
+            }
+
+            fn draw_board(&mut self, state: &GameState) {
+                self.0.push(state.render_window(
+                    0..state.get_width(),
+                    0..state.get_height(),
+                    GridStyle::Compact,
+                ));
+            }
+
+            fn draw_result(&mut self, state: &GameState, formula: &ScoreFormula) {
+                self.0.push(format!("won {}", state.get_won()));
+                if state.get_won() {
+                    self.0.push(format!("score {}", formula.score(state)));
+                }
+            }
+
+            fn draw_menu(
+                &mut self,
+                paused: bool,
+                _difficulty: &Difficulty,
+                record: Option<&Record>,
+            ) {
+                self.0.push(format!("menu {paused}"));
+                if let Some(record) = record {
+                    self.0.push(format!("streak {}", record.current_streak));
+                }
+            }
+
+            fn draw_paused(&mut self, state: &GameState) {
+                self.0.push(format!("paused {}", state.get_turn_count()));
+            }
+        }
+
+        #[test]
+        fn draws_games_through_any_renderer() {
+            let mut state = GameState::from_layout("*.").unwrap();
+            state.reveal(1);
+
+            let mut recorder = Recorder::default();
+            recorder.draw_game(&state, &ScoreFormula::default());
+
+            assert_eq!(
+                vec!["status 1", "    AB\n  1 ⚑1\n", "won true", "score 600"],
+                recorder.0
+            );
+        }
+
+        // Somewhere out of the way for anything a test game keeps, named for the test
+        fn scratch_paths(name: &str) -> DataPaths {
+            let dir = env::temp_dir().join(format!("minesweeper-{name}-{}", process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            DataPaths::in_dir(&dir)
+        }
+
+        #[test]
+        fn clock_waits_for_the_first_clear() {
+            let mut state = GameState::from_layout("*..*").unwrap();
+            // A flag, then leave for the menu before clearing anything
+            let mut input: &[u8] = b"a1\nf\na1\nm\n";
+
+            let paths = scratch_paths("clock");
+
+            std::thread::sleep(Duration::from_millis(5));
+            assert_eq!(
+                Ok(None),
+                run(
+                    &mut Recorder::default(),
+                    &mut input,
+                    &mut state,
+                    false,
+                    &paths
+                )
+            );
+
+            assert!(!state.has_started());
+            assert_eq!(Duration::ZERO, state.get_elapsed());
+
+            let mut input: &[u8] = b"b1\nc\na1\nm\n";
+            assert_eq!(
+                Ok(None),
+                run(
+                    &mut Recorder::default(),
+                    &mut input,
+                    &mut state,
+                    false,
+                    &paths
+                )
+            );
+
+            assert!(state.has_started());
+            assert!(state.get_elapsed() > Duration::ZERO);
+        }
+
+        #[test]
+        fn pauses_without_showing_the_board() {
+            let mut state = GameState::from_layout("*..*").unwrap();
+            // Pause, come back, and clear a tile, then pause again before leaving for the menu
+            let mut input: &[u8] = b"a1\np\n\nb1\nc\na1\np\n\na1\nm\n";
+            let mut recorder = Recorder::default();
+
+            let paths = scratch_paths("pause");
+
+            assert_eq!(
+                Ok(None),
+                run(&mut recorder, &mut input, &mut state, false, &paths)
+            );
+
+            let paused = recorder.0.iter().position(|drawn| drawn == "paused 0");
+            assert_eq!(Some(3), paused);
+            // Drawn again once the player is back
+            assert_eq!("status 0", recorder.0[4]);
+            assert_eq!(Some(1), state.get_tile(1).hint());
+            assert_eq!(
+                1,
+                recorder
+                    .0
+                    .iter()
+                    .filter(|drawn| *drawn == "paused 1")
+                    .count()
+            );
+            assert!(input.is_empty());
+        }
+
+        #[test]
+        fn keeps_unfinished_games_for_resuming() {
+            let paths = scratch_paths("resume");
+            let mut settings = Settings::default();
+            let mut state = GameState::from_layout("*..").unwrap();
+            assert!(state.toggle_flag(0));
+            state.set_elapsed(Duration::from_secs(30));
+
+            // Leave for the menu part way through
+            let mut input: &[u8] = b"b1\nm\n";
+            let paused = play_session(
+                &mut Recorder::default(),
+                &mut input,
+                state,
+                &mut settings,
+                false,
+                &paths,
+            )
+            .unwrap()
+            .unwrap();
+
+            assert_eq!(Duration::from_secs(30), paused.get_elapsed());
+            assert_eq!(0, paused.get_mine_count());
+
+            // Resumed from the menu and won, then a name for the leaderboard, and out through the
+            // end of game prompt and the menu
+            let mut input: &[u8] = b"r\nc1\nc\ntester\nq\nq\n";
+            let mut recorder = Recorder::default();
+
+            assert_eq!(
+                Ok(()),
+                play_with(
+                    &mut recorder,
+                    &mut input,
+                    Some(paused),
+                    settings,
+                    false,
+                    &paths
+                )
+            );
+            assert_eq!("menu true", recorder.0[0]);
+            assert!(recorder.0.iter().any(|drawn| drawn == "won true"));
+            assert_eq!(Some("menu false"), recorder.0.last().map(String::as_str));
+            assert!(input.is_empty());
+
+            let _ = fs::remove_dir_all(paths.stats.parent().unwrap());
+        }
+
+        #[test]
+        fn draws_scores_and_streaks_from_the_given_paths() {
+            let paths = scratch_paths("render");
+            fs::create_dir_all(paths.scoring.parent().unwrap()).unwrap();
+            fs::write(
+                &paths.scoring,
+                "per_3bv 0\nwin_bonus 7\nper_second 0\nper_mistake 0\n",
+            )
+            .unwrap();
+
+            let state = GameState::from_layout("*..").unwrap();
+            let mut settings = Settings {
+                difficulty: state.get_difficulty(),
+                ..Settings::default()
+            };
+
+            // Won on the first clear, then a name for the leaderboard and out to the menu
+            let mut input: &[u8] = b"c1\nc\ntester\nq\n";
+            let mut recorder = Recorder::default();
+            let paused = play_session(
+                &mut recorder,
+                &mut input,
+                state,
+                &mut settings,
+                false,
+                &paths,
+            );
+            assert!(matches!(paused, Ok(None)));
+            assert!(recorder.0.iter().any(|drawn| drawn == "score 7"));
+            let record = Stats::load(&paths.stats).get(&settings.difficulty).cloned();
+            assert_eq!(Some(Some(7)), record.map(|record| record.best_score));
+
+            let mut input: &[u8] = b"q\n";
+            let mut recorder = Recorder::default();
+            assert_eq!(
+                Ok(()),
+                play_with(&mut recorder, &mut input, None, settings, false, &paths)
+            );
+            assert_eq!(vec!["menu false", "streak 1"], recorder.0);
+
+            let _ = fs::remove_dir_all(paths.stats.parent().unwrap());
+        }
+
+        #[test]
+        fn ends_the_session_when_input_runs_out() {
+            let paths = scratch_paths("frontend");
+            let state = GameState::from_layout("*..*").unwrap();
+            fs::create_dir_all(paths.saved_game.parent().unwrap()).unwrap();
+            state.save_to(&paths.saved_game).unwrap();
+
+            // Into the saved game, then out of input mid-move
+            let mut input: &[u8] = b"r\na1\n";
+            let mut recorder = Recorder::default();
+
+            assert_eq!(
+                Err(MinesweeperError::Input(io::ErrorKind::UnexpectedEof)),
+                play_with_frontend(&mut recorder, &mut input, false, &paths)
+            );
+            // The save came from the paths given, and was taken off disk for resuming
+            assert_eq!(Some("menu true"), recorder.0.first().map(String::as_str));
+            assert!(!paths.saved_game.exists());
+
+            let _ = fs::remove_dir_all(paths.saved_game.parent().unwrap());
+        }
+
+        #[test]
+        fn reads_moves_from_any_input_source() {
+            let mut state = GameState::from_layout("*.").unwrap();
+            // Off the board first, then a flag on the mine
+            let mut input: &[u8] = b"c9\na1\nf\n";
+
+            let action = process_input(&mut input, &mut state);
+
+            assert_eq!(Ok(Some(Action::Flag(Coord::new(0, 0)))), action);
+            assert!(input.is_empty());
+        }
+
+        #[test]
+        fn explains_refused_moves_and_asks_again() {
+            let mut state = GameState::from_layout(".*.").unwrap();
+            state.reveal(2);
+            // A revealed tile, then a chord on the unflagged 1, then an undo
+            let mut input: &[u8] = b"c1\nc\nc1\ns\na1\nb\n";
+
+            let action = process_input(&mut input, &mut state);
+
+            assert_eq!(Ok(Some(Action::Undo)), action);
+        }
+
+        #[test]
+        fn undo_takes_back_the_last_move() {
+            let mut state = GameState::from_layout(".*.").unwrap();
+            state.reveal(2);
+            // A cancelled selection first, which asks for a tile again
+            let mut input: &[u8] = b"a1\nx\na1\nu\n";
+
+            let action = process_input(&mut input, &mut state).unwrap().unwrap();
+            state.apply(action);
+
+            assert!(input.is_empty());
+            assert_eq!(
+                Tile::Hidden {
+                    has_mine: false,
+                    flagged: false,
+                    questioned: false
+                },
+                *state.get_tile(2)
+            );
+            assert!(state.can_redo());
+        }
+
+        #[test]
+        fn resolves_far_corners_of_extreme_boards() {
+            // Tall and narrow, then short and as wide as the labels go
+            for (width, height, corner) in [
+                (2, 30, "B30"),
+                (1, 30, "A30"),
+                (30, 2, "AD2"),
+                (crate::MAX_WIDTH, 2, "AZ2"),
+                (crate::MAX_WIDTH, 1, "az1"),
+            ] {
+                let mut layout = vec![".".repeat(width as usize); height as usize].join("\n");
+                layout.replace_range(layout.len() - 1.., "*");
+                let mut state = GameState::from_layout(&layout).unwrap();
+
+                // A column past the edge first, then a flag on the far corner
+                let past = format!("{}1", column_label(width));
+                let script = format!("{past}\n{corner}\nf\n");
+                let mut input = script.as_bytes();
+
+                let action = process_input(&mut input, &mut state).unwrap().unwrap();
+                assert_eq!(
+                    Action::Flag(Coord::new(height - 1, width - 1)),
+                    action,
+                    "{corner}"
+                );
+
+                state.apply(action);
+                let corner_index = (width * height - 1) as usize;
+                assert!(
+                    matches!(
+                        state.get_tile(corner_index),
+                        Tile::Hidden {
+                            has_mine: true,
+                            flagged: true,
+                            ..
+                        }
+                    ),
+                    "{corner}"
+                );
+            }
+        }
+    }
+}
+
+// The rules of the game: setting up boards, reveals, flags, flood fills, and winning or losing.
+// Nothing here reads input or writes to the terminal; `game_loop` is one frontend built on it.
+pub mod engine {
+
+    use super::{
+        input_handler::InputMode, solver, Action, Adjacency, BoardError, Coord, Difficulty,
+        GameEvent, GameOutcome, GameState, MinesweeperError, Shape, Tile, WinCondition,
+    };
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+    use std::rc::Rc;
+
+    /// Build a ready to play state, optionally pinned to a seed so the board can be recreated.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BoardError` if the difficulty describes a board that can't be played.
+    pub fn new_game(difficulty: &Difficulty, seed: Option<u64>) -> Result<GameState, BoardError> {
+        new_shaped_game(difficulty, seed, Shape::Rectangle)
+    }
+
+    /// Like `new_game`, with the board cut down to `shape`. Mines only go on tiles inside it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BoardError` if the difficulty describes a board that can't be played, or has
+    /// too many mines for the tiles left inside the shape.
+    pub fn new_shaped_game(
+        difficulty: &Difficulty,
+        seed: Option<u64>,
+        shape: Shape,
+    ) -> Result<GameState, BoardError> {
+        let mut state = GameState::new();
+        state.set_seed(seed);
+        state.shape = shape;
+        setup(&mut state, difficulty)?;
+
+        Ok(state)
+    }
+
+    /// Like `new_game`, with the board's seed drawn from `rng`, so a caller's own generator
+    /// decides the board. The seed is kept on the state, so the board can still be recreated
+    /// and replayed without the generator.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BoardError` if the difficulty describes a board that can't be played.
+    pub fn new_game_with_rng(
+        difficulty: &Difficulty,
+        rng: &mut impl Rng,
+    ) -> Result<GameState, BoardError> {
+        new_game(difficulty, Some(rng.gen()))
+    }
+
+    // Move any mine off the clicked tile, and with `opening` its neighbors too, so the first
+    // clear is safe or opens up an area. Each goes to a safe tile elsewhere picked with the
+    // board's seed, so replays match. Dense boards may not have room for them all; the clicked
+    // tile itself comes first, and can fall back on a safe neighbor.
+    pub(crate) fn open_first_click(state: &mut GameState, index: usize, opening: bool) {
+        let mut neighborhood = vec![index];
+        if opening {
+            neighborhood.extend_from_slice(find_neighbors(state, index));
+        }
+
+        let mut elsewhere: Vec<usize> = (0..state.board.tiles.len())
+            .filter(|&tile| !neighborhood.contains(&tile) && is_free(state, tile))
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(state.get_seed().unwrap_or_default() ^ index as u64);
+
+        for (position, &tile) in neighborhood.iter().enumerate() {
+            if !state.get_tile(tile).has_mine() {
+                continue;
+            }
+
+            let destination = if elsewhere.is_empty() {
+                // Only worth giving up an opening to keep the clicked tile itself safe
+                if position > 0 {
+                    break;
+                }
+
+                match neighborhood[1..]
+                    .iter()
+                    .copied()
+                    .find(|&neighbor| is_free(state, neighbor))
+                {
+                    Some(neighbor) => neighbor,
+                    None => break,
+                }
+            } else {
+                elsewhere.swap_remove(rng.gen_range(0..elsewhere.len()))
+            };
+
+            move_mine(state, tile, destination);
+        }
+
+        compute_hints(state);
+    }
+
+    // A tile a mine could be moved onto: in the board's shape, with no mine of its own
+    fn is_free(state: &GameState, index: usize) -> bool {
+        let tile = state.get_tile(index);
+        tile.is_active() && !tile.has_mine()
+    }
+
+    // Deals tried for a no-guess board before settling for the last one
+    const NO_GUESS_ATTEMPTS: u32 = 1000;
+
+    // Re-deal the mines around an opening at `index` until the solver can clear the whole board
+    // from there by logic alone. Deals are drawn from the board's seed, so replays match. Boards
+    // too crowded to ever be solved that way keep the last deal after NO_GUESS_ATTEMPTS.
+    pub(crate) fn deal_no_guess(state: &mut GameState, index: usize) {
+        if solver::solvable_from(state, index) {
+            return;
+        }
+
+        let mines = state.get_mines() as usize;
+        let tiles = state.board.tiles.len();
+        let active = state
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| tile.is_active())
+            .count();
+
+        // Only keep the neighbors clear too if there's room for the mines elsewhere
+        let mut neighborhood = vec![index];
+        if active - mines > find_neighbors(state, index).len() {
+            neighborhood.extend_from_slice(find_neighbors(state, index));
+        }
+
+        let mut elsewhere: Vec<usize> = (0..tiles)
+            .filter(|&tile| !neighborhood.contains(&tile) && state.get_tile(tile).is_active())
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(
+            state.get_seed().unwrap_or_default().rotate_left(32) ^ index as u64,
+        );
+
+        for _ in 0..NO_GUESS_ATTEMPTS {
+            elsewhere.shuffle(&mut rng);
+
+            for tile in 0..tiles {
+                if let Tile::Hidden {
+                    flagged,
+                    questioned,
+                    ..
+                } = *state.get_tile(tile)
+                {
+                    state.set_tile(
+                        tile,
+                        Tile::Hidden {
+                            has_mine: elsewhere[..mines].contains(&tile),
+                            flagged,
+                            questioned,
+                        },
+                    );
+                }
+            }
+            compute_hints(state);
+
+            if solver::solvable_from(state, index) {
+                return;
+            }
+        }
+    }
+
+    // Give each mined tile between 1 and `mines_per_tile` mines, drawn from the board's seed and
+    // the first clear so replays match
+    pub(crate) fn stack_mines(state: &mut GameState, index: usize) {
+        let mut rng = StdRng::seed_from_u64(
+            state.get_seed().unwrap_or_default().rotate_left(16) ^ index as u64,
+        );
+        let most = u8::try_from(state.mines_per_tile).unwrap_or(u8::MAX);
+
+        state.mine_counts = state
+            .board
+            .tiles
+            .iter()
+            .map(|tile| {
+                if tile.has_mine() {
+                    rng.gen_range(1..=most)
+                } else {
+                    0
+                }
+            })
+            .collect();
+
+        compute_hints(state);
+    }
+
+    // Swap the mine on one hidden tile onto another, leaving any flags where they are
+    fn move_mine(state: &mut GameState, from: usize, to: usize) {
+        // A stack of mines moves as one
+        if !state.mine_counts.is_empty() {
+            state.mine_counts[to] = state.mine_counts[from];
+            state.mine_counts[from] = 0;
+        }
+
+        for (index, has_mine) in [(from, false), (to, true)] {
+            if let Tile::Hidden {
+                flagged,
+                questioned,
+                ..
+            } = *state.get_tile(index)
+            {
+                state.set_tile(
+                    index,
+                    Tile::Hidden {
+                        has_mine,
+                        flagged,
+                        questioned,
+                    },
+                );
+            }
+        }
+    }
+
+    fn place_mines(state: &mut GameState) {
+        let num_mines = state.get_mines() as usize;
+
+        // Generate an array of the indices of every tile inside the board's shape
+        let mut indices: Vec<usize> = (0..state.board.tiles.len())
+            .filter(|&index| state.get_tile(index).is_active())
+            .collect();
+        let total_tiles = indices.len();
+
+        // Fisher-Yates shuffle algorithm
+        // idea to use this algorithm came from the following stack overflow question:
+        // https://stackoverflow.com/questions/28891084/minesweeper-mine-generation-algorithm
+        // Boards without a requested seed get a random one, so every board can be recreated
+        let seed = state.get_seed().unwrap_or_else(rand::random);
+        state.set_seed(Some(seed));
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        for i in (1..total_tiles).rev() {
+            let j = rng.gen_range(0..=i);
+            indices.swap(i, j);
+        }
+
+        // Place mines in the first `num_mines` positions of the shuffled indices. The shuffle is
+        // a permutation, so these are all different tiles and every one gets a mine.
+        for &mine_index in indices.iter().take(num_mines) {
+            let flagged = matches!(
+                state.get_tile(mine_index),
+                Tile::Hidden { flagged: true, .. }
+            );
+
+            state.set_tile(
+                mine_index,
+                Tile::Hidden {
+                    has_mine: true,
+                    flagged,
+                    questioned: false,
+                },
+            );
+        }
+
+        let placed = state
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| tile.has_mine())
+            .count();
+        assert_eq!(num_mines, placed, "placed the wrong number of mines");
+
+        compute_hints(state);
+    }
+
+    // A seed typed in by a player. Numbers are taken as they are, so printed seeds can be entered
+    // back; anything else is hashed with 64 bit FNV-1a, which gives the same seed everywhere.
+    #[must_use]
+    pub fn seed_from_text(text: &str) -> u64 {
+        let text = text.trim();
+
+        text.parse().unwrap_or_else(|_| {
+            text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+        })
+    }
+
+    // The date as YYYYMMDD, given whole days since 1970-01-01. Uses Howard Hinnant's
+    // `civil_from_days` algorithm, so there's no need for a date library.
+    #[must_use]
+    pub fn daily_seed(days: u64) -> u64 {
+        // Shift the epoch to 0000-03-01, so leap days fall at the end of each year
+        let days = days + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+        year * 10_000 + month * 100 + day
+    }
+
+    pub(crate) fn setup(state: &mut GameState, difficulty: &Difficulty) -> Result<(), BoardError> {
+        let (width, height, num_mines) = difficulty.dimensions();
+        state.board_setup(width, height, num_mines)?;
+
+        let number_of_tiles = state.get_height() * state.get_width();
+        let shape = state.get_shape();
+
+        for index in 0..number_of_tiles as usize {
+            if shape.contains(width, height, Coord::from_index(index, width)) {
+                state.add_tile(Tile::Hidden {
+                    has_mine: false,
+                    flagged: false,
+                    questioned: false,
+                });
+            } else {
+                state.add_tile(Tile::Inactive);
+            }
+        }
+
+        // The shape has to leave a safe tile, as the rectangle does
+        let active = state
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| tile.is_active())
+            .count();
+        let active = u32::try_from(active).unwrap_or(u32::MAX);
+        if num_mines >= active {
+            return Err(BoardError::TooManyMines {
+                mines: num_mines,
+                tiles: active,
+            });
+        }
+
+        place_mines(state);
+
+        Ok(())
+    }
+
+    // Look up a tile's neighbors in the table built by board_setup
+    pub(crate) fn find_neighbors(state: &GameState, index: usize) -> &[usize] {
+        state.neighbors(index)
+    }
+
+    // Every tile's neighbors, indexed by tile
+    pub(crate) fn neighbor_table(
+        width: u32,
+        height: u32,
+        wrap: bool,
+        adjacency: Adjacency,
+    ) -> Rc<[Vec<usize>]> {
+        (0..(width * height) as usize)
+            .map(|index| compute_neighbors(width, height, wrap, adjacency, index))
+            .collect()
+    }
+
+    // Triangles alternate up and down along rows and columns alike, so an odd run would bring a
+    // triangle round to one facing the same way, and neighbors would stop being mutual
+    pub(crate) fn wraps_evenly(width: u32, height: u32, adjacency: Adjacency) -> bool {
+        adjacency != Adjacency::Triangle || (width.is_multiple_of(2) && height.is_multiple_of(2))
+    }
+
+    // On a wrapping board, coordinates that fall off one edge come back on the opposite one
+    #[allow(
+        clippy::cast_possible_wrap,
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation
+    )]
+    fn compute_neighbors(
+        width: u32,
+        height: u32,
+        wrap: bool,
+        adjacency: Adjacency,
+        index: usize,
+    ) -> Vec<usize> {
+        let place = Coord::from_index(index, width);
+        let index_x = place.col as isize;
+        let index_y = place.row as isize;
+        let columns = width;
+        let width = width as isize;
+        let height = height as isize;
+
+        let mut neighbors = Vec::new();
+
+        for (row_offset, col_offset) in neighbor_offsets(adjacency, place) {
+            let mut neighbor_x = index_x + col_offset;
+            let mut neighbor_y = index_y + row_offset;
+
+            if wrap {
+                neighbor_x = neighbor_x.rem_euclid(width);
+                neighbor_y = neighbor_y.rem_euclid(height);
+            }
+
+            if neighbor_x >= 0 && neighbor_x < width && neighbor_y >= 0 && neighbor_y < height {
+                let neighbor_index =
+                    Coord::new(neighbor_y as u32, neighbor_x as u32).to_index(columns);
+
+                // Narrow wrapping boards can reach the same tile, or the tile itself, twice
+                if neighbor_index != index && !neighbors.contains(&neighbor_index) {
+                    neighbors.push(neighbor_index);
+                }
+            }
+        }
+
+        neighbors
+    }
+
+    // Where a tile's neighbors sit relative to it, as (row, column) steps
+    fn neighbor_offsets(adjacency: Adjacency, place: Coord) -> Vec<(isize, isize)> {
+        match adjacency {
+            Adjacency::Eight | Adjacency::Four => (-1..=1)
+                .flat_map(|row_offset| (-1..=1).map(move |col_offset| (row_offset, col_offset)))
+                .filter(|&offset| offset != (0, 0))
+                // Diagonals only touch when all eight around count
+                .filter(|&(row_offset, col_offset)| {
+                    adjacency == Adjacency::Eight || row_offset == 0 || col_offset == 0
+                })
+                .collect(),
+            Adjacency::Triangle => triangle_offsets(place.points_up()),
+        }
+    }
+
+    // A triangle touches two tiles either side along its row, three across its point, and five
+    // along its flat side, which is below a tile pointing up and above one pointing down
+    fn triangle_offsets(points_up: bool) -> Vec<(isize, isize)> {
+        let (point, flat) = if points_up { (-1, 1) } else { (1, -1) };
+
+        let mut offsets: Vec<(isize, isize)> = [-2, -1, 1, 2].map(|col| (0, col)).to_vec();
+        offsets.extend((-1..=1).map(|col| (point, col)));
+        offsets.extend((-2..=2).map(|col| (flat, col)));
+
+        offsets
+    }
+
+    // The hint a revealed tile stores, with 10 standing in for a mine
+    pub(crate) fn tile_hint(state: &GameState, index: usize) -> u32 {
+        if state.get_tile(index).has_mine() {
+            10
+        } else {
+            calculate_hint(state, index)
+        }
+    }
+
+    // The board's 3BV (Bechtel's Board Benchmark Value): the fewest clicks that clear it without
+    // chording, one for each opening plus one for each number not on the edge of an opening
+    #[must_use]
+    pub fn board_3bv(state: &GameState) -> u32 {
+        count_3bv(state).0
+    }
+
+    // How much of the board's 3BV the player has done so far: openings they've opened, and
+    // numbers away from openings they've cleared
+    #[must_use]
+    pub fn solved_3bv(state: &GameState) -> u32 {
+        count_3bv(state).1
+    }
+
+    // The board's 3BV, and how much of it is done
+    fn count_3bv(state: &GameState) -> (u32, u32) {
+        let tiles = state.board.tiles.len();
+        let safe = |index: usize| {
+            let tile = state.get_tile(index);
+            tile.is_active() && !tile.has_mine()
+        };
+        let revealed = |index: usize| matches!(state.get_tile(index), Tile::Revealed { .. });
+        let mut cleared = vec![false; tiles];
+        let (mut total, mut solved) = (0, 0);
+
+        // A click on an opening clears it along with the numbers around its edge
+        for start in 0..tiles {
+            if cleared[start] || !safe(start) || state.get_hint(start) != 0 {
+                continue;
+            }
+
+            cleared[start] = true;
+            let mut opened = revealed(start);
+            let mut pending = vec![start];
+
+            while let Some(index) = pending.pop() {
+                for &neighbor in find_neighbors(state, index) {
+                    if !cleared[neighbor] && safe(neighbor) {
+                        cleared[neighbor] = true;
+
+                        if state.get_hint(neighbor) == 0 {
+                            opened |= revealed(neighbor);
+                            pending.push(neighbor);
+                        }
+                    }
+                }
+            }
+
+            total += 1;
+            solved += u32::from(opened);
+        }
+
+        for index in (0..tiles).filter(|&index| !cleared[index] && safe(index)) {
+            total += 1;
+            solved += u32::from(revealed(index));
+        }
+
+        (total, solved)
+    }
+
+    // Mines stay put once placed, so every tile's hint can be worked out up front
+    pub(crate) fn compute_hints(state: &mut GameState) {
+        state.hints = (0..state.board.tiles.len())
+            .map(|index| tile_hint(state, index))
+            .collect();
+    }
+
+    pub(crate) fn calculate_hint(state: &GameState, index: usize) -> u32 {
+        let neighbors = find_neighbors(state, index);
+
+        let mut count = 0;
+
+        // Mines revealed by a spent life still count towards their neighbors' hints
+        for &neighbor_index in neighbors {
+            count += state.mines_at(neighbor_index);
+        }
+
+        count
+    }
+
+    // A clear is risky when the tile borders a number still short of flags,
+    // and no number that already has all its flags to vouch for it
+    pub(crate) fn is_risky_clear(state: &GameState, index: usize) -> bool {
+        let mut unsatisfied = false;
+
+        for &neighbor_index in find_neighbors(state, index) {
+            if let Some(hint) = state.get_tile(neighbor_index).hint() {
+                if marked_mines(state, neighbor_index) >= hint as usize {
+                    return false;
+                }
+
+                unsatisfied = true;
+            }
+        }
+
+        unsatisfied
+    }
+
+    // Neighbors known to be mines, either flagged or revealed by a spent life. A flag marks as
+    // many mines as the player has flagged the tile, while a mine revealed by a spent life
+    // shows how many it held.
+    pub(crate) fn marked_mines(state: &GameState, index: usize) -> usize {
+        find_neighbors(state, index)
+            .iter()
+            .map(|&neighbor| match state.get_tile(neighbor) {
+                Tile::Hidden { flagged: true, .. } => state.flags_at(neighbor) as usize,
+                Tile::Revealed { has_mine: true, .. } => state.mines_at(neighbor) as usize,
+                Tile::Hidden { .. } | Tile::Revealed { .. } | Tile::Inactive => 0,
+            })
+            .sum()
+    }
+
+    // The unflagged hidden neighbors of a revealed number that has exactly as many marked mines as
+    // its hint, or None when there are no such neighbors to chord on
+    pub(crate) fn chord_targets(state: &GameState, index: usize) -> Option<Vec<usize>> {
+        let hint = state.tile_at(index)?.hint()?;
+
+        if marked_mines(state, index) != hint as usize {
+            return None;
+        }
+
+        let targets: Vec<usize> = find_neighbors(state, index)
+            .iter()
+            .copied()
+            .filter(|&neighbor| {
+                matches!(
+                    state.get_tile(neighbor),
+                    Tile::Hidden { flagged: false, .. }
+                )
+            })
+            .collect();
+
+        (!targets.is_empty()).then_some(targets)
+    }
+
+    // As `chord_targets`, but only the neighbors the numbers on show prove safe. The flags are
+    // set aside for the proof, so a wrong one can't lead onto a mine, and where the mines really
+    // are never comes into it.
+    pub(crate) fn safe_neighbors(state: &GameState, index: usize) -> Option<Vec<usize>> {
+        proven_safe_neighbors(state, index, &solver::unflagged_probabilities(state))
+    }
+
+    // `safe_neighbors` against chances already worked out, for checking many numbers at once
+    pub(crate) fn proven_safe_neighbors(
+        state: &GameState,
+        index: usize,
+        probabilities: &[Option<f64>],
+    ) -> Option<Vec<usize>> {
+        let targets: Vec<usize> = chord_targets(state, index)?
+            .into_iter()
+            .filter(
+                |&target| matches!(probabilities.get(target), Some(Some(chance)) if *chance <= 0.0),
+            )
+            .collect();
+
+        (!targets.is_empty()).then_some(targets)
+    }
+
+    // Flag a hidden tile, spending one flag from the remaining budget.
+    // Returns false without touching the tile when no flags remain.
+    pub(crate) fn place_flag(state: &mut GameState, index: usize) -> bool {
+        if state.flags_left() == 0 {
+            return false;
+        }
+
+        if let Tile::Hidden {
+            has_mine,
+            flagged: false,
+            ..
+        } = *state.get_tile(index)
+        {
+            state.set_tile(
+                index,
+                Tile::Hidden {
+                    has_mine,
+                    flagged: true,
+                    questioned: false,
+                },
+            );
+            // A fresh flag marks one mine, whatever an earlier flag here was raised to
+            if let Some(count) = state.flag_counts.get_mut(index) {
+                *count = 0;
+            }
+            state.decrement_mine_count();
+            return true;
+        }
+
+        false
+    }
+
+    // Flood fill outwards from a zero hint tile.
+    // Uses a worklist rather than recursion so large open boards can't overflow the stack.
+    fn reveal_neighbors(state: &mut GameState, index: usize) {
+        // Hold our own handle on the table so tiles can be changed while walking it
+        let neighbors = Rc::clone(state.neighbor_table());
+        let mut pending = vec![index];
+        let mut revealed_count = 0;
+
+        while let Some(current) = pending.pop() {
+            for &neighbor_index in &neighbors[current] {
+                let Some(coord) = state.board.coord_of(neighbor_index) else {
+                    continue;
+                };
+
+                if let Some(Tile::Hidden {
+                    has_mine: false,
+                    flagged: false,
+                    ..
+                }) = state.board.get(coord)
+                {
+                    let hint = state.get_hint(neighbor_index);
+                    let revealed = Tile::Revealed {
+                        has_mine: false,
+                        hint,
+                    };
+
+                    // Safe tiles stay safe, so the stored hints are still good
+                    state.board.set(coord, revealed);
+                    state.emit(GameEvent::Revealed {
+                        index: neighbor_index,
+                        tile: revealed,
+                    });
+                    revealed_count += 1;
+
+                    if hint == 0 {
+                        pending.push(neighbor_index);
+                    }
+                }
+            }
+        }
+
+        if revealed_count > 0 {
+            state.emit(GameEvent::FloodFilled {
+                index,
+                revealed: revealed_count,
+            });
+        }
+    }
+
+    fn check_for_win(state: &mut GameState) {
+        if state.get_game_over() {
+            return;
+        }
+
+        // Mines revealed while the player still had lives to spare don't stand in the way
+        let cleared = || {
+            state.tiles().all(|(_, tile)| {
+                !matches!(
+                    tile,
+                    Tile::Hidden {
+                        has_mine: false,
+                        flagged: _,
+                        ..
+                    }
+                )
+            })
+        };
+        let flagged = || {
+            state.tiles().all(|(_, &tile)| match tile {
+                Tile::Hidden {
+                    has_mine, flagged, ..
+                } => has_mine == flagged,
+                Tile::Revealed { .. } | Tile::Inactive => true,
+            })
+        };
+
+        let winner = match state.get_win_condition() {
+            WinCondition::RevealSafe => cleared(),
+            WinCondition::FlagMines => flagged(),
+            WinCondition::Either => cleared() || flagged(),
+        };
+
+        if winner {
+            state.set_won(winner);
+        }
+    }
+
+    /// Carry out the current input mode on the selected tile.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::NoSelection` if no tile has been selected.
+    pub fn update(state: &mut GameState) -> Result<(), MinesweeperError> {
+        let coord = Coord::from_index(state.get_selected()?, state.get_width());
+
+        if let Some(action) = to_action(state.get_input_mode(), coord) {
+            state.apply(action);
+        }
+
+        Ok(())
+    }
+
+    // The action an input mode stands for on the given tile, if it acts on the game at all
+    #[must_use]
+    pub fn to_action(input_mode: InputMode, coord: Coord) -> Option<Action> {
+        match input_mode {
+            InputMode::Clear => Some(Action::Reveal(coord)),
+            InputMode::Flag => Some(Action::Flag(coord)),
+            InputMode::Safe => Some(Action::SafeReveal(coord)),
+            InputMode::Chord => Some(Action::Chord(coord)),
+            InputMode::Hint => Some(Action::Hint),
+            InputMode::Undo => Some(Action::Undo),
+            InputMode::Redo => Some(Action::Redo),
+            InputMode::Cancel | InputMode::Pause | InputMode::Menu => None,
+        }
+    }
+
+    /// Play a list of moves with no terminal involved, stopping early once the game is decided.
+    /// Moves that aren't allowed are skipped, as they would be for a player.
+    ///
+    /// ```
+    /// use minesweeper::{engine, Action, Coord, GameOutcome, GameState};
+    ///
+    /// let mut state = GameState::from_layout("*..").unwrap();
+    /// let outcome = engine::simulate(&mut state, &[Action::Reveal(Coord::new(0, 2))]);
+    ///
+    /// assert_eq!(GameOutcome::Won, outcome);
+    /// ```
+    pub fn simulate(state: &mut GameState, moves: &[Action]) -> GameOutcome {
+        for &action in moves {
+            if state.outcome() != GameOutcome::InProgress {
+                break;
+            }
+            state.apply(action);
+        }
+
+        state.outcome()
+    }
+
+    // Work through the consequences of the move just made on the selected tile
+    pub(crate) fn resolve(state: &mut GameState, index: usize) {
+        let stored_hint = state.get_hint(index);
+
+        let hit_mine = matches!(
+            state.get_tile(index),
+            Tile::Revealed {
+                has_mine: true,
+                hint: _,
+            }
+        );
+
+        if hit_mine {
+            state.emit(GameEvent::Exploded {
+                index,
+                tile: state.board.tiles[index],
+            });
+            state.lose_life();
+
+            // A spare life only costs the one mine, which now counts as found
+            if state.get_lives() > 0 {
+                state.decrement_mine_count();
+            } else {
+                state.set_game_over(true);
+            }
+        }
+
+        if state.get_game_over() {
+            for index in 0..state.board.tiles.len() {
+                if let Tile::Hidden {
+                    has_mine: true,
+                    flagged: _,
+                    ..
+                } = state.board.tiles[index]
+                {
+                    state.board.tiles[index] = Tile::Revealed {
+                        has_mine: true,
+                        hint: state.get_hint(index),
+                    };
+                    state.emit(GameEvent::Revealed {
+                        index,
+                        tile: state.board.tiles[index],
+                    });
+                }
+            }
+
+            state.emit(GameEvent::Lost {
+                index,
+                tile: state.board.tiles[index],
+            });
+        } else if !hit_mine && state.get_input_mode() == InputMode::Clear && stored_hint == 0 {
+            reveal_neighbors(state, index);
+        } else if let Tile::Hidden { flagged: false, .. } = state.get_tile(index) {
+            // A flag stops the flood fill, so a tile unflagged beside an opening is picked
+            // back up by it. Anything next to a revealed zero is safe.
+            let opening = find_neighbors(state, index)
+                .iter()
+                .copied()
+                .find(|&neighbor| state.get_tile(neighbor).hint() == Some(0));
+
+            if let Some(opening) = opening {
+                reveal_neighbors(state, opening);
+            }
+        }
+
+        // Only a cleared tile counts as a turn; flags and unflags are free
+        if state.get_input_mode() == InputMode::Clear {
+            state.increment_turn_count();
+        }
+
+        check_for_win(state);
+
+        if state.get_won() {
+            flag_remaining_mines(state);
+            state.emit(GameEvent::Won {
+                index,
+                tile: state.board.tiles[index],
+            });
+        }
+    }
+
+    // Finish off a won board the way classic minesweeper does, with every mine flagged
+    fn flag_remaining_mines(state: &mut GameState) {
+        for (_, _, tile) in state.iter_tiles_mut() {
+            if let Tile::Hidden { has_mine: true, .. } = tile {
+                *tile = Tile::Hidden {
+                    has_mine: true,
+                    flagged: true,
+                    questioned: false,
+                };
+            }
+        }
+
+        state.set_mine_count(0);
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        // Build a board with mines at the given indices and nothing revealed
+        fn board_with_mines(width: u32, height: u32, mines: &[usize]) -> GameState {
+            let mut state = GameState::new();
+            state
+                .board_setup(width, height, u32::try_from(mines.len()).unwrap())
+                .unwrap();
+
+            for index in 0..(width * height) as usize {
+                state.add_tile(Tile::Hidden {
+                    has_mine: mines.contains(&index),
+                    flagged: false,
+                    questioned: false,
+                });
+            }
+
+            compute_hints(&mut state);
+            // Mines stay where each test puts them, even under the first clear
+            state.set_first_click_safe(false);
+            state
+        }
+
+        fn flag(state: &mut GameState, index: u32) {
+            state.set_input_mode(InputMode::Flag);
+            state.set_selected(index);
+            update(state).unwrap();
+        }
+
+        #[test]
+        fn rejects_flags_beyond_budget() {
+            let mut state = board_with_mines(5, 5, &[0, 1, 2, 3]);
+
+            for index in 0..state.get_mines() as usize {
+                assert!(place_flag(&mut state, index));
+            }
+
+            assert_eq!(0, state.get_mine_count());
+            assert!(!place_flag(&mut state, 10));
+            assert_eq!(
+                &Tile::Hidden {
+                    has_mine: false,
+                    flagged: false,
+                    questioned: false
+                },
+                state.get_tile(10)
+            );
+        }
+
+        fn clear(state: &mut GameState, index: u32) {
+            state.set_input_mode(InputMode::Clear);
+            state.set_selected(index);
+            update(state).unwrap();
+        }
+
+        #[test]
+        fn auto_flags_certain_mines_after_a_clear() {
+            let mut state = board_with_mines(7, 1, &[0, 4]);
+            clear(&mut state, 2);
+
+            // Off by default
+            assert_eq!(2, state.get_mine_count());
+
+            let mut state = board_with_mines(7, 1, &[0, 4]);
+            state.set_auto_flag(true);
+            clear(&mut state, 2);
+
+            assert!(!state.get_won());
+            assert_eq!(0, state.get_mine_count());
+            assert!(matches!(
+                state.get_tile(0),
+                Tile::Hidden { flagged: true, .. }
+            ));
+            assert!(matches!(
+                state.get_tile(4),
+                Tile::Hidden { flagged: true, .. }
+            ));
+            assert!(matches!(
+                state.get_tile(5),
+                Tile::Hidden { flagged: false, .. }
+            ));
+        }
+
+        #[test]
+        fn undoes_auto_flags_with_their_move() {
+            let mut state = board_with_mines(7, 1, &[0, 4]);
+            state.set_auto_flag(true);
+            clear(&mut state, 2);
+
+            assert!(state.undo());
+
+            assert!(!state.can_undo());
+            assert_eq!(2, state.get_mine_count());
+            assert!(state.get_moves().is_empty());
+            assert!(state
+                .tiles()
+                .all(|(_, tile)| matches!(tile, Tile::Hidden { flagged: false, .. })));
+        }
+
+        #[test]
+        fn auto_flags_within_the_flag_budget() {
+            let mut state = board_with_mines(7, 1, &[0, 4]);
+            state.set_auto_flag(true);
+            state.set_mine_count(1);
+            clear(&mut state, 2);
+
+            assert_eq!(0, state.get_mine_count());
+            assert!(matches!(
+                state.get_tile(0),
+                Tile::Hidden { flagged: true, .. }
+            ));
+            assert!(matches!(
+                state.get_tile(4),
+                Tile::Hidden { flagged: false, .. }
+            ));
+        }
+
+        #[test]
+        fn counts_flag_operations() {
+            let mut state = board_with_mines(3, 1, &[0]);
+
+            flag(&mut state, 0);
+            flag(&mut state, 0);
+            flag(&mut state, 1);
+
+            assert_eq!(3, state.get_flag_operations());
+        }
+
+        #[test]
+        fn no_flag_challenge_ignores_flags() {
+            let mut state = board_with_mines(3, 1, &[0]);
+            state.set_flagging_allowed(false);
+
+            flag(&mut state, 0);
+
+            assert!(matches!(
+                state.get_tile(0),
+                Tile::Hidden { flagged: false, .. }
+            ));
+            assert_eq!(1, state.get_mine_count());
+            assert_eq!(0, state.get_flag_operations());
+            assert!(!state.can_undo());
+        }
+
+        #[test]
+        fn flags_mines_on_win() {
+            let mut state = board_with_mines(3, 3, &[0]);
+
+            clear(&mut state, 8);
+
+            assert!(state.get_won());
+            assert_eq!(0, state.get_mine_count());
+            assert_eq!(
+                &Tile::Hidden {
+                    has_mine: true,
+                    flagged: true,
+                    questioned: false
+                },
+                state.get_tile(0)
+            );
+        }
+
+        #[test]
+        fn reveals_all_without_ending_game() {
+            let mut state = board_with_mines(3, 3, &[0, 8]);
+
+            state.reveal_all();
+
+            assert!(!state.get_game_over());
+            assert_eq!(
+                &Tile::Revealed {
+                    has_mine: true,
+                    hint: 10
+                },
+                state.get_tile(0)
+            );
+            assert_eq!(
+                &Tile::Revealed {
+                    has_mine: false,
+                    hint: 2
+                },
+                state.get_tile(4)
+            );
+            assert_eq!(
+                &Tile::Revealed {
+                    has_mine: false,
+                    hint: 0
+                },
+                state.get_tile(6)
+            );
+        }
+
+        #[test]
+        fn spare_lives_survive_a_mine() {
+            let mut state = board_with_mines(3, 3, &[0, 2]);
+            state.set_lives(3);
+
+            clear(&mut state, 0);
+
+            assert!(!state.get_game_over());
+            assert_eq!(2, state.get_lives());
+            assert_eq!(1, state.get_mine_count());
+            assert_eq!(2, calculate_hint(&state, 1));
+            assert_eq!(
+                &Tile::Hidden {
+                    has_mine: true,
+                    flagged: false,
+                    questioned: false
+                },
+                state.get_tile(2)
+            );
+
+            for index in [1, 3, 4, 5, 6, 7, 8] {
+                clear(&mut state, index);
+            }
+
+            assert!(state.get_won());
+        }
+
+        #[test]
+        fn last_life_ends_game() {
+            let mut state = board_with_mines(3, 3, &[0, 2]);
+            state.set_lives(2);
+
+            clear(&mut state, 0);
+            clear(&mut state, 2);
+
+            assert!(state.get_game_over());
+            assert!(!state.get_won());
+            assert_eq!(0, state.get_lives());
+        }
+
+        // The original recursive flood fill, kept to check the worklist version against
+        fn reveal_neighbors_recursive(state: &mut GameState, index: usize) {
+            for neighbor_index in find_neighbors(state, index).to_vec() {
+                if let Tile::Hidden {
+                    has_mine: false,
+                    flagged: false,
+                    ..
+                } = state.get_tile(neighbor_index)
+                {
+                    let hint = calculate_hint(state, neighbor_index);
+                    state.set_tile(
+                        neighbor_index,
+                        Tile::Revealed {
+                            has_mine: false,
+                            hint,
+                        },
+                    );
+                    if hint == 0 {
+                        reveal_neighbors_recursive(state, neighbor_index);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn flood_fill_matches_recursive_version() {
+            for seed in 0..20 {
+                let mut iterative = new_game(&Difficulty::Hard, Some(seed)).unwrap();
+                let mut recursive = new_game(&Difficulty::Hard, Some(seed)).unwrap();
+
+                if let Some(start) = (0..iterative.board.tiles.len()).find(|&index| {
+                    calculate_hint(&iterative, index) == 0
+                        && matches!(
+                            iterative.get_tile(index),
+                            Tile::Hidden {
+                                has_mine: false,
+                                ..
+                            }
+                        )
+                }) {
+                    reveal_neighbors(&mut iterative, start);
+                    reveal_neighbors_recursive(&mut recursive, start);
+                }
+
+                assert_eq!(recursive.board.tiles, iterative.board.tiles);
+            }
+        }
+
+        #[test]
+        fn clears_large_sparse_board() {
+            let mut state = board_with_mines(crate::MAX_WIDTH, crate::MAX_HEIGHT, &[1287, 2573]);
+
+            clear(&mut state, 0);
+
+            assert!(state.get_won());
+        }
+
+        // Run with `cargo test --release times_large_flood_fill -- --ignored --nocapture`
+        #[test]
+        #[ignore = "times the neighbor table rather than checking behavior"]
+        fn times_large_flood_fill() {
+            use std::{
+                cell::OnceCell,
+                time::{Duration, Instant},
+            };
+
+            const RUNS: u32 = 200;
+            let board = board_with_mines(crate::MAX_WIDTH, crate::MAX_HEIGHT, &[1287, 2573]);
+
+            // Each run clears a copy of the board, either sharing its neighbor table or building
+            // one on the way, as each fill had to before the table was kept
+            let average = |keep_table: bool| {
+                let total: Duration = (0..RUNS)
+                    .map(|_| {
+                        let mut state = board.clone();
+                        if !keep_table {
+                            state.neighbors = OnceCell::new();
+                        }
+
+                        let start = Instant::now();
+                        clear(&mut state, 0);
+                        let taken = start.elapsed();
+
+                        assert!(state.get_won());
+                        taken
+                    })
+                    .sum();
+                total / RUNS
+            };
+
+            let (built, kept) = (average(false), average(true));
+            println!(
+                "Opening a {}x{} board: {built:?} building the neighbor table, {kept:?} with it kept",
+                crate::MAX_WIDTH,
+                crate::MAX_HEIGHT
+            );
+        }
+
+        #[test]
+        fn precomputed_hints_match_calculated_hints() {
+            let state = new_game(&Difficulty::Hard, Some(42)).unwrap();
+
+            assert_eq!(state.board.tiles.len(), state.hints.len());
+
+            for index in 0..state.board.tiles.len() {
+                let expected = if state.get_tile(index).has_mine() {
+                    10
+                } else {
+                    calculate_hint(&state, index)
+                };
+
+                assert_eq!(expected, state.get_hint(index));
+            }
+        }
+
+        #[test]
+        fn moving_a_mine_discards_precomputed_hints() {
+            let mut state = board_with_mines(3, 1, &[0]);
+            assert_eq!(1, state.get_hint(1));
+
+            state.set_tile(
+                2,
+                Tile::Hidden {
+                    has_mine: true,
+                    flagged: false,
+                    questioned: false,
+                },
+            );
+
+            assert!(state.hints.is_empty());
+            assert_eq!(2, state.get_hint(1));
+        }
+
+        #[test]
+        fn neighbor_cache_matches_computed_neighbors() {
+            for (width, height) in [(1, 1), (1, 7), (7, 1), (5, 5), (26, 13)] {
+                let state = board_with_mines(width, height, &[]);
+
+                for index in 0..(width * height) as usize {
+                    assert_eq!(
+                        compute_neighbors(width, height, false, Adjacency::Eight, index),
+                        find_neighbors(&state, index)
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn neighbor_cache_follows_board_size() {
+            let mut state = board_with_mines(5, 5, &[]);
+            state.board_setup(3, 2, 1).unwrap();
+
+            assert_eq!([0, 2, 3, 4, 5], find_neighbors(&state, 1));
+        }
+
+        #[test]
+        fn places_exactly_the_requested_mines() {
+            for seed in 0..50 {
+                for (width, height, mines) in [(1, 2, 1), (2, 30, 59), (26, 2, 13), (12, 12, 35)] {
+                    let difficulty = Difficulty::Custom {
+                        width,
+                        height,
+                        mines,
+                    };
+                    let state = new_game(&difficulty, Some(seed)).unwrap();
+
+                    let placed = state
+                        .iter_tiles()
+                        .filter(|(_, _, tile)| tile.has_mine())
+                        .count();
+                    assert_eq!(state.get_mines() as usize, placed);
+                }
+            }
+        }
+
+        #[test]
+        fn wrapped_corner_has_eight_neighbors() {
+            let mut state = board_with_mines(3, 3, &[]);
+            state.set_wrap(true);
+
+            let mut neighbors = find_neighbors(&state, 0).to_vec();
+            neighbors.sort_unstable();
+
+            assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8], neighbors);
+        }
+
+        #[test]
+        fn wrapped_hints_and_flood_fill_cross_edges() {
+            let mut state = board_with_mines(4, 4, &[0]);
+            state.set_wrap(true);
+
+            assert_eq!(1, state.get_hint(15));
+            assert_eq!(0, state.get_hint(14));
+
+            clear(&mut state, 14);
+
+            assert!(state.get_won());
+        }
+
+        #[test]
+        fn four_way_adjacency_skips_diagonals() {
+            let mut state = board_with_mines(3, 3, &[0]);
+            assert_eq!(8, find_neighbors(&state, 4).len());
+            assert_eq!(1, state.get_hint(4));
+
+            state.set_adjacency(Adjacency::Four);
+
+            let mut neighbors = find_neighbors(&state, 4).to_vec();
+            neighbors.sort_unstable();
+
+            assert_eq!(vec![1, 3, 5, 7], neighbors);
+            assert_eq!(0, state.get_hint(4));
+            assert_eq!(1, state.get_hint(1));
+        }
+
+        #[test]
+        fn four_way_flood_fill_opens_through_edges_only() {
+            let mut state = board_with_mines(3, 3, &[0]);
+            state.set_adjacency(Adjacency::Four);
+
+            // The center only touched the mine diagonally, so it opens up the whole board
+            clear(&mut state, 4);
+
+            assert!(state.get_won());
+            assert_eq!(1, state.get_hint(3));
+        }
+
+        #[test]
+        fn triangles_touch_by_orientation() {
+            let mut state = board_with_mines(5, 3, &[]);
+            state.set_adjacency(Adjacency::Triangle);
+
+            // Pointing down, so the flat side is on the row above
+            let mut neighbors = find_neighbors(&state, 7).to_vec();
+            neighbors.sort_unstable();
+            assert_eq!(vec![0, 1, 2, 3, 4, 5, 6, 8, 9, 11, 12, 13], neighbors);
+
+            // Pointing up, next to the left edge
+            let mut neighbors = find_neighbors(&state, 6).to_vec();
+            neighbors.sort_unstable();
+            assert_eq!(vec![0, 1, 2, 5, 7, 8, 10, 11, 12, 13], neighbors);
+        }
+
+        #[test]
+        fn wrapped_neighbors_are_mutual() {
+            for adjacency in [Adjacency::Eight, Adjacency::Four, Adjacency::Triangle] {
+                for (width, height) in [(5, 5), (5, 6), (6, 5), (6, 4), (3, 3)] {
+                    let mut state = board_with_mines(width, height, &[]);
+                    state.set_adjacency(adjacency);
+                    state.set_wrap(true);
+
+                    for index in 0..(width * height) as usize {
+                        for &neighbor in find_neighbors(&state, index) {
+                            assert!(
+                                find_neighbors(&state, neighbor).contains(&index),
+                                "{adjacency:?} {width}x{height}: {index} and {neighbor}"
+                            );
+                        }
+                    }
+                }
+            }
+
+            // Triangles only wrap where both sides are even
+            let mut state = board_with_mines(5, 5, &[]);
+            state.set_adjacency(Adjacency::Triangle);
+            state.set_wrap(true);
+            assert!(!state.get_wrap());
+
+            let mut state = board_with_mines(6, 4, &[]);
+            state.set_adjacency(Adjacency::Triangle);
+            state.set_wrap(true);
+            assert!(state.get_wrap());
+            assert_eq!(12, find_neighbors(&state, 0).len());
+        }
+
+        #[test]
+        fn narrow_wrapped_boards_have_no_duplicate_neighbors() {
+            let mut state = board_with_mines(1, 2, &[]);
+            state.set_wrap(true);
+
+            assert_eq!([1], find_neighbors(&state, 0));
+        }
+
+        #[test]
+        fn tracks_progress_towards_a_win() {
+            let mut state = board_with_mines(4, 1, &[3]);
+
+            assert!(state.progress().abs() < f32::EPSILON);
+
+            flag(&mut state, 3);
+            clear(&mut state, 2);
+
+            assert!((state.progress() - 1.0 / 3.0).abs() < f32::EPSILON);
+
+            clear(&mut state, 0);
+
+            assert!(state.get_won());
+            assert!((state.progress() - 1.0).abs() < f32::EPSILON);
+        }
+
+        #[test]
+        fn counts_remaining_safe_tiles() {
+            let mut state = board_with_mines(5, 2, &[9]);
+
+            assert_eq!(9, state.remaining_safe());
+
+            // Clearing a corner floods the zeros and their borders
+            clear(&mut state, 0);
+
+            assert_eq!(1, state.remaining_safe());
+            assert!(!state.get_won());
+
+            clear(&mut state, 4);
+
+            assert_eq!(0, state.remaining_safe());
+            assert!(state.get_won());
+        }
+
+        #[test]
+        fn guards_clears_next_to_unsatisfied_numbers() {
+            let mut state = board_with_mines(5, 1, &[0, 4]);
+            clear(&mut state, 1);
+            clear(&mut state, 3);
+
+            // Both neighboring 1s are still missing their mine
+            assert!(is_risky_clear(&state, 2));
+
+            flag(&mut state, 4);
+
+            // The flag satisfies the 1 on the right, which vouches for the tile
+            assert!(!is_risky_clear(&state, 2));
+            assert!(is_risky_clear(&state, 0));
+
+            let fresh = board_with_mines(5, 1, &[0]);
+            assert!(!is_risky_clear(&fresh, 2));
+        }
+
+        #[test]
+        fn safe_reveal_opens_around_a_satisfied_number() {
+            let mut state = board_with_mines(3, 3, &[0]);
+            for index in [4, 1, 3] {
+                clear(&mut state, index);
+            }
+
+            // The 1 still needs its flag
+            assert!(!state.safe_reveal(4));
+
+            flag(&mut state, 0);
+            state.set_input_mode(InputMode::Safe);
+            state.set_selected(4);
+            update(&mut state).unwrap();
+
+            assert!(state.get_won());
+        }
+
+        #[test]
+        fn chords_trust_the_flags() {
+            let mut state = board_with_mines(3, 3, &[0]);
+            clear(&mut state, 4);
+
+            // Not enough flags yet
+            assert!(!state.chord(4));
+
+            flag(&mut state, 8);
+            state.set_input_mode(InputMode::Chord);
+            state.set_selected(4);
+            update(&mut state).unwrap();
+
+            assert!(state.get_game_over());
+            assert!(matches!(
+                state.get_tile(0),
+                Tile::Revealed { has_mine: true, .. }
+            ));
+        }
+
+        #[test]
+        fn chords_open_around_a_satisfied_number() {
+            let mut state = board_with_mines(3, 3, &[0]);
+            clear(&mut state, 4);
+            flag(&mut state, 0);
+
+            let result = state.apply(Action::Chord(Coord::new(1, 1)));
+
+            assert!(result.applied);
+            assert!(result.game_won);
+            assert_eq!(vec![1, 2, 3, 5, 6, 7, 8], result.changed);
+        }
+
+        #[test]
+        fn safe_reveal_refuses_when_flags_are_wrong() {
+            let mut state = board_with_mines(3, 3, &[0]);
+            clear(&mut state, 4);
+            flag(&mut state, 8);
+
+            assert!(safe_neighbors(&state, 4).is_none());
+            assert!(!state.safe_reveal(4));
+            assert!(!state.get_game_over());
+            assert_eq!(
+                &Tile::Hidden {
+                    has_mine: true,
+                    flagged: false,
+                    questioned: false
+                },
+                state.get_tile(0)
+            );
+        }
+
+        #[test]
+        fn safe_reveal_gives_nothing_away_about_the_flags() {
+            // The same numbers and flags on show, with the flag right on one board and wrong on
+            // the other
+            let boards = [&[8], &[0]].map(|mines| {
+                let mut state = board_with_mines(3, 3, mines);
+                clear(&mut state, 4);
+                flag(&mut state, 8);
+                state
+            });
+
+            let [right, wrong] = boards.each_ref().map(|state| {
+                (
+                    safe_neighbors(state, 4),
+                    state.check_move(Action::SafeReveal(Coord::new(1, 1))),
+                )
+            });
+            assert_eq!(right, wrong);
+            assert_eq!(None, right.0);
+        }
+
+        #[test]
+        fn auto_solve_step_makes_only_forced_moves() {
+            let mut state = board_with_mines(4, 1, &[0]);
+            state.set_tile(
+                1,
+                Tile::Revealed {
+                    has_mine: false,
+                    hint: 1,
+                },
+            );
+            state.set_tile(
+                2,
+                Tile::Revealed {
+                    has_mine: false,
+                    hint: 0,
+                },
+            );
+
+            assert!(state.auto_solve_step());
+
+            // The 1's only unknown neighbor is its mine, and the 0 vouches for the last tile
+            assert_eq!(
+                &Tile::Hidden {
+                    has_mine: true,
+                    flagged: true,
+                    questioned: false
+                },
+                state.get_tile(0)
+            );
+            assert_eq!(Some(0), state.get_tile(3).hint());
+            assert!(state.get_won());
+            assert!(!state.auto_solve_step());
+        }
+
+        #[test]
+        fn auto_solve_step_leaves_guesses_alone() {
+            let mut state = board_with_mines(3, 1, &[0]);
+            state.set_tile(
+                1,
+                Tile::Revealed {
+                    has_mine: false,
+                    hint: 1,
+                },
+            );
+
+            assert!(!state.auto_solve_step());
+            assert_eq!(1, state.get_mine_count());
+        }
+
+        #[test]
+        fn unflagging_beside_an_opening_reveals_the_tile() {
+            let mut state = board_with_mines(5, 1, &[4]);
+
+            // A mistaken flag holds back the flood fill
+            flag(&mut state, 1);
+            clear(&mut state, 0);
+
+            assert_eq!(
+                &Tile::Hidden {
+                    has_mine: false,
+                    flagged: true,
+                    questioned: false
+                },
+                state.get_tile(1)
+            );
+            assert_eq!(
+                &Tile::Hidden {
+                    has_mine: false,
+                    flagged: false,
+                    questioned: false
+                },
+                state.get_tile(2)
+            );
+
+            flag(&mut state, 1);
+
+            assert_eq!(Some(0), state.get_tile(1).hint());
+            assert_eq!(Some(0), state.get_tile(2).hint());
+            assert_eq!(Some(1), state.get_tile(3).hint());
+            assert!(state.get_won());
+        }
+
+        #[test]
+        fn unflagging_away_from_openings_leaves_the_tile_hidden() {
+            let mut state = board_with_mines(3, 1, &[0]);
+
+            flag(&mut state, 2);
+            flag(&mut state, 2);
+
+            assert_eq!(
+                &Tile::Hidden {
+                    has_mine: false,
+                    flagged: false,
+                    questioned: false
+                },
+                state.get_tile(2)
+            );
+        }
+
+        #[test]
+        fn first_clear_on_easy_always_opens_an_area() {
+            for seed in 0..200 {
+                let mut state = new_game(&Difficulty::Easy, Some(seed)).unwrap();
+                state.set_first_click_zero(true);
+                let index = (seed % 25) as usize;
+
+                state.reveal(index);
+
+                assert_eq!(Some(0), state.get_tile(index).hint(), "seed {seed}");
+                assert!(
+                    state
+                        .iter_tiles()
+                        .filter(|(_, _, tile)| tile.hint().is_some())
+                        .count()
+                        > 1
+                );
+                assert_eq!(
+                    4,
+                    state
+                        .iter_tiles()
+                        .filter(|(_, _, tile)| tile.has_mine())
+                        .count()
+                );
+            }
+        }
+
+        #[test]
+        fn no_guess_boards_clear_by_logic_alone() {
+            for seed in 0..20 {
+                let mut state = new_game(&Difficulty::Medium, Some(seed)).unwrap();
+                state.set_no_guess(true);
+                let index = (seed * 7 % 64) as usize;
+
+                state.reveal(index);
+
+                assert!(!state.get_game_over(), "seed {seed}");
+                assert_eq!(
+                    14,
+                    state.tiles().filter(|(_, tile)| tile.has_mine()).count()
+                );
+                assert_eq!(
+                    GameOutcome::Won,
+                    crate::solver::solve(&mut state),
+                    "seed {seed}"
+                );
+            }
+        }
+
+        #[test]
+        fn no_guess_keeps_boards_that_need_no_guess() {
+            // A mine in the corner, found from the numbers around the opening
+            let mut state = board_with_mines(3, 3, &[8]);
+            state.set_no_guess(true);
+
+            state.reveal(0);
+
+            assert!(state.get_tile(8).has_mine());
+            assert_eq!(GameOutcome::Won, state.outcome());
+        }
+
+        #[test]
+        fn solver_stops_at_a_guess() {
+            // Two tiles either side of a 1, with nothing to tell them apart
+            let state = board_with_mines(3, 1, &[0]);
+
+            assert!(!crate::solver::solvable_from(&state, 1));
+            assert!(crate::solver::solvable_from(&state, 2));
+            assert_eq!(None, state.get_tile(1).hint());
+        }
+
+        #[test]
+        fn hints_clear_a_safe_tile() {
+            let mut state = board_with_mines(4, 1, &[3]);
+            state.set_tile(
+                1,
+                Tile::Revealed {
+                    has_mine: false,
+                    hint: 0,
+                },
+            );
+
+            assert_eq!(Ok(()), state.check_move(Action::Hint));
+            assert_eq!(Some(0), state.hint());
+            assert_eq!(Some(0), state.get_tile(0).hint());
+            assert_eq!(1, state.get_hints_used());
+        }
+
+        #[test]
+        fn hints_flag_a_certain_mine_when_nothing_is_safe() {
+            let mut state = board_with_mines(6, 1, &[0, 3]);
+            clear(&mut state, 5);
+
+            assert_eq!(Some(3), state.hint());
+            assert!(matches!(
+                state.get_tile(3),
+                Tile::Hidden { flagged: true, .. }
+            ));
+
+            // One mine left somewhere in the first three tiles
+            assert_eq!(None, state.hint());
+            assert_eq!(
+                Err(crate::MoveError::NoCertainMove),
+                state.check_move(Action::Hint)
+            );
+            assert_eq!(1, state.get_hints_used());
+        }
+
+        #[test]
+        fn hints_look_past_wrong_flags() {
+            let mut state = board_with_mines(3, 1, &[0]);
+            state.set_tile(
+                1,
+                Tile::Revealed {
+                    has_mine: false,
+                    hint: 1,
+                },
+            );
+            flag(&mut state, 2);
+
+            // Trusting the flag would make the mine look safe
+            assert_eq!(None, state.hint());
+            assert!(!state.get_game_over());
+        }
+
+        #[test]
+        fn counts_the_clicks_a_board_needs() {
+            // One opening clears everything
+            assert_eq!(1, board_3bv(&board_with_mines(3, 3, &[0])));
+            // Numbers away from any opening each take a click
+            assert_eq!(1, board_3bv(&board_with_mines(3, 1, &[0, 2])));
+            assert_eq!(2, board_3bv(&board_with_mines(3, 1, &[1])));
+            // An opening with its edge, then a number on its own
+            assert_eq!(2, board_3bv(&board_with_mines(5, 1, &[2, 4])));
+            // Two openings either side of a wall of mines
+            assert_eq!(2, board_3bv(&board_with_mines(5, 3, &[2, 7, 12])));
+        }
+
+        #[test]
+        fn counts_the_3bv_done_so_far() {
+            let mut state = board_with_mines(5, 1, &[2, 4]);
+            assert_eq!(0, solved_3bv(&state));
+
+            // The number on its own
+            clear(&mut state, 3);
+            assert_eq!(1, solved_3bv(&state));
+
+            // The opening, whichever of its tiles is cleared
+            clear(&mut state, 1);
+            assert_eq!(1, solved_3bv(&state));
+            clear(&mut state, 0);
+            assert_eq!(2, solved_3bv(&state));
+        }
+
+        #[test]
+        fn first_clear_never_hits_a_mine() {
+            let mut state = board_with_mines(3, 3, &[0, 1]);
+            state.set_first_click_safe(true);
+
+            assert!(state.reveal(0));
+
+            assert!(!state.get_game_over());
+            assert!(!state.get_tile(0).has_mine());
+            assert_eq!(2, state.tiles().filter(|(_, tile)| tile.has_mine()).count());
+
+            // Only the first clear is spared
+            state.reveal(1);
+            assert!(state.get_game_over());
+        }
+
+        #[test]
+        fn first_clear_stays_safe_on_crowded_boards() {
+            // Every tile but the corner is a mine, so only the clicked tile can be spared
+            let mut state = board_with_mines(3, 3, &[0, 1, 2, 3, 4, 5, 6, 7]);
+            state.set_first_click_zero(true);
+
+            state.reveal(4);
+
+            assert!(!state.get_game_over());
+            assert_eq!(Some(8), state.get_tile(4).hint());
+            assert!(state.get_tile(8).has_mine());
+        }
+
+        #[test]
+        fn flags_do_not_count_as_turns() {
+            let mut state = board_with_mines(5, 5, &[0, 1, 2, 3]);
+
+            flag(&mut state, 0);
+            flag(&mut state, 1);
+            flag(&mut state, 2);
+
+            assert_eq!(0, state.get_turn_count());
+        }
+
+        #[test]
+        fn wins_by_flagging_every_mine() {
+            let mut state = board_with_mines(4, 1, &[0, 3]);
+            state.set_win_condition(WinCondition::FlagMines);
+
+            // A wrong flag doesn't count, even once every mine has one
+            flag(&mut state, 1);
+            flag(&mut state, 0);
+            assert!(!state.get_won());
+
+            flag(&mut state, 1);
+            flag(&mut state, 3);
+            assert!(state.get_won());
+        }
+
+        #[test]
+        fn clearing_alone_wins_only_when_allowed() {
+            let mut state = board_with_mines(3, 1, &[0]);
+            state.set_win_condition(WinCondition::FlagMines);
+            clear(&mut state, 2);
+            assert!(!state.get_won());
+
+            flag(&mut state, 0);
+            assert!(state.get_won());
+
+            let mut state = board_with_mines(3, 1, &[0]);
+            state.set_win_condition(WinCondition::Either);
+            clear(&mut state, 2);
+            assert!(state.get_won());
+        }
+
+        #[test]
+        fn simulates_games_to_their_outcome() {
+            let mut state = board_with_mines(3, 1, &[0]);
+            assert_eq!(GameOutcome::InProgress, simulate(&mut state, &[]));
+
+            let lost = simulate(&mut state, &[Action::Reveal(Coord::new(0, 0))]);
+            assert_eq!(GameOutcome::Lost, lost);
+
+            // Nothing after the winning move is played
+            let mut state = board_with_mines(3, 1, &[0]);
+            let moves = [
+                Action::Reveal(Coord::new(0, 2)),
+                Action::Flag(Coord::new(0, 0)),
+            ];
+            assert_eq!(GameOutcome::Won, simulate(&mut state, &moves));
+            assert_eq!(0, state.get_flag_operations());
+        }
+
+        #[test]
+        fn simulates_many_seeded_games() {
+            let strategy = |state: &GameState| {
+                (0..state.board().tiles().len())
+                    .filter(|&index| matches!(state.get_tile(index), Tile::Hidden { .. }))
+                    .map(|index| Action::Reveal(Coord::from_index(index, state.get_width())))
+                    .collect::<Vec<_>>()
+            };
+
+            for seed in 0..50 {
+                let mut state = new_game(&Difficulty::Easy, Some(seed)).unwrap();
+                let moves = strategy(&state);
+
+                // Clearing every tile in order always ends the game one way or the other
+                assert_ne!(GameOutcome::InProgress, simulate(&mut state, &moves));
+            }
+        }
+    }
+}
+
+// Deductions about hidden tiles drawn only from what the player can see
+pub mod solver {
+
+    use super::{engine::find_neighbors, GameOutcome, GameState, Tile, WinCondition};
+    use std::collections::BTreeSet;
+
+    // Larger groups of linked tiles fall back to a per-number ratio rather than enumeration
+    const MAX_ENUMERATED: usize = 16;
+
+    // A revealed number: exactly `mines` of the unknown `cells` around it hold a mine
+    struct Constraint {
+        cells: Vec<usize>,
+        mines: u32,
+    }
+
+    fn is_unknown(state: &GameState, index: usize) -> bool {
+        matches!(
+            state.get_tile(index),
+            Tile::Hidden {
+                has_mine: _,
+                flagged: false,
+                ..
+            }
+        )
+    }
+
+    fn constraints(state: &GameState) -> Vec<Constraint> {
+        let mut constraints = Vec::new();
+
+        for index in 0..state.board.tiles.len() {
+            if let Some(hint) = state.get_tile(index).hint() {
+                let neighbors = find_neighbors(state, index);
+
+                let cells: Vec<usize> = neighbors
+                    .iter()
+                    .copied()
+                    .filter(|&neighbor| is_unknown(state, neighbor))
+                    .collect();
+
+                if cells.is_empty() {
+                    continue;
+                }
+
+                // Flags and spent-life mines already account for part of the hint
+                let known = neighbors
+                    .iter()
+                    .filter(|&&neighbor| {
+                        matches!(
+                            state.get_tile(neighbor),
+                            Tile::Hidden { flagged: true, .. }
+                                | Tile::Revealed { has_mine: true, .. }
+                        )
+                    })
+                    .count();
+
+                constraints.push(Constraint {
+                    cells,
+                    mines: hint.saturating_sub(u32::try_from(known).unwrap_or(u32::MAX)),
+                });
+            }
+        }
+
+        constraints
+    }
+
+    // Split constraints into independent groups that share no cells
+    fn groups(constraints: &[Constraint]) -> Vec<Vec<usize>> {
+        let mut assigned = vec![false; constraints.len()];
+        let mut groups = Vec::new();
+
+        for start in 0..constraints.len() {
+            if assigned[start] {
+                continue;
+            }
+
+            assigned[start] = true;
+            let mut group = vec![start];
+            let mut pending = vec![start];
+
+            while let Some(current) = pending.pop() {
+                for other in 0..constraints.len() {
+                    if !assigned[other]
+                        && constraints[other]
+                            .cells
+                            .iter()
+                            .any(|cell| constraints[current].cells.contains(cell))
+                    {
+                        assigned[other] = true;
+                        group.push(other);
+                        pending.push(other);
+                    }
+                }
+            }
+
+            groups.push(group);
+        }
+
+        groups
+    }
+
+    // Count every mine layout of `cells` that satisfies `rules` (given as local cell positions),
+    // tallying how many of those layouts put a mine on each cell
+    fn count_layouts(
+        rules: &[(Vec<usize>, u32)],
+        layout: &mut Vec<bool>,
+        cell_count: usize,
+        mine_tally: &mut [u64],
+        total: &mut u64,
+    ) {
+        let position = layout.len();
+
+        // Prune as soon as any number is overfilled or can no longer be reached
+        for (cells, mines) in rules {
+            let placed = cells
+                .iter()
+                .filter(|&&cell| cell < position && layout[cell])
+                .count();
+            let open = cells.iter().filter(|&&cell| cell >= position).count();
+
+            if placed > *mines as usize || placed + open < *mines as usize {
+                return;
+            }
+        }
+
+        if position == cell_count {
+            *total += 1;
+            for (tally, &mine) in mine_tally.iter_mut().zip(layout.iter()) {
+                if mine {
+                    *tally += 1;
+                }
+            }
+            return;
+        }
+
+        for mine in [false, true] {
+            layout.push(mine);
+            count_layouts(rules, layout, cell_count, mine_tally, total);
+            layout.pop();
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)] // Tile and layout counts are far below f64 precision
+    #[must_use]
+    pub fn mine_probabilities(state: &GameState) -> Vec<Option<f64>> {
+        let unknown = (0..state.board.tiles.len())
+            .filter(|&index| is_unknown(state, index))
+            .count();
+
+        // Tiles away from any number only have the overall density to go on
+        let density = if unknown == 0 {
+            0.0
+        } else {
+            (f64::from(state.get_mine_count()) / unknown as f64).min(1.0)
+        };
+
+        let mut probabilities: Vec<Option<f64>> = (0..state.board.tiles.len())
+            .map(|index| is_unknown(state, index).then_some(density))
+            .collect();
+
+        let constraints = constraints(state);
+
+        for group in groups(&constraints) {
+            let cells: Vec<usize> = group
+                .iter()
+                .flat_map(|&rule| constraints[rule].cells.iter().copied())
+                .collect::<BTreeSet<usize>>()
+                .into_iter()
+                .collect();
+
+            let mut mine_tally = vec![0; cells.len()];
+            let mut total = 0;
+
+            if cells.len() <= MAX_ENUMERATED {
+                let rules: Vec<(Vec<usize>, u32)> = group
+                    .iter()
+                    .map(|&rule| {
+                        let local = constraints[rule]
+                            .cells
+                            .iter()
+                            .filter_map(|cell| cells.iter().position(|other| other == cell))
+                            .collect();
+                        (local, constraints[rule].mines)
+                    })
+                    .collect();
+
+                count_layouts(
+                    &rules,
+                    &mut Vec::with_capacity(cells.len()),
+                    cells.len(),
+                    &mut mine_tally,
+                    &mut total,
+                );
+            }
+
+            for (position, &cell) in cells.iter().enumerate() {
+                probabilities[cell] = Some(if total > 0 {
+                    mine_tally[position] as f64 / total as f64
+                } else {
+                    // Too many linked tiles to enumerate (or contradictory flags),
+                    // so take the most pessimistic ratio of any neighboring number
+                    group
+                        .iter()
+                        .filter(|&&rule| constraints[rule].cells.contains(&cell))
+                        .map(|&rule| {
+                            f64::from(constraints[rule].mines)
+                                / constraints[rule].cells.len() as f64
+                        })
+                        .fold(0.0, f64::max)
+                        .min(1.0)
+                });
+            }
+        }
+
+        probabilities
+    }
+
+    // Whether clearing `start` and then only ever making certain moves wins the game. Works on a
+    // copy, so `state` is left as it was.
+    #[must_use]
+    pub fn solvable_from(state: &GameState, start: usize) -> bool {
+        let mut trial = state.clone();
+        trial.set_first_click_zero(false);
+        trial.set_first_click_safe(false);
+        trial.set_no_guess(false);
+        trial.set_flagging_allowed(true);
+        trial.set_win_condition(WinCondition::RevealSafe);
+        trial.set_time_limit(None);
+
+        trial.reveal(start) && solve(&mut trial) == GameOutcome::Won
+    }
+
+    // Make certain moves until the game is decided or only guesses are left
+    pub fn solve(state: &mut GameState) -> GameOutcome {
+        while state.outcome() == GameOutcome::InProgress {
+            if state.auto_solve_step() {
+                continue;
+            }
+
+            // Nothing follows from one number at a time, so weigh every layout of each group
+            let mut changed = false;
+            for (index, chance) in mine_probabilities(state).into_iter().enumerate() {
+                match chance {
+                    Some(chance) if chance <= 0.0 => changed |= state.reveal(index),
+                    Some(chance) if chance >= 1.0 => changed |= state.toggle_flag(index),
+                    _ => {}
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        state.outcome()
+    }
+
+    // A hidden tile that is certainly safe (false) or certainly a mine (true), preferring safe
+    // tiles and leaving out mines that are already flagged. Flags are set aside first, since
+    // the player's could be wrong.
+    #[must_use]
+    pub fn certain_move(state: &GameState) -> Option<(usize, bool)> {
+        let probabilities = unflagged_probabilities(state);
+
+        let safe = probabilities
+            .iter()
+            .position(|chance| chance.is_some_and(|chance| chance <= 0.0));
+        let mine = || {
+            probabilities
+                .iter()
+                .enumerate()
+                .position(|(index, chance)| {
+                    chance.is_some_and(|chance| chance >= 1.0)
+                        && state.get_flagging_allowed()
+                        && matches!(state.get_tile(index), Tile::Hidden { flagged: false, .. })
+                        && state.get_mine_count() > 0
+                })
+        };
+
+        safe.map(|index| (index, false))
+            .or_else(|| mine().map(|index| (index, true)))
+    }
+
+    // As `mine_probabilities`, with the player's flags set aside since they could be wrong. What
+    // this proves follows from the numbers on show alone.
+    pub(crate) fn unflagged_probabilities(state: &GameState) -> Vec<Option<f64>> {
+        let mut unflagged = state.clone();
+        for index in 0..unflagged.board.tiles.len() {
+            if let Tile::Hidden {
+                has_mine,
+                flagged: true,
+                questioned,
+            } = unflagged.board.tiles[index]
+            {
+                unflagged.board.tiles[index] = Tile::Hidden {
+                    has_mine,
+                    flagged: false,
+                    questioned,
+                };
+                unflagged.increment_mine_count();
+            }
+        }
+
+        mine_probabilities(&unflagged)
+    }
+
+    // Fit a probability into a tile's three characters
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Always within 0-100
+    #[must_use]
+    pub fn format_probability(chance: f64) -> String {
+        if chance >= 1.0 {
+            String::from(" ! ")
+        } else {
+            format!("{:>2}%", ((chance * 100.0).round() as u32).min(99))
+        }
+    }
+}
+
+// Recorded games that can be played back move by move
+pub mod replay {
+
+    use super::{
+        input_handler::InputMode, Adjacency, BoardError, Difficulty, GameState, ParseError, Shape,
+        WinCondition,
+    };
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq)]
+    #[allow(clippy::struct_excessive_bools)] // Each is an independent game option
+    pub struct Replay {
+        pub difficulty: Difficulty,
+        pub seed: u64,
+        pub lives: u32,
+        pub first_click_zero: bool,
+        pub first_click_safe: bool,
+        pub no_guess: bool,
+        pub practice: bool,
+        pub question_marks: bool,
+        pub mines_per_tile: u32,
+        pub adjacency: Adjacency,
+        pub wrap: bool,
+        pub shape: Shape,
+        pub win_condition: WinCondition,
+        pub time_limit: Option<Duration>,
+        // The game clock when the log was written, kept for time attack games
+        pub elapsed: Option<Duration>,
+        // Clicks the player made, which older logs didn't record
+        pub clicks: Option<u32>,
+        pub moves: Vec<(usize, InputMode)>,
+        // When each move was made on the game clock, if the log recorded it
+        pub times: Vec<Option<Duration>>,
+    }
+
+    // One `mode index` line of a log, with the time in milliseconds after it in newer logs
+    pub(crate) fn parse_move(line: &str) -> Option<(usize, InputMode, Option<Duration>)> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (input_mode, index, time) = match fields[..] {
+            [input_mode, index] => (input_mode, index, None),
+            [input_mode, index, millis] => (
+                input_mode,
+                index,
+                Some(Duration::from_millis(millis.parse().ok()?)),
+            ),
+            _ => return None,
+        };
+
+        Some((index.parse().ok()?, input_mode.parse().ok()?, time))
+    }
+
+    impl Replay {
+        /// Read a log written by `GameState::export_moves`.
+        ///
+        /// # Errors
+        ///
+        /// Returns a `ParseError` for a malformed header (including a board without a seed,
+        /// which can't be recreated) or move line.
+        pub fn parse(log: &str) -> Result<Replay, ParseError> {
+            let mut entries = log.lines().map(str::trim).filter(|line| !line.is_empty());
+
+            let header = entries
+                .next()
+                .ok_or_else(|| ParseError::new("replay", log))?;
+            let bad_header = || ParseError::new("replay header", header);
+
+            let fields: Vec<&str> = header.split_whitespace().collect();
+            let [size, mines, seed, lives, ref options @ ..] = fields[..] else {
+                return Err(bad_header());
+            };
+
+            let (mut first_click_zero, mut first_click_safe, mut no_guess) = (false, true, false);
+            let (mut practice, mut clicks, mut mines_per_tile) = (false, None, 1);
+            let mut question_marks = false;
+            let (mut adjacency, mut wrap, mut shape) = (Adjacency::Eight, false, Shape::Rectangle);
+            let mut win_condition = WinCondition::RevealSafe;
+            let (mut time_limit, mut elapsed) = (None, None);
+            for &option in options {
+                match option {
+                    "opening" => first_click_zero = true,
+                    "risky" => first_click_safe = false,
+                    "no-guess" => no_guess = true,
+                    "practice" => practice = true,
+                    "questions" => question_marks = true,
+                    "four" => adjacency = Adjacency::Four,
+                    "triangles" => adjacency = Adjacency::Triangle,
+                    "wrap" => wrap = true,
+                    _ => match option.split_once('=') {
+                        Some(("clicks", count)) => {
+                            clicks = Some(count.parse().map_err(|_| bad_header())?);
+                        }
+                        Some(("stacks", most)) => {
+                            mines_per_tile = most.parse().map_err(|_| bad_header())?;
+                        }
+                        Some(("shape", name)) => shape = name.parse().map_err(|_| bad_header())?,
+                        Some(("win", condition)) => {
+                            win_condition = condition.parse().map_err(|_| bad_header())?;
+                        }
+                        Some(("limit", secs)) => {
+                            time_limit =
+                                Some(Duration::from_secs(secs.parse().map_err(|_| bad_header())?));
+                        }
+                        Some(("elapsed", millis)) => {
+                            elapsed = Some(Duration::from_millis(
+                                millis.parse().map_err(|_| bad_header())?,
+                            ));
+                        }
+                        _ => return Err(bad_header()),
+                    },
+                }
+            }
+            let (width, height) = size.split_once('x').ok_or_else(bad_header)?;
+
+            let difficulty = Difficulty::from_dimensions(
+                width.parse().map_err(|_| bad_header())?,
+                height.parse().map_err(|_| bad_header())?,
+                mines.parse().map_err(|_| bad_header())?,
+            );
+
+            let mut moves = Vec::new();
+            let mut times = Vec::new();
+
+            for line in entries {
+                let (index, input_mode, time) =
+                    parse_move(line).ok_or_else(|| ParseError::new("replay move", line))?;
+                moves.push((index, input_mode));
+                times.push(time);
+            }
+
+            Ok(Replay {
+                difficulty,
+                seed: seed.parse().map_err(|_| bad_header())?,
+                lives: lives.parse().map_err(|_| bad_header())?,
+                first_click_zero,
+                first_click_safe,
+                no_guess,
+                practice,
+                question_marks,
+                mines_per_tile,
+                adjacency,
+                wrap,
+                shape,
+                win_condition,
+                time_limit,
+                elapsed,
+                clicks,
+                moves,
+                times,
+            })
+        }
+
+        /// The board as it was before the first move.
+        ///
+        /// # Errors
+        ///
+        /// Returns a `BoardError` if the recorded board can't be played.
+        pub fn new_game(&self) -> Result<GameState, BoardError> {
+            let mut state =
+                super::engine::new_shaped_game(&self.difficulty, Some(self.seed), self.shape)?;
+            state.set_lives(self.lives);
+            state.set_first_click_zero(self.first_click_zero);
+            state.set_first_click_safe(self.first_click_safe);
+            state.set_no_guess(self.no_guess);
+            state.set_practice(self.practice);
+            state.set_mines_per_tile(self.mines_per_tile);
+            state.set_adjacency(self.adjacency);
+            state.set_wrap(self.wrap);
+            state.set_win_condition(self.win_condition);
+            state.set_question_marks(self.question_marks);
+            state.set_time_limit(self.time_limit);
+
+            Ok(state)
+        }
+
+        // Wind the game clock to when move `step` was made, if the log says
+        pub fn set_clock(&self, state: &mut GameState, step: usize) {
+            if let Some(&Some(time)) = self.times.get(step) {
+                state.set_elapsed(time);
+            }
+        }
+
+        // Wind the game clock on to when the log was written, where a time attack game that ran
+        // out ends, as it did when played
+        pub fn stop_clock(&self, state: &mut GameState) {
+            if let Some(elapsed) = self.elapsed {
+                state.set_elapsed(elapsed);
+                state.check_time_limit();
+            }
+        }
+
+        pub fn apply(state: &mut GameState, index: usize, input_mode: InputMode) -> bool {
+            match input_mode {
+                InputMode::Clear => state.reveal(index),
+                InputMode::Flag => state.toggle_flag(index),
+                InputMode::Safe => state.safe_reveal(index),
+                InputMode::Chord => state.chord(index),
+                // Undone moves are dropped from the log, and hints are logged as the move they
+                // made, so these never appear in it
+                InputMode::Hint
+                | InputMode::Cancel
+                | InputMode::Undo
+                | InputMode::Redo
+                | InputMode::Pause
+                | InputMode::Menu => false,
+            }
+        }
+
+        /// The board as it was after the last move.
+        ///
+        /// # Errors
+        ///
+        /// Returns a `BoardError` if the recorded board can't be played.
+        pub fn run(&self) -> Result<GameState, BoardError> {
+            let mut state = self.new_game()?;
+
+            for (step, &(index, input_mode)) in self.moves.iter().enumerate() {
+                self.set_clock(&mut state, step);
+                Replay::apply(&mut state, index, input_mode);
+            }
+            self.stop_clock(&mut state);
+
+            if let Some(clicks) = self.clicks {
+                state.clicks = clicks;
+            }
+
+            Ok(state)
+        }
+    }
+}
+
+// Win/loss records that persist between runs of the program
+pub mod stats {
+
+    use super::Difficulty;
+    use std::{
+        collections::BTreeMap,
+        env,
+        fmt::{self, Write},
+        fs, io,
+        path::{Path, PathBuf},
+        str::FromStr,
+        time::Duration,
+    };
+
+    #[derive(Debug, Default, Clone, PartialEq)]
+    pub struct Record {
+        pub played: u32,
+        pub won: u32,
+        pub best_time: Option<u64>,
+        pub fewest_turns: Option<u32>,
+        // Wins in a row, broken by any loss
+        pub current_streak: u32,
+        pub best_streak: u32,
+        pub best_score: Option<i64>,
+        // Totals over every game, won or lost
+        pub total_time: u64,
+        pub tiles_cleared: u64,
+        pub mines_hit: u64,
+    }
+
+    impl Record {
+        #[must_use]
+        pub fn win_rate(&self) -> f64 {
+            if self.played == 0 {
+                0.0
+            } else {
+                f64::from(self.won) / f64::from(self.played) * 100.0
+            }
+        }
+
+        // Seconds per game played, or None before the first
+        #[allow(clippy::cast_precision_loss)] // Nobody plays for 2^52 seconds
+        #[must_use]
+        pub fn average_time(&self) -> Option<f64> {
+            (self.played > 0).then(|| self.total_time as f64 / f64::from(self.played))
+        }
+    }
+
+    // Records are keyed by difficulty, with custom boards keyed by their dimensions
+    #[derive(Debug, Default, PartialEq)]
+    pub struct Stats {
+        records: BTreeMap<String, Record>,
+    }
+
+    #[must_use]
+    pub fn key(difficulty: &Difficulty) -> String {
+        match difficulty {
+            Difficulty::Custom {
+                width,
+                height,
+                mines,
+            } => format!("custom-{width}x{height}x{mines}"),
+            preset => preset.to_string().to_lowercase(),
+        }
+    }
+
+    // Where program data lives, falling back to the working directory
+    #[must_use]
+    pub fn data_dir() -> PathBuf {
+        if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+            PathBuf::from(dir).join("minesweeper")
+        } else if let Some(dir) = env::var_os("APPDATA") {
+            PathBuf::from(dir).join("minesweeper")
+        } else if let Some(home) = env::var_os("HOME") {
+            PathBuf::from(home).join(".local/share/minesweeper")
+        } else {
+            PathBuf::from(".")
+        }
+    }
+
+    fn parse_optional<T: FromStr>(field: &str) -> Result<Option<T>, T::Err> {
+        if field == "-" {
+            Ok(None)
+        } else {
+            field.parse().map(Some)
+        }
+    }
+
+    fn format_optional<T: fmt::Display>(value: Option<T>) -> String {
+        value.map_or_else(|| String::from("-"), |value| value.to_string())
+    }
+
+    impl Stats {
+        #[must_use]
+        pub fn default_path() -> PathBuf {
+            data_dir().join("stats.txt")
+        }
+
+        // One record per line: `key played won best_time fewest_turns current_streak best_streak
+        // best_score total_time tiles_cleared mines_hit`, with `-` for no value. Files from before
+        // streaks, scores, or totals were kept stop short of them. Returns None if any line is
+        // malformed.
+        #[must_use]
+        pub fn parse(text: &str) -> Option<Stats> {
+            // What the fields after `fewest_turns` start as in older files
+            const LATER_FIELDS: [&str; 6] = ["0", "0", "-", "0", "0", "0"];
+
+            let mut stats = Stats::default();
+
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+
+                let [key, played, won, best_time, fewest_turns, ref later @ ..] = fields[..] else {
+                    return None;
+                };
+                if ![0, 2, 3, 6].contains(&later.len()) {
+                    return None;
+                }
+                let later = |field: usize| later.get(field).copied().unwrap_or(LATER_FIELDS[field]);
+
+                let record = Record {
+                    played: played.parse().ok()?,
+                    won: won.parse().ok()?,
+                    best_time: parse_optional(best_time).ok()?,
+                    fewest_turns: parse_optional(fewest_turns).ok()?,
+                    current_streak: later(0).parse().ok()?,
+                    best_streak: later(1).parse().ok()?,
+                    best_score: parse_optional(later(2)).ok()?,
+                    total_time: later(3).parse().ok()?,
+                    tiles_cleared: later(4).parse().ok()?,
+                    mines_hit: later(5).parse().ok()?,
+                };
+
+                stats.records.insert(key.to_string(), record);
+            }
+
+            Some(stats)
+        }
+
+        // Missing or corrupt files start a fresh set of stats
+        #[must_use]
+        pub fn load(path: &Path) -> Stats {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|text| Stats::parse(&text))
+                .unwrap_or_default()
+        }
+
+        /// # Errors
+        ///
+        /// Returns any error from creating the data directory or writing the file.
+        pub fn save(&self, path: &Path) -> io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            fs::write(path, self.serialize())
+        }
+
+        #[must_use]
+        pub fn serialize(&self) -> String {
+            let mut text = String::new();
+
+            for (key, record) in &self.records {
+                // Writing to a String can't fail
+                let _ = writeln!(
+                    text,
+                    "{key} {} {} {} {} {} {} {} {} {} {}",
+                    record.played,
+                    record.won,
+                    format_optional(record.best_time),
+                    format_optional(record.fewest_turns),
+                    record.current_streak,
+                    record.best_streak,
+                    format_optional(record.best_score),
+                    record.total_time,
+                    record.tiles_cleared,
+                    record.mines_hit,
+                );
+            }
+
+            text
+        }
+
+        #[must_use]
+        pub fn get(&self, difficulty: &Difficulty) -> Option<&Record> {
+            self.records.get(&key(difficulty))
+        }
+
+        pub fn record_game(
+            &mut self,
+            difficulty: &Difficulty,
+            won: bool,
+            turns: u32,
+            elapsed: Duration,
+        ) {
+            self.record_game_as(key(difficulty), won, turns, elapsed);
+        }
+
+        // Record a game under a key of its own, rather than just its difficulty
+        pub fn record_game_as(&mut self, key: String, won: bool, turns: u32, elapsed: Duration) {
+            let record = self.records.entry(key).or_default();
+
+            record.played += 1;
+            record.total_time += elapsed.as_secs();
+
+            if won {
+                let seconds = elapsed.as_secs();
+
+                record.won += 1;
+                record.best_time = Some(record.best_time.map_or(seconds, |best| best.min(seconds)));
+                record.fewest_turns =
+                    Some(record.fewest_turns.map_or(turns, |best| best.min(turns)));
+
+                record.current_streak += 1;
+                record.best_streak = record.best_streak.max(record.current_streak);
+            } else {
+                record.current_streak = 0;
+            }
+        }
+
+        // Keep the highest score seen under a key, for the leaderboard
+        pub fn record_score(&mut self, key: &str, score: i64) {
+            let record = self.records.entry(key.to_string()).or_default();
+            record.best_score = Some(record.best_score.map_or(score, |best| best.max(score)));
+        }
+
+        // Add a game's cleared tiles and set off mines to the totals under a key
+        pub fn record_tiles(&mut self, key: &str, tiles_cleared: u32, mines_hit: u32) {
+            let record = self.records.entry(key.to_string()).or_default();
+            record.tiles_cleared += u64::from(tiles_cleared);
+            record.mines_hit += u64::from(mines_hit);
+        }
+
+        #[must_use]
+        pub fn get_key(&self, key: &str) -> Option<&Record> {
+            self.records.get(key)
+        }
+
+        // Every record added together. Bests and streaks are per board, so they're left out.
+        #[must_use]
+        pub fn totals(&self) -> Record {
+            let mut totals = Record::default();
+
+            for record in self.records.values() {
+                totals.played += record.played;
+                totals.won += record.won;
+                totals.total_time += record.total_time;
+                totals.tiles_cleared += record.tiles_cleared;
+                totals.mines_hit += record.mines_hit;
+            }
+
+            totals
+        }
+    }
+
+    impl fmt::Display for Stats {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if self.records.is_empty() {
+                return writeln!(f, "No games played yet.");
+            }
+
+            writeln!(
+                f,
+                "{:<20} {:>6} {:>6} {:>7} {:>9} {:>12} {:>6} {:>11} {:>10}",
+                "Difficulty",
+                "Played",
+                "Won",
+                "Win %",
+                "Best time",
+                "Fewest turns",
+                "Streak",
+                "Best streak",
+                "Best score"
+            )?;
+
+            for (key, record) in &self.records {
+                writeln!(
+                    f,
+                    "{key:<20} {:>6} {:>6} {:>6.1}% {:>9} {:>12} {:>6} {:>11} {:>10}",
+                    record.played,
+                    record.won,
+                    record.win_rate(),
+                    record
+                        .best_time
+                        .map_or_else(|| String::from("-"), |secs| format!("{secs}s")),
+                    format_optional(record.fewest_turns),
+                    record.current_streak,
+                    record.best_streak,
+                    format_optional(record.best_score),
+                )?;
+            }
+
+            writeln!(
+                f,
+                "\n{:<20} {:>6} {:>7} {:>9} {:>13} {:>9}",
+                "Difficulty", "Played", "Win %", "Avg time", "Tiles cleared", "Mines hit"
+            )?;
+
+            let totals = self.totals();
+            let rows = self
+                .records
+                .iter()
+                .map(|(key, record)| (key.as_str(), record));
+
+            for (key, record) in rows.chain([("all", &totals)]) {
+                writeln!(
+                    f,
+                    "{key:<20} {:>6} {:>6.1}% {:>9} {:>13} {:>9}",
+                    record.played,
+                    record.win_rate(),
+                    record
+                        .average_time()
+                        .map_or_else(|| String::from("-"), |secs| format!("{secs:.1}s")),
+                    record.tiles_cleared,
+                    record.mines_hit,
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+// Turning a game into a single score, by a formula that can be tuned in a file of its own
+pub mod scoring {
+
+    use super::{engine, stats::data_dir, GameState, Tile};
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    // Points earned for each unit of 3BV done and for a win, and lost for each second on the
+    // clock and each mistake
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ScoreFormula {
+        pub per_3bv: i64,
+        pub win_bonus: i64,
+        pub per_second: i64,
+        pub per_mistake: i64,
+    }
+
+    impl Default for ScoreFormula {
+        fn default() -> Self {
+            ScoreFormula {
+                per_3bv: 100,
+                win_bonus: 500,
+                per_second: 2,
+                per_mistake: 100,
+            }
+        }
+    }
+
+    // Mines set off, flags left on safe tiles, and hints asked for
+    #[must_use]
+    pub fn mistakes(state: &GameState) -> u32 {
+        let wrong_flags = state
+            .board
+            .tiles
+            .iter()
+            .filter(|tile| {
+                matches!(
+                    tile,
+                    Tile::Hidden {
+                        has_mine: false,
+                        flagged: true,
+                        ..
+                    }
+                )
+            })
+            .count();
+
+        state.mines_hit() + u32::try_from(wrong_flags).unwrap_or(u32::MAX) + state.get_hints_used()
+    }
+
+    impl ScoreFormula {
+        #[must_use]
+        pub fn default_path() -> PathBuf {
+            data_dir().join("scoring.txt")
+        }
+
+        // One `name value` line for each weight to change, named as the fields are.
+        // Returns None for a name it doesn't know or a value that isn't a whole number.
+        #[must_use]
+        pub fn parse(text: &str) -> Option<ScoreFormula> {
+            let mut formula = ScoreFormula::default();
+
+            for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                let (name, value) = line.split_once(char::is_whitespace)?;
+                let value = value.trim().parse().ok()?;
+
+                match name {
+                    "per_3bv" => formula.per_3bv = value,
+                    "win_bonus" => formula.win_bonus = value,
+                    "per_second" => formula.per_second = value,
+                    "per_mistake" => formula.per_mistake = value,
+                    _ => return None,
+                }
+            }
+
+            Some(formula)
+        }
+
+        // A missing or broken file leaves the default formula
+        #[must_use]
+        pub fn load(path: &Path) -> ScoreFormula {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|text| ScoreFormula::parse(&text))
+                .unwrap_or_default()
+        }
+
+        // Points for the game so far. A loss keeps what was earned for the 3BV done.
+        // Never drops below zero.
+        #[must_use]
+        pub fn score(&self, state: &GameState) -> i64 {
+            let seconds = i64::try_from(state.get_elapsed().as_secs()).unwrap_or(i64::MAX);
+
+            let earned = i64::from(engine::solved_3bv(state)).saturating_mul(self.per_3bv)
+                + if state.get_won() { self.win_bonus } else { 0 };
+            let lost = seconds
+                .saturating_mul(self.per_second)
+                .saturating_add(i64::from(mistakes(state)).saturating_mul(self.per_mistake));
+
+            earned.saturating_sub(lost).max(0)
+        }
+    }
+}
+
+// The best wins on each board, kept with the names of the players who made them
+pub mod leaderboard {
+
+    use super::stats::data_dir;
+    use std::{
+        collections::BTreeMap,
+        fmt::{self, Write},
+        fs, io,
+        path::{Path, PathBuf},
+    };
+
+    // Places kept on each table
+    pub const TOP_ENTRIES: usize = 10;
+    pub const MAX_NAME_LENGTH: usize = 20;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Ranking {
+        // Fastest first, in milliseconds
+        Time,
+        // Highest first
+        Score,
+    }
+
+    impl Ranking {
+        #[must_use]
+        pub fn name(self) -> &'static str {
+            match self {
+                Ranking::Time => "time",
+                Ranking::Score => "score",
+            }
+        }
+
+        // Whether `value` ranks above `other`. Ties go to whoever got there first.
+        fn beats(self, value: i64, other: i64) -> bool {
+            match self {
+                Ranking::Time => value < other,
+                Ranking::Score => value > other,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Entry {
+        pub name: String,
+        pub value: i64,
+    }
+
+    // Tables are keyed by ranking and by the same keys as `stats::Stats`
+    #[derive(Debug, Default, PartialEq)]
+    pub struct Leaderboard {
+        tables: BTreeMap<(String, Ranking), Vec<Entry>>,
+    }
+
+    // Names are one line of at most `MAX_NAME_LENGTH` characters, with runs of spaces collapsed
+    #[must_use]
+    pub fn clean_name(name: &str) -> String {
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        let name: String = name.chars().take(MAX_NAME_LENGTH).collect();
+
+        if name.trim().is_empty() {
+            String::from("Anonymous")
+        } else {
+            name.trim().to_string()
+        }
+    }
+
+    impl Leaderboard {
+        #[must_use]
+        pub fn default_path() -> PathBuf {
+            data_dir().join("leaderboard.txt")
+        }
+
+        // One entry per line, in order: `key ranking value name`, where the name runs to the end
+        // of the line. Returns None if any line is malformed.
+        #[must_use]
+        pub fn parse(text: &str) -> Option<Leaderboard> {
+            let mut leaderboard = Leaderboard::default();
+
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                let mut fields = line.trim().splitn(4, ' ');
+                let (Some(key), Some(ranking), Some(value), Some(name)) =
+                    (fields.next(), fields.next(), fields.next(), fields.next())
+                else {
+                    return None;
+                };
+
+                let ranking = match ranking {
+                    "time" => Ranking::Time,
+                    "score" => Ranking::Score,
+                    _ => return None,
+                };
+                let entry = Entry {
+                    name: clean_name(name),
+                    value: value.parse().ok()?,
+                };
+
+                leaderboard
+                    .tables
+                    .entry((key.to_string(), ranking))
+                    .or_default()
+                    .push(entry);
+            }
+
+            Some(leaderboard)
+        }
+
+        // Missing or corrupt files start an empty leaderboard
+        #[must_use]
+        pub fn load(path: &Path) -> Leaderboard {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|text| Leaderboard::parse(&text))
+                .unwrap_or_default()
+        }
+
+        /// # Errors
+        ///
+        /// Returns any error from creating the data directory or writing the file.
+        pub fn save(&self, path: &Path) -> io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            fs::write(path, self.serialize())
+        }
+
+        #[must_use]
+        pub fn serialize(&self) -> String {
+            let mut text = String::new();
+
+            for ((key, ranking), entries) in &self.tables {
+                for entry in entries {
+                    // Writing to a String can't fail
+                    let _ = writeln!(
+                        text,
+                        "{key} {} {} {}",
+                        ranking.name(),
+                        entry.value,
+                        entry.name
+                    );
+                }
+            }
+
+            text
+        }
+
+        #[must_use]
+        pub fn get(&self, key: &str, ranking: Ranking) -> &[Entry] {
+            self.tables
+                .get(&(key.to_string(), ranking))
+                .map_or(&[], Vec::as_slice)
+        }
+
+        // The place, counting from 1, that `value` would take on a table, or None if it wouldn't
+        // make the top `TOP_ENTRIES`
+        #[must_use]
+        pub fn place(&self, key: &str, ranking: Ranking, value: i64) -> Option<usize> {
+            let place = self
+                .get(key, ranking)
+                .iter()
+                .take_while(|entry| !ranking.beats(value, entry.value))
+                .count();
+
+            (place < TOP_ENTRIES).then_some(place + 1)
+        }
+
+        // Add an entry where it ranks, dropping whoever falls off the bottom. Returns its place,
+        // counting from 1, or None if it didn't make the table.
+        pub fn insert(&mut self, key: &str, ranking: Ranking, entry: Entry) -> Option<usize> {
+            let place = self.place(key, ranking, entry.value)?;
+            let table = self.tables.entry((key.to_string(), ranking)).or_default();
+
+            table.insert(place - 1, entry);
+            table.truncate(TOP_ENTRIES);
+
+            Some(place)
+        }
+    }
+
+    impl fmt::Display for Leaderboard {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if self.tables.is_empty() {
+                return writeln!(f, "No records yet. Win a game to set one.");
+            }
+
+            for ((key, ranking), entries) in &self.tables {
+                let title = match ranking {
+                    Ranking::Time => "best times",
+                    Ranking::Score => "best scores",
+                };
+                writeln!(f, "{key} {title}")?;
+
+                for (place, entry) in entries.iter().enumerate() {
+                    let value = match ranking {
+                        #[allow(clippy::cast_precision_loss)] // Times are far below f64 precision
+                        Ranking::Time => format!("{:.1}s", entry.value as f64 / 1000.0),
+                        Ranking::Score => entry.value.to_string(),
+                    };
+                    writeln!(
+                        f,
+                        "{:>4}. {:<width$} {value:>10}",
+                        place + 1,
+                        entry.name,
+                        width = MAX_NAME_LENGTH
+                    )?;
+                }
+
+                writeln!(f)?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+// A run of boards that grow larger and denser, with lives and score carried from one to the
+// next and progress kept between sessions
+pub mod campaign {
+
+    use super::{stats::data_dir, Difficulty};
+    use std::{
+        fs, io,
+        path::{Path, PathBuf},
+    };
+
+    // Width, height, and mine density of each level, in order
+    pub const LEVELS: [(u32, u32, u32); 8] = [
+        (5, 5, 12),
+        (6, 6, 13),
+        (8, 8, 14),
+        (9, 9, 15),
+        (10, 10, 16),
+        (12, 12, 17),
+        (14, 14, 18),
+        (16, 16, 20),
+    ];
+
+    // Lives to get through the whole campaign with
+    pub const STARTING_LIVES: u32 = 3;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Progress {
+        // The level being played, counting from 0
+        pub level: usize,
+        pub lives: u32,
+        pub score: i64,
+    }
+
+    impl Default for Progress {
+        fn default() -> Self {
+            Progress {
+                level: 0,
+                lives: STARTING_LIVES,
+                score: 0,
+            }
+        }
+    }
+
+    impl Progress {
+        #[must_use]
+        pub fn default_path() -> PathBuf {
+            data_dir().join("campaign.txt")
+        }
+
+        // `level lives score` on one line. Returns None if it's malformed.
+        #[must_use]
+        pub fn parse(text: &str) -> Option<Progress> {
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            let [level, lives, score] = fields[..] else {
+                return None;
+            };
+
+            Some(Progress {
+                level: level.parse().ok().filter(|&level| level <= LEVELS.len())?,
+                lives: lives.parse().ok()?,
+                score: score.parse().ok()?,
+            })
+        }
+
+        // A missing or corrupt file starts the campaign over
+        #[must_use]
+        pub fn load(path: &Path) -> Progress {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|text| Progress::parse(&text))
+                .unwrap_or_default()
+        }
+
+        /// # Errors
+        ///
+        /// Returns any error from creating the data directory or writing the file.
+        pub fn save(&self, path: &Path) -> io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            fs::write(path, self.serialize())
+        }
+
+        #[must_use]
+        pub fn serialize(&self) -> String {
+            format!("{} {} {}\n", self.level, self.lives, self.score)
+        }
+
+        // The board for the current level, or None once every level is cleared
+        #[must_use]
+        pub fn difficulty(&self) -> Option<Difficulty> {
+            LEVELS
+                .get(self.level)
+                .map(|&(width, height, density)| Difficulty::with_density(width, height, density))
+        }
+
+        #[must_use]
+        pub fn is_complete(&self) -> bool {
+            self.level >= LEVELS.len()
+        }
+
+        // Leaving a level part way through means starting it again next time, but the lives lost
+        // on it stay lost
+        pub fn leave_level(&mut self, lives: u32) {
+            self.lives = self.lives.min(lives);
+        }
+
+        // Move on after a level, keeping the lives left over and adding the level's score. A loss
+        // has used up every life, so the campaign starts over.
+        pub fn finish_level(&mut self, won: bool, lives: u32, score: i64) {
+            if won {
+                self.level += 1;
+                self.lives = lives;
+                self.score += score;
+            } else {
+                *self = Progress::default();
+            }
+        }
+    }
+}
+
+// Command line arguments for starting a game without going through the menu
+pub mod cli {
+
+    use super::{
+        engine, Adjacency, BoardError, Difficulty, GameState, Shape, MAX_DENSITY, MAX_HEIGHT,
+        MAX_LIVES, MAX_WIDTH,
+    };
+    use std::{fmt, path::PathBuf};
+
+    pub const USAGE: &str = "Usage: minesweeper [--difficulty easy|medium|hard] \
+[--width N] [--height N] [--mines N | --density PERCENT] [--seed N] [--lives N] [--opening] [--triangles] [--wrap] [--shape rectangle|diamond|cross|donut] [--replay FILE] [--layout FILE] [--json]";
+
+    #[derive(Debug, PartialEq)]
+    pub enum ArgError {
+        UnknownFlag(String),
+        MissingValue(String),
+        InvalidValue { flag: String, value: String },
+        InvalidBoard(BoardError),
+    }
+
+    impl fmt::Display for ArgError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ArgError::UnknownFlag(flag) => write!(f, "Unknown argument '{flag}'"),
+                ArgError::MissingValue(flag) => write!(f, "Missing value for '{flag}'"),
+                ArgError::InvalidValue { flag, value } => {
+                    write!(f, "Invalid value '{value}' for '{flag}'")
+                }
+                ArgError::InvalidBoard(error) => write!(f, "Invalid board: {error}"),
+            }
+        }
+    }
+
+    impl std::error::Error for ArgError {}
+
+    #[derive(Debug, Default, PartialEq)]
+    #[allow(clippy::struct_excessive_bools)] // Each is an independent switch
+    pub struct Args {
+        pub difficulty: Option<Difficulty>,
+        pub width: Option<u32>,
+        pub height: Option<u32>,
+        pub mines: Option<u32>,
+        // Mines as a percentage of the board, for when no count is given
+        pub density: Option<u32>,
+        pub seed: Option<u64>,
+        // Mines the player can hit before the game ends
+        pub lives: Option<u32>,
+        pub replay: Option<PathBuf>,
+        pub layout: Option<PathBuf>,
+        pub json: bool,
+        // The first clear always lands on a zero and opens up an area
+        pub opening: bool,
+        // Triangular tiles in place of squares
+        pub triangles: bool,
+        // Opposite edges of the board touch, so every tile has a full set of neighbors
+        pub wrap: bool,
+        // The outline to cut the board to
+        pub shape: Option<Shape>,
+    }
+
+    impl Args {
+        /// Parse arguments (excluding the program name) into a validated set of options.
+        ///
+        /// # Errors
+        ///
+        /// Returns an `ArgError` for unknown flags, missing or malformed values,
+        /// and boards that can't be played.
+        pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, ArgError> {
+            let mut parsed = Args::default();
+            let mut args = args.into_iter();
+
+            while let Some(flag) = args.next() {
+                // Switches don't take a value
+                if flag == "--json" {
+                    parsed.json = true;
+                    continue;
+                }
+                if flag == "--opening" || flag == "-o" {
+                    parsed.opening = true;
+                    continue;
+                }
+                if flag == "--triangles" {
+                    parsed.triangles = true;
+                    continue;
+                }
+                if flag == "--wrap" {
+                    parsed.wrap = true;
+                    continue;
+                }
+
+                let value = args
+                    .next()
+                    .ok_or_else(|| ArgError::MissingValue(flag.clone()))?;
+
+                let invalid = || ArgError::InvalidValue {
+                    flag: flag.clone(),
+                    value: value.clone(),
+                };
+
+                match flag.as_str() {
+                    "--difficulty" | "-d" => {
+                        parsed.difficulty = Some(value.parse().map_err(|_| invalid())?);
+                    }
+                    "--width" | "-w" => parsed.width = Some(value.parse().map_err(|_| invalid())?),
+                    "--height" | "-h" => {
+                        parsed.height = Some(value.parse().map_err(|_| invalid())?);
+                    }
+                    "--mines" | "-m" => parsed.mines = Some(value.parse().map_err(|_| invalid())?),
+                    "--seed" | "-s" => parsed.seed = Some(value.parse().map_err(|_| invalid())?),
+                    "--density" => {
+                        parsed.density = Some(
+                            value
+                                .trim_end_matches('%')
+                                .parse()
+                                .ok()
+                                .filter(|percent| (1..=MAX_DENSITY).contains(percent))
+                                .ok_or_else(invalid)?,
+                        );
+                    }
+                    "--lives" => {
+                        parsed.lives = Some(
+                            value
+                                .parse()
+                                .ok()
+                                .filter(|lives| (1..=MAX_LIVES).contains(lives))
+                                .ok_or_else(invalid)?,
+                        );
+                    }
+                    "--shape" => parsed.shape = Some(value.parse().map_err(|_| invalid())?),
+                    "--replay" | "-r" => parsed.replay = Some(PathBuf::from(value)),
+                    "--layout" | "-l" => parsed.layout = Some(PathBuf::from(value)),
+                    _ => return Err(ArgError::UnknownFlag(flag)),
+                }
+            }
+
+            if let Some(Difficulty::Custom {
+                width,
+                height,
+                mines,
+            }) = parsed.board()
+            {
+                if width > MAX_WIDTH {
+                    return Err(ArgError::InvalidValue {
+                        flag: String::from("--width"),
+                        value: format!("{width} (at most {MAX_WIDTH})"),
+                    });
+                }
+
+                if height > MAX_HEIGHT {
+                    return Err(ArgError::InvalidValue {
+                        flag: String::from("--height"),
+                        value: format!("{height} (at most {MAX_HEIGHT})"),
+                    });
+                }
+
+                GameState::validate_board(width, height, mines).map_err(ArgError::InvalidBoard)?;
+            }
+
+            if let Some((width, height, _)) = parsed.board().map(|board| board.dimensions()) {
+                if parsed.wrap
+                    && parsed.triangles
+                    && !engine::wraps_evenly(width, height, Adjacency::Triangle)
+                {
+                    return Err(ArgError::InvalidBoard(BoardError::UnevenWrap {
+                        width,
+                        height,
+                    }));
+                }
+            }
+
+            Ok(parsed)
+        }
+
+        // The board requested on the command line, if any.
+        // Individual dimensions override the chosen (or easy) preset.
+        #[must_use]
+        pub fn board(&self) -> Option<Difficulty> {
+            let preset = self.difficulty.clone();
+
+            if self.width.is_none()
+                && self.height.is_none()
+                && self.mines.is_none()
+                && self.density.is_none()
+            {
+                let board_options =
+                    self.seed.is_some() || self.lives.is_some() || self.shape.is_some();
+                return preset.or(board_options.then_some(Difficulty::Easy));
+            }
+
+            let (width, height, mines) = preset.unwrap_or(Difficulty::Easy).dimensions();
+            let (width, height) = (self.width.unwrap_or(width), self.height.unwrap_or(height));
+
+            // A count of mines wins out over a density
+            match (self.mines, self.density) {
+                (None, Some(percent)) => Some(Difficulty::with_density(width, height, percent)),
+                (count, _) => Some(Difficulty::Custom {
+                    width,
+                    height,
+                    mines: count.unwrap_or(mines),
+                }),
+            }
+        }
+
+        /// A ready to play game when any board options were given, otherwise None.
+        ///
+        /// # Errors
+        ///
+        /// Returns a `BoardError` if the requested board can't be played.
+        pub fn game_state(&self) -> Result<Option<GameState>, BoardError> {
+            self.board()
+                .map(|difficulty| {
+                    let shape = self.shape.unwrap_or_default();
+                    let mut state = engine::new_shaped_game(&difficulty, self.seed, shape)?;
+                    state.set_first_click_zero(self.opening);
+                    if self.triangles {
+                        state.set_adjacency(Adjacency::Triangle);
+                    }
+                    state.set_wrap(self.wrap);
+                    if let Some(lives) = self.lives {
+                        state.set_lives(lives);
+                    }
+                    Ok(state)
+                })
+                .transpose()
+        }
+    }
+}
+
+// Create a new module to handle input to the program
+pub mod input_handler {
+
+    use super::{
+        engine, leaderboard, Difficulty, GameMode, MinesweeperError, ParseError, Shape,
+        WinCondition, MAX_DENSITY, MAX_HEIGHT, MAX_WIDTH,
+    };
+    #[cfg(feature = "serde")]
+    use serde::{Deserialize, Serialize};
+    use std::{
+        fmt,
+        io::{self, BufRead, Write},
+        str::FromStr,
+    };
+
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub enum InputMode {
+        Clear,
+        Flag,
+        Safe,
+        Chord,
+        // Let the game make one certain move
+        Hint,
+        // Drop the selected tile and pick another
+        Cancel,
+        // Take back the last move
+        Undo,
+        Redo,
+        // Hide the board and stop the clock until the player is back
+        Pause,
+        Menu,
+    }
+
+    impl fmt::Display for InputMode {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                InputMode::Clear => f.pad("clear"),
+                InputMode::Flag => f.pad("flag"),
+                InputMode::Safe => f.pad("safe"),
+                InputMode::Chord => f.pad("chord"),
+                InputMode::Hint => f.pad("hint"),
+                InputMode::Cancel => f.pad("cancel"),
+                InputMode::Undo => f.pad("undo"),
+                InputMode::Redo => f.pad("redo"),
+                InputMode::Pause => f.pad("pause"),
+                InputMode::Menu => f.pad("menu"),
+            }
+        }
+    }
+
+    impl FromStr for InputMode {
+        type Err = ParseError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "clear" | "c" => Ok(InputMode::Clear),
+                "flag" | "f" => Ok(InputMode::Flag),
+                "safe" | "s" => Ok(InputMode::Safe),
+                "chord" | "h" => Ok(InputMode::Chord),
+                "hint" | "i" => Ok(InputMode::Hint),
+                "cancel" | "x" => Ok(InputMode::Cancel),
+                // Going back a move was once its own key
+                "undo" | "u" | "back" | "b" => Ok(InputMode::Undo),
+                "redo" | "r" => Ok(InputMode::Redo),
+                "pause" | "p" => Ok(InputMode::Pause),
+                "menu" | "m" | "quit" | "q" => Ok(InputMode::Menu),
+                _ => Err(ParseError::new("input mode", s)),
+            }
+        }
+    }
+
+    // What to do next once a game has ended
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum EndChoice {
+        Replay,
+        NewGame,
+        Configure,
+        Quit,
+    }
+
+    impl FromStr for EndChoice {
+        type Err = ParseError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "replay" | "r" => Ok(EndChoice::Replay),
+                "new" | "n" => Ok(EndChoice::NewGame),
+                "configure" | "c" => Ok(EndChoice::Configure),
+                "quit" | "q" => Ok(EndChoice::Quit),
+                _ => Err(ParseError::new("end of game choice", s)),
+            }
+        }
+    }
+
+    // Where the player's input comes from. Anything `BufRead` is one, so the game loop can read
+    // stdin through `io::stdin().lock()`, or a script of moves from a byte string.
+    pub trait InputSource {
+        /// Show `prompt` and read one line, without its line ending.
+        ///
+        /// # Errors
+        ///
+        /// Returns `MinesweeperError::Input` if reading fails, or with `UnexpectedEof` once the
+        /// input runs out.
+        fn next_line(&mut self, prompt: &str) -> Result<String, MinesweeperError>;
+    }
+
+    impl<R: BufRead> InputSource for R {
+        fn next_line(&mut self, prompt: &str) -> Result<String, MinesweeperError> {
+            print!("{prompt}");
+            io::stdout().flush()?;
+
+            let mut line = String::new();
+            if self.read_line(&mut line)? == 0 {
+                return Err(MinesweeperError::Input(io::ErrorKind::UnexpectedEof));
+            }
+
+            if let Some('\n') = line.chars().next_back() {
+                line.pop();
+            }
+
+            if let Some('\r') = line.chars().next_back() {
+                line.pop();
+            }
+
+            Ok(line)
+        }
+    }
+
+    /// Prompt until a non-empty line is read from `source`, lowercased.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails, or with `UnexpectedEof` if the input
+    /// runs out first. Every reader below fails the same way.
+    pub fn read_input(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<String, MinesweeperError> {
+        loop {
+            let input = source.next_line(prompt)?;
+
+            if !input.is_empty() {
+                // Might want to make a configuration parameter for case sensitivity that defaults to false
+                break Ok(input.to_lowercase());
+            }
+        }
+    }
+
+    /// As `read_input`, from before it handed back its errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    #[deprecated(note = "`read_input` now returns the error itself")]
+    pub fn try_read_input(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<String, MinesweeperError> {
+        read_input(source, prompt)
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_as_bool(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<bool, MinesweeperError> {
+        loop {
+            let input = read_input(source, prompt)?;
+
+            match input.trim().to_lowercase().as_str() {
+                "yes" | "y" | "Y" => break Ok(true),
+                "no" | "n" | "N" => break Ok(false),
+                _ => println!("Invalid input. Please enter either 'yes' or 'no'."),
+            }
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_as_int(
+        source: &mut dyn InputSource,
+        prompt: &str,
+        min: u32,
+        max: u32,
+    ) -> Result<u32, MinesweeperError> {
+        loop {
+            let input = read_input(source, prompt)?;
+
+            if let Ok(n) = input.trim().parse::<u32>() {
+                if n >= min && n <= max {
+                    break Ok(n);
+                }
+
+                println!("Number must be between {min} and {max} inclusive.");
+                continue;
+            }
+            println!("Invalid input. Please enter an integer.");
+        }
+    }
+
+    // Column letters followed by a row number, like "B12" or "AC3", as a zero based column and
+    // row. Only the syntax is checked here, not the bounds of any board.
+    #[must_use]
+    pub fn parse_column_row(input: &str) -> Option<(u32, u32)> {
+        let input = input.trim();
+        let (letters, row) = input.split_at(input.find(|c: char| !c.is_ascii_alphabetic())?);
+
+        // A is 1 and Z is 26 in each place, so AA follows on from Z
+        let column = letters.bytes().try_fold(0_u32, |column, letter| {
+            let letter = u32::from(letter.to_ascii_lowercase() - b'a') + 1;
+            column.checked_mul(26)?.checked_add(letter)
+        })?;
+
+        let row = row.trim().parse::<u32>().ok()?;
+
+        Some((column.checked_sub(1)?, row.checked_sub(1)?))
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_column_row(
+        source: &mut dyn InputSource,
+        prompt: &str,
+        min: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(u32, u32), MinesweeperError> {
+        loop {
+            let input = read_input(source, prompt)?;
+
+            let Some((column, row)) = parse_column_row(&input) else {
+                println!("Invalid input. Please enter a valid column and row.");
+                continue;
+            };
+
+            // Rows are numbered from `min`, while columns always start at A
+            if column < width && row + 1 >= min && row < height {
+                break Ok((column, row));
+            }
+            println!("Column and row must be within valid bounds.");
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_input_mode(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<InputMode, MinesweeperError> {
+        loop {
+            if let Ok(input_mode) = read_input(source, prompt)?.parse() {
+                break Ok(input_mode);
+            }
+            println!("Invalid input. Please enter a valid input mode.");
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_game_mode(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<GameMode, MinesweeperError> {
+        loop {
+            if let Ok(game_mode) = read_input(source, prompt)?.parse() {
+                break Ok(game_mode);
+            }
+            println!("Invalid input. Please select a menu option.");
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_end_choice(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<EndChoice, MinesweeperError> {
+        loop {
+            if let Ok(choice) = read_input(source, prompt)?.parse() {
+                break Ok(choice);
+            }
+            println!("Invalid input. Please select an option.");
+        }
+    }
+
+    /// Going on to the (n)ext campaign level, as `EndChoice::NewGame`, or (q)uitting. The other
+    /// end of game choices don't apply between campaign levels.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_campaign_choice(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<EndChoice, MinesweeperError> {
+        loop {
+            match read_input(source, prompt)?.trim().to_lowercase().as_str() {
+                "next" | "n" => break Ok(EndChoice::NewGame),
+                "quit" | "q" => break Ok(EndChoice::Quit),
+                _ => println!("Invalid input. Please select an option."),
+            }
+        }
+    }
+
+    /// A preset by name, or (c)ustom to be asked for the board's size and mine count.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_difficulty(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<Difficulty, MinesweeperError> {
+        loop {
+            let input = read_input(source, prompt)?;
+
+            if matches!(input.trim(), "c" | "custom") {
+                break read_custom_difficulty(source);
+            }
+            if let Ok(difficulty) = input.parse() {
+                break Ok(difficulty);
+            }
+            println!("Invalid input. Please select a difficulty.");
+        }
+    }
+
+    /// Each bound follows from the answers before it, so every board asked for can be played.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_custom_difficulty(
+        source: &mut dyn InputSource,
+    ) -> Result<Difficulty, MinesweeperError> {
+        let width = read_as_int(
+            source,
+            &format!("Board width (1-{MAX_WIDTH}): "),
+            1,
+            MAX_WIDTH,
+        )?;
+        // A board needs room for at least one mine and one safe tile
+        let min_height = if width == 1 { 2 } else { 1 };
+        let height = read_as_int(
+            source,
+            &format!("Board height ({min_height}-{MAX_HEIGHT}): "),
+            min_height,
+            MAX_HEIGHT,
+        )?;
+
+        read_mine_count(source, width, height)
+    }
+
+    // A number of mines, or a percentage of the board's tiles to fill with them
+    fn read_mine_count(
+        source: &mut dyn InputSource,
+        width: u32,
+        height: u32,
+    ) -> Result<Difficulty, MinesweeperError> {
+        let max_mines = width * height - 1;
+        let prompt = format!("Number of mines (1-{max_mines}), or a density like 15%: ");
+
+        loop {
+            let input = read_input(source, &prompt)?;
+
+            if let Some(percent) = input.trim().strip_suffix('%') {
+                match percent.trim().parse::<u32>() {
+                    Ok(percent) if (1..=MAX_DENSITY).contains(&percent) => {
+                        break Ok(Difficulty::with_density(width, height, percent));
+                    }
+                    _ => println!("Density must be between 1% and {MAX_DENSITY}% inclusive."),
+                }
+                continue;
+            }
+
+            match input.trim().parse::<u32>() {
+                Ok(mines) if (1..=max_mines).contains(&mines) => {
+                    break Ok(Difficulty::Custom {
+                        width,
+                        height,
+                        mines,
+                    });
+                }
+                Ok(_) => println!("Number must be between 1 and {max_mines} inclusive."),
+                Err(_) => println!("Invalid input. Please enter an integer or a percentage."),
+            }
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_shape(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<Shape, MinesweeperError> {
+        loop {
+            if let Ok(shape) = read_input(source, prompt)?.parse() {
+                break Ok(shape);
+            }
+            println!("Invalid input. Please select a board shape.");
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_win_condition(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<WinCondition, MinesweeperError> {
+        loop {
+            if let Ok(win_condition) = read_input(source, prompt)?.parse() {
+                break Ok(win_condition);
+            }
+            println!("Invalid input. Please select a win condition.");
+        }
+    }
+
+    /// A board seed as a number or any other text, or None for a blank line.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_seed(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<Option<u64>, MinesweeperError> {
+        let line = source.next_line(prompt)?;
+
+        Ok((!line.trim().is_empty()).then(|| engine::seed_from_text(&line)))
+    }
+
+    /// A player's name for the leaderboard, keeping its case.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn read_name(
+        source: &mut dyn InputSource,
+        prompt: &str,
+    ) -> Result<String, MinesweeperError> {
+        Ok(leaderboard::clean_name(&source.next_line(prompt)?))
+    }
+
+    /// # Errors
+    ///
+    /// Returns `MinesweeperError::Input` if reading fails or the input runs out.
+    pub fn enter_to_continue(source: &mut dyn InputSource) -> Result<(), MinesweeperError> {
+        source.next_line("Press enter to continue... ").map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::bool_assert_comparison)]
+
+    // Open entire root module to our test module
+    use super::*;
+    use std::{cell::RefCell, path::Path, time::Duration};
+
+    #[test]
+    fn gets_game_over() {
+        let state = GameState::new();
+
+        assert_eq!(state.game_over, state.get_game_over());
+    }
+
+    #[test]
+    fn gets_height() {
+        let state = GameState::new();
+
+        assert_eq!(state.board.height, state.get_height());
+    }
+
+    #[test]
+    fn gets_width() {
+        let state = GameState::new();
+
+        assert_eq!(state.board.width, state.get_width());
+    }
+
+    #[test]
+    fn gets_board() {
+        let state = GameState::from_layout("*.\n..").unwrap();
+
+        assert_eq!(state.board().width(), 2);
+        assert_eq!(state.board().height(), 2);
+        assert_eq!(state.board().tiles(), &state.board.tiles[..]);
+    }
+
+    #[test]
+    fn iterates_tiles_rows_and_neighbors() {
+        let state = GameState::from_layout("*..\n...").unwrap();
+
+        let mines: Vec<Coord> = state
+            .tiles()
+            .filter(|(_, tile)| tile.has_mine())
+            .map(|(coord, _)| coord)
+            .collect();
+        assert_eq!(vec![Coord::new(0, 0)], mines);
+        assert_eq!(
+            Some((Coord::new(1, 2), &hidden(false))),
+            state.tiles().last()
+        );
+
+        let rows: Vec<&[Tile]> = state.rows().collect();
+        assert_eq!(2, rows.len());
+        assert_eq!(&[hidden(true), hidden(false), hidden(false)], rows[0]);
+
+        let around: Vec<Coord> = state.neighbor_tiles(0).map(|(coord, _)| coord).collect();
+        assert_eq!(
+            vec![Coord::new(0, 1), Coord::new(1, 0), Coord::new(1, 1)],
+            around
+        );
+        assert_eq!(
+            1,
+            state
+                .neighbor_tiles(4)
+                .filter(|(_, tile)| tile.has_mine())
+                .count()
+        );
+        assert_eq!(0, state.neighbor_tiles(6).count());
+        assert!(state
+            .iter_tiles()
+            .zip(state.tiles())
+            .all(|((col, row, _), (coord, _))| coord == Coord::new(row, col)));
+    }
+
+    #[test]
+    fn converts_coords_and_indices() {
+        let coord = Coord::from_index(7, 3);
+
+        assert_eq!(coord, Coord::new(2, 1));
+        assert_eq!(coord.to_index(3), 7);
+    }
+
+    #[test]
+    fn gets_and_sets_board_tiles_by_coord() {
+        let mut state = GameState::from_layout("*..\n...").unwrap();
+        let board = &mut state.board;
+        let revealed = Tile::Revealed {
+            has_mine: false,
+            hint: 1,
+        };
+
+        assert_eq!(board.get(Coord::new(0, 0)), Some(&hidden(true)));
+        assert!(board.set(Coord::new(1, 2), revealed));
+        assert_eq!(board.get(Coord::new(1, 2)), Some(&revealed));
+        assert_eq!(board.index_of(Coord::new(1, 2)), Some(5));
+        assert_eq!(board.coord_of(5), Some(Coord::new(1, 2)));
+
+        // Off the board in either direction
+        assert_eq!(board.get(Coord::new(0, 3)), None);
+        assert_eq!(board.get(Coord::new(2, 0)), None);
+        assert!(!board.set(Coord::new(0, 3), revealed));
+        assert_eq!(board.coord_of(6), None);
+    }
+
+    #[test]
+    fn gets_tile() {
+        let mut state = GameState::new();
+
+        state.board.tiles.push(Tile::Hidden {
+            has_mine: false,
+            flagged: false,
+            questioned: false,
+        });
+
+        assert_eq!(&state.board.tiles[0], state.get_tile(0));
+    }
+
+    #[test]
+    fn gets_selected() {
+        let mut state = GameState::from_layout("*.").unwrap();
+
+        state.set_selected(1);
+
+        assert_eq!(Ok(1), state.get_selected());
+        assert_eq!(
+            Some(Coord::new(0, 1)),
+            state.get_selection().map(Selection::coord)
+        );
+        assert_eq!(
+            Err(MinesweeperError::NoSelection),
+            GameState::new().get_selected()
+        );
+    }
+
+    #[test]
+    fn gets_input_mode() {
+        let mut state = GameState::new();
+
+        state.input_mode = InputMode::Clear;
+
+        assert_eq!(InputMode::Clear, state.get_input_mode());
+    }
+
+    #[test]
+    fn gets_won() {
+        let mut state = GameState::new();
+
+        state.game_won = true;
+
+        assert_eq!(true, state.get_won());
+    }
+
+    #[test]
+    fn gets_mines() {
+        let mut state = GameState::new();
+
+        state.starting_mines = 6;
+
+        assert_eq!(6, state.get_mines());
+    }
+
+    #[test]
+    fn gets_game_mode() {
+        let mut state = GameState::new();
+
+        state.game_mode = GameMode::Play;
+
+        assert_eq!(GameMode::Play, state.get_game_mode());
+    }
+
+    #[test]
+    fn sets_game_over() {
+        let mut state = GameState::new();
+        state.set_game_over(true);
+
+        assert_eq!(true, state.game_over);
+    }
+
+    #[test]
+    fn sets_width() {
+        let mut state = GameState::new();
+        state.set_width(5);
+
+        assert_eq!(5, state.board.width);
+    }
+
+    #[test]
+    fn sets_height() {
+        let mut state = GameState::new();
+        state.set_height(5);
+
+        assert_eq!(5, state.board.height);
+    }
+
+    #[test]
+    fn sets_tile() {
+        let mut state = GameState::new();
+        state.board.tiles.push(Tile::Hidden {
+            has_mine: (false),
+            flagged: false,
+            questioned: false,
+        });
+        state.set_tile(
+            0,
+            Tile::Revealed {
+                has_mine: (false),
+                hint: (0),
+            },
+        );
+
+        assert_eq!(
+            state.board.tiles[0],
+            Tile::Revealed {
+                has_mine: (false),
+                hint: (0)
+            }
+        );
+    }
+
+    #[test]
+    fn sets_selected() {
+        let mut state = GameState::from_layout("*.\n..").unwrap();
+        state.set_selected(0);
+
+        assert_eq!(state.selected_tile.unwrap().index(), 0);
+
+        // Off the board, nothing is left selected for a move to trip over
+        state.set_selected(4);
+        assert_eq!(Err(MinesweeperError::NoSelection), state.get_selected());
+        state.set_input_mode(InputMode::Clear);
+        assert_eq!(
+            Err(MinesweeperError::NoSelection),
+            engine::update(&mut state)
+        );
+    }
+
+    #[test]
+    fn selects_only_tiles_on_the_board() {
+        let mut state = GameState::from_layout("*.\n..").unwrap();
+
+        let selection = state.select(Coord::new(1, 0)).unwrap();
+        assert_eq!(2, selection.index());
+
+        assert_eq!(None, state.select(Coord::new(0, 2)));
+        assert_eq!(Some(selection), state.get_selection());
+    }
+
+    #[test]
+    fn sets_input_mode() {
+        let mut state = GameState::new();
+        state.set_input_mode(InputMode::Flag);
+
+        assert_eq!(state.input_mode, InputMode::Flag);
+    }
+
+    #[test]
+    fn sets_won() {
+        let mut state = GameState::new();
+        state.set_won(true);
+
+        assert_eq!(state.game_won, true);
+    }
+
+    #[test]
+    fn sets_mines() {
+        let mut state = GameState::new();
+        state.set_mines(6);
+
+        assert_eq!(state.starting_mines, 6);
+    }
+
+    #[test]
+    fn sets_game_mode() {
+        let mut state = GameState::new();
+        state.set_game_mode(GameMode::Play);
+
+        assert_eq!(state.game_mode, GameMode::Play);
+    }
+
+    #[test]
+    fn gets_lives() {
+        let mut state = GameState::new();
+
+        state.lives = 3;
+
+        assert_eq!(3, state.get_lives());
+    }
+
+    #[test]
+    fn sets_lives() {
+        let mut state = GameState::new();
+        state.set_lives(3);
+
+        assert_eq!(state.lives, 3);
+    }
+
+    #[test]
+    fn gets_elapsed() {
+        let mut state = GameState::new();
+
+        state.elapsed = Duration::from_secs(42);
+
+        assert_eq!(Duration::from_secs(42), state.get_elapsed());
+    }
+
+    #[test]
+    fn sets_elapsed() {
+        let mut state = GameState::new();
+        state.set_elapsed(Duration::from_secs(42));
+
+        assert_eq!(state.elapsed, Duration::from_secs(42));
+    }
+
+    #[test]
+    fn gets_wrap() {
+        let mut state = GameState::new();
+
+        state.wrap = true;
+
+        assert!(state.get_wrap());
+    }
+
+    #[test]
+    fn sets_wrap() {
+        let mut state = GameState::new();
+        state.set_wrap(true);
+
+        assert!(state.wrap);
+    }
+
+    #[test]
+    fn gets_confirm_risky_clears() {
+        let mut state = GameState::new();
+
+        state.confirm_risky_clears = true;
+
+        assert!(state.get_confirm_risky_clears());
+    }
+
+    #[test]
+    fn sets_confirm_risky_clears() {
+        let mut state = GameState::new();
+        state.set_confirm_risky_clears(true);
+
+        assert!(state.confirm_risky_clears);
+    }
+
+    #[test]
+    fn gets_first_click_zero() {
+        let mut state = GameState::new();
+
+        state.first_click_zero = true;
+
+        assert!(state.get_first_click_zero());
+    }
+
+    #[test]
+    fn sets_first_click_zero() {
+        let mut state = GameState::new();
+        state.set_first_click_zero(true);
+
+        assert!(state.first_click_zero);
+    }
+
+    #[test]
+    fn gets_history_limit() {
+        let mut state = GameState::new();
+
+        state.history_limit = 5;
+
+        assert_eq!(5, state.get_history_limit());
+    }
+
+    #[test]
+    fn sets_history_limit() {
+        let mut state = GameState::new();
+        state.set_history_limit(5);
+
+        assert_eq!(state.history_limit, 5);
+    }
+
+    #[test]
+    fn gets_adjacency() {
+        let mut state = GameState::new();
+
+        state.adjacency = Adjacency::Four;
+
+        assert_eq!(Adjacency::Four, state.get_adjacency());
+    }
+
+    #[test]
+    fn sets_adjacency() {
+        let mut state = GameState::new();
+        state.set_adjacency(Adjacency::Four);
+
+        assert_eq!(Adjacency::Four, state.adjacency);
+    }
+
+    #[test]
+    fn gets_flagging_allowed() {
+        let mut state = GameState::new();
+
+        state.flagging_allowed = false;
+
+        assert!(!state.get_flagging_allowed());
+    }
+
+    #[test]
+    fn sets_flagging_allowed() {
+        let mut state = GameState::new();
+        state.set_flagging_allowed(false);
+
+        assert!(!state.flagging_allowed);
+    }
+
+    #[test]
+    fn gets_time_limit() {
+        let mut state = GameState::new();
+
+        state.time_limit = Some(Duration::from_secs(90));
+
+        assert_eq!(Some(Duration::from_secs(90)), state.get_time_limit());
+    }
+
+    #[test]
+    fn sets_time_limit() {
+        let mut state = GameState::new();
+        state.set_time_limit(Some(Duration::from_secs(90)));
+
+        assert_eq!(Some(Duration::from_secs(90)), state.time_limit);
+    }
+
+    #[test]
+    fn loses_when_time_runs_out() {
+        let mut state = GameState::new();
+        state.board_setup(2, 1, 1).unwrap();
+        state.add_tile(hidden(true));
+        state.add_tile(hidden(false));
+
+        // No limit, no countdown
+        state.set_elapsed(Duration::from_secs(1000));
+        assert!(!state.check_time_limit());
+        assert_eq!(None, state.time_remaining());
+
+        state.set_time_limit(Some(Duration::from_secs(90)));
+        state.set_elapsed(Duration::from_secs(89));
+
+        assert!(!state.check_time_limit());
+        assert_eq!(Some(Duration::from_secs(1)), state.time_remaining());
+        assert!(!state.get_game_over());
+
+        state.set_elapsed(Duration::from_secs(91));
+
+        assert!(state.check_time_limit());
+        assert!(state.get_game_over());
+        assert!(state.get_timed_out());
+        assert!(!state.reveal(1));
+    }
+
+    #[test]
+    fn gets_daily() {
+        let mut state = GameState::new();
+
+        state.daily = true;
+
+        assert!(state.get_daily());
+    }
+
+    #[test]
+    fn sets_daily() {
+        let mut state = GameState::new();
+        state.set_daily(true);
+
+        assert!(state.daily);
+    }
+
+    #[test]
+    fn gets_auto_flag() {
+        let mut state = GameState::new();
+
+        state.auto_flag = true;
+
+        assert!(state.get_auto_flag());
+    }
+
+    #[test]
+    fn sets_auto_flag() {
+        let mut state = GameState::new();
+        state.set_auto_flag(true);
+
+        assert!(state.auto_flag);
+    }
+
+    #[test]
+    fn gets_compact() {
+        let mut state = GameState::new();
+
+        state.compact = true;
+
+        assert!(state.get_compact());
+    }
+
+    #[test]
+    fn sets_compact() {
+        let mut state = GameState::new();
+        state.set_compact(true);
+
+        assert!(state.compact);
+    }
+
+    #[test]
+    fn gets_unicode_grid() {
+        let mut state = GameState::new();
+
+        state.unicode_grid = true;
+
+        assert!(state.get_unicode_grid());
+    }
+
+    #[test]
+    fn sets_unicode_grid() {
+        let mut state = GameState::new();
+        state.set_unicode_grid(true);
+
+        assert!(state.unicode_grid);
+    }
+
+    #[test]
+    fn gets_show_probabilities() {
+        let mut state = GameState::new();
+
+        state.show_probabilities = true;
+
+        assert!(state.get_show_probabilities());
+    }
+
+    #[test]
+    fn sets_show_probabilities() {
+        let mut state = GameState::new();
+        state.set_show_probabilities(true);
+
+        assert!(state.show_probabilities);
+    }
+
+    #[test]
+    fn gets_theme() {
+        let mut state = GameState::new();
+
+        state.theme = Theme::unicode();
+
+        assert_eq!(Theme::unicode(), state.get_theme());
+    }
+
+    #[test]
+    fn sets_theme() {
+        let mut state = GameState::new();
+        state.set_theme(Theme::unicode());
+
+        assert_eq!(state.theme, Theme::unicode());
+    }
+
+    #[test]
+    fn gets_seed() {
+        let mut state = GameState::new();
+
+        state.seed = Some(12345);
+
+        assert_eq!(Some(12345), state.get_seed());
+    }
+
+    #[test]
+    fn sets_seed() {
+        let mut state = GameState::new();
+        state.set_seed(Some(12345));
+
+        assert_eq!(state.seed, Some(12345));
+    }
+
+    #[test]
+    fn parses_game_mode() {
+        assert_eq!(Ok(GameMode::Play), "p".parse());
+        assert_eq!(Ok(GameMode::Config), "Configure".parse());
+        assert_eq!(Ok(GameMode::Stats), "s".parse());
+        assert_eq!(Ok(GameMode::Leaderboard), "l".parse());
+        assert_eq!(Ok(GameMode::Resume), "r".parse());
+        assert_eq!(Ok(GameMode::Menu), "menu".parse());
+        assert_eq!(Ok(GameMode::Daily), "d".parse());
+        assert_eq!(Ok(GameMode::Campaign), "a".parse());
+        assert_eq!(Ok(GameMode::Quit), " QUIT ".parse());
+        assert!("x".parse::<GameMode>().is_err());
+    }
+
+    #[test]
+    fn parses_difficulty() {
+        assert_eq!(Ok(Difficulty::Easy), "E".parse());
+        assert_eq!(Ok(Difficulty::Medium), "medium".parse());
+        assert_eq!(Ok(Difficulty::Hard), "h".parse());
+        assert_eq!(
+            Err(ParseError::new("difficulty", "extreme")),
+            "extreme".parse::<Difficulty>()
+        );
+    }
+
+    #[test]
+    fn reads_custom_difficulty() {
+        // Too wide, then too many mines for a 10x10 board
+        let mut input: &[u8] = b"c\n60\n10\n10\n100\n20\n";
+
+        let difficulty = input_handler::read_difficulty(&mut input, "Difficulty: ").unwrap();
+
+        assert_eq!(
+            Difficulty::Custom {
+                width: 10,
+                height: 10,
+                mines: 20
+            },
+            difficulty
+        );
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn custom_single_column_boards_fit_a_mine() {
+        let mut input: &[u8] = b"1\n1\n2\n1\n";
+
+        assert_eq!(
+            Difficulty::Custom {
+                width: 1,
+                height: 2,
+                mines: 1
+            },
+            input_handler::read_custom_difficulty(&mut input).unwrap()
+        );
+    }
+
+    #[test]
+    fn suggests_time_limits_that_can_be_set() {
+        assert_eq!(Duration::from_secs(50), Difficulty::Easy.time_limit());
+
+        let tiny = Difficulty::Custom {
+            width: 2,
+            height: 2,
+            mines: 1,
+        };
+        assert_eq!(
+            Duration::from_secs(MIN_TIME_LIMIT_SECS.into()),
+            tiny.time_limit()
+        );
+
+        let largest = Difficulty::Custom {
+            width: MAX_WIDTH,
+            height: MAX_HEIGHT,
+            mines: 1,
+        };
+        assert_eq!(
+            Duration::from_secs(MAX_TIME_LIMIT_SECS.into()),
+            largest.time_limit()
+        );
+    }
+
+    #[test]
+    fn computes_mines_from_density() {
+        let mines = |width, height, percent| {
+            Difficulty::with_density(width, height, percent)
+                .dimensions()
+                .2
+        };
+
+        assert_eq!(41, mines(16, 16, 16));
+        assert_eq!(96, mines(30, 16, 20));
+        // Always at least one mine and one safe tile
+        assert_eq!(1, mines(3, 3, 1));
+        assert_eq!(3, mines(2, 2, 100));
+        assert_eq!(1, mines(1, 2, 90));
+        // Oversize boards don't overflow, and are left for `validate_board` to reject
+        assert_eq!(490_000_000, mines(70_000, 70_000, 10));
+        assert_eq!(u32::MAX, mines(100_000, 100_000, 90));
+        assert!(matches!(
+            args(&["--width", "26", "--height", "100000000", "--density", "90"]),
+            Err(cli::ArgError::InvalidValue { .. })
+        ));
+
+        let mut input: &[u8] = b"10\n10\n95%\n20%\n";
+        assert_eq!(
+            Difficulty::Custom {
+                width: 10,
+                height: 10,
+                mines: 20
+            },
+            input_handler::read_custom_difficulty(&mut input).unwrap()
+        );
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn parses_win_condition() {
+        assert_eq!(Ok(WinCondition::RevealSafe), "r".parse());
+        assert_eq!(Ok(WinCondition::FlagMines), "Flag".parse());
+        assert_eq!(Ok(WinCondition::Either), "e".parse());
+        assert!("all".parse::<WinCondition>().is_err());
+    }
+
+    #[test]
+    fn parses_input_mode() {
+        assert_eq!(Ok(InputMode::Clear), "c".parse());
+        assert_eq!(Ok(InputMode::Flag), "FLAG".parse());
+        assert_eq!(Ok(InputMode::Cancel), "x".parse());
+        assert_eq!(Ok(InputMode::Safe), "s".parse());
+        assert_eq!(Ok(InputMode::Chord), "h".parse());
+        assert_eq!(Ok(InputMode::Undo), "u".parse());
+        assert_eq!(Ok(InputMode::Undo), "b".parse());
+        assert_eq!(Ok(InputMode::Redo), "Redo".parse());
+        assert_eq!(Ok(InputMode::Hint), "i".parse());
+        assert_eq!(Ok(InputMode::Hint), "Hint".parse());
+        assert_eq!(Ok(InputMode::Menu), "m".parse());
+        assert_eq!(Ok(InputMode::Menu), "Quit".parse());
+        assert!("".parse::<InputMode>().is_err());
+    }
+
+    #[test]
+    fn parses_end_choice() {
+        use input_handler::EndChoice;
+
+        assert_eq!(Ok(EndChoice::Replay), "r".parse());
+        assert_eq!(Ok(EndChoice::NewGame), "New".parse());
+        assert_eq!(Ok(EndChoice::Configure), "c".parse());
+        assert_eq!(Ok(EndChoice::Quit), " q ".parse());
+        assert!("x".parse::<EndChoice>().is_err());
+
+        // Between campaign levels there's only going on or stopping
+        let mut input: &[u8] = b"r\nc\nnext\nQ\n";
+        assert_eq!(
+            Ok(EndChoice::NewGame),
+            input_handler::read_campaign_choice(&mut input, "")
+        );
+        assert_eq!(
+            Ok(EndChoice::Quit),
+            input_handler::read_campaign_choice(&mut input, "")
+        );
+    }
+
+    fn args(list: &[&str]) -> Result<cli::Args, cli::ArgError> {
+        cli::Args::parse(list.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn parses_json_switch() {
+        let parsed = args(&["--json", "--seed", "7"]).unwrap();
+
+        assert!(parsed.json);
+        assert_eq!(Some(7), parsed.seed);
+        assert!(!args(&[]).unwrap().json);
+    }
+
+    #[test]
+    fn opening_switch_guarantees_a_zero_first_clear() {
+        for seed in 0..20 {
+            let seed = seed.to_string();
+            let parsed = args(&["--opening", "-d", "hard", "--seed", &seed]).unwrap();
+            let mut state = parsed.game_state().unwrap().unwrap();
+
+            state.reveal(70);
+
+            assert_eq!(Some(0), state.get_tile(70).hint());
+        }
+        assert!(!args(&["-s", "1"]).unwrap().opening);
+    }
+
+    #[test]
+    fn parses_lives() {
+        let parsed = args(&["--lives", "3"]).unwrap();
+        let state = parsed.game_state().unwrap().unwrap();
+
+        assert_eq!(3, state.get_lives());
+        assert_eq!(Some(Difficulty::Easy), parsed.board());
+
+        for lives in ["0", "10", "many"] {
+            assert_eq!(
+                Err(cli::ArgError::InvalidValue {
+                    flag: String::from("--lives"),
+                    value: String::from(lives),
+                }),
+                args(&["--lives", lives])
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_oversize_boards() {
+        assert_eq!(
+            Err(cli::ArgError::InvalidValue {
+                flag: String::from("--width"),
+                value: String::from("53 (at most 52)"),
+            }),
+            args(&["--width", "53", "--mines", "1"])
+        );
+        assert_eq!(
+            Err(cli::ArgError::InvalidValue {
+                flag: String::from("--height"),
+                value: String::from("200000000 (at most 99)"),
+            }),
+            args(&["--width", "26", "--height", "200000000", "--mines", "1"])
+        );
+    }
+
+    #[test]
+    fn parses_density() {
+        let custom = |width, height, mines| Difficulty::Custom {
+            width,
+            height,
+            mines,
+        };
+
+        let parsed = args(&["-w", "10", "-h", "10", "--density", "15%"]).unwrap();
+        assert_eq!(Some(custom(10, 10, 15)), parsed.board());
+
+        // Easy's size when only a density is given, and a count of mines wins out
+        assert_eq!(
+            Some(custom(5, 5, 5)),
+            args(&["--density", "20"]).unwrap().board()
+        );
+        let both = args(&["--density", "20", "--mines", "3"]).unwrap();
+        assert_eq!(Some(custom(5, 5, 3)), both.board());
+
+        for density in ["0", "91", "lots"] {
+            assert!(args(&["--density", density]).is_err());
+        }
+    }
+
+    #[test]
+    fn no_args_keeps_the_menu() {
+        assert_eq!(None, args(&[]).unwrap().game_state().unwrap().map(|_| ()));
+    }
+
+    #[test]
+    fn parses_custom_board_args() {
+        let parsed = args(&[
+            "--width", "16", "--height", "16", "--mines", "40", "--seed", "12345",
+        ])
+        .unwrap();
+        let state = parsed.game_state().unwrap().unwrap();
+
+        assert_eq!(16, state.get_width());
+        assert_eq!(16, state.get_height());
+        assert_eq!(40, state.get_mines());
+        assert_eq!(Some(12345), state.get_seed());
+    }
+
+    #[test]
+    fn parses_difficulty_shorthand() {
+        let parsed = args(&["--difficulty", "hard"]).unwrap();
+
+        assert_eq!(Some(Difficulty::Hard), parsed.board());
+    }
+
+    #[test]
+    fn rejects_too_many_mines() {
+        assert_eq!(
+            Err(cli::ArgError::InvalidBoard(BoardError::TooManyMines {
+                mines: 16,
+                tiles: 16
+            })),
+            args(&["--width", "4", "--height", "4", "--mines", "16"])
+        );
+    }
+
+    #[test]
+    fn rejects_bad_args() {
+        assert!(args(&["--width"]).is_err());
+        assert!(args(&["--width", "wide"]).is_err());
+        assert!(args(&["--colour", "blue"]).is_err());
+    }
+
+    #[test]
+    fn same_seed_places_same_mines() {
+        let first = engine::new_game(&Difficulty::Hard, Some(7)).unwrap();
+        let second = engine::new_game(&Difficulty::Hard, Some(7)).unwrap();
+
+        assert_eq!(first.board.tiles, second.board.tiles);
+    }
+
+    #[test]
+    fn builds_games_with_scattered_mines() {
+        let built = GameState::builder(12, 12)
+            .mines(35)
+            .seed(7)
+            .first_click_zero(true)
+            .build()
+            .unwrap();
+        let made = engine::new_game(&Difficulty::Hard, Some(7)).unwrap();
+
+        assert_eq!(made.board, built.board);
+        assert!(built.get_first_click_zero());
+        assert_eq!(Some(7), built.get_seed());
+    }
+
+    #[test]
+    fn builds_games_with_mines_placed_by_hand() {
+        let built = GameState::builder(3, 2)
+            .mine_positions([5, 0, 5])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            GameState::from_layout("*..\n..*").unwrap().board,
+            built.board
+        );
+        assert_eq!(2, built.get_mine_count());
+        assert_eq!(2, built.get_hint(1));
+
+        // The last of mines and positions wins
+        let built = GameState::builder(3, 2)
+            .mine_positions([0])
+            .mines(2)
+            .seed(1)
+            .build()
+            .unwrap();
+        assert_eq!(2, built.get_mines());
+    }
+
+    #[test]
+    fn builder_rejects_unplayable_boards() {
+        assert_eq!(
+            Err(BoardError::MineOffBoard { index: 6, tiles: 6 }),
+            GameState::builder(3, 2)
+                .mine_positions([1, 6])
+                .build()
+                .map(|state| state.board)
+        );
+        assert_eq!(
+            Err(BoardError::TooManyMines { mines: 4, tiles: 4 }),
+            GameState::builder(2, 2)
+                .mine_positions(0..4)
+                .build()
+                .map(|state| state.board)
+        );
+        assert!(GameState::builder(0, 2).build().is_err());
+        assert_eq!(
+            Err(BoardError::TooLarge {
+                width: 70_000,
+                height: 70_000
+            }),
+            GameState::builder(70_000, 70_000)
+                .mines(1)
+                .build()
+                .map(|state| state.board)
+        );
+        assert!(GameState::builder(70_000, 70_000)
+            .mine_positions([0])
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn same_rng_places_same_mines() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let first = engine::new_game_with_rng(&Difficulty::Hard, &mut rng).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let second = engine::new_game_with_rng(&Difficulty::Hard, &mut rng).unwrap();
+
+        assert_eq!(first.board.tiles, second.board.tiles);
+
+        // The seed it drew is enough to build the board again
+        let rebuilt = engine::new_game(&Difficulty::Hard, first.get_seed()).unwrap();
+        assert_eq!(first.board.tiles, rebuilt.board.tiles);
+    }
+
+    #[test]
+    fn displays_tiles() {
+        let hidden = Tile::Hidden {
+            has_mine: true,
+            flagged: false,
+            questioned: false,
+        };
+        let flagged = Tile::Hidden {
+            has_mine: false,
+            flagged: true,
+            questioned: false,
+        };
+        let mine = Tile::Revealed {
+            has_mine: true,
+            hint: 10,
+        };
+        let hint = Tile::Revealed {
+            has_mine: false,
+            hint: 3,
+        };
+
+        assert_eq!(" - ", hidden.to_string());
+        assert_eq!(" F ", flagged.to_string());
+        assert_eq!(" X ", mine.to_string());
+        assert_eq!(" 3 ", hint.to_string());
+    }
+
+    #[test]
+    fn displays_board() {
+        let mut state = GameState::new();
+        state.board_setup(2, 2, 1).unwrap();
+        state.add_tile(Tile::Revealed {
+            has_mine: false,
+            hint: 1,
+        });
+        state.add_tile(Tile::Hidden {
+            has_mine: true,
+            flagged: true,
+            questioned: false,
+        });
+        state.add_tile(Tile::Revealed {
+            has_mine: false,
+            hint: 1,
+        });
+        state.add_tile(Tile::Hidden {
+            has_mine: false,
+            flagged: false,
+            questioned: false,
+        });
+
+        let expected = "     A  B  \n   1 1  F \n   2 1  - \n";
+
+        assert_eq!(expected, state.to_string());
+    }
+
+    #[test]
+    fn decrements_mine_count_down_to_zero() {
+        let mut state = GameState::new();
+        state.board_setup(2, 2, 1).unwrap();
+
+        assert!(state.decrement_mine_count());
+        assert_eq!(0, state.get_mine_count());
+
+        assert!(!state.decrement_mine_count());
+        assert_eq!(0, state.get_mine_count());
+    }
+
+    #[test]
+    fn increments_mine_count_up_to_starting_mines() {
+        let mut state = GameState::new();
+        state.board_setup(2, 2, 1).unwrap();
+
+        assert!(!state.increment_mine_count());
+        assert_eq!(1, state.get_mine_count());
+
+        state.decrement_mine_count();
+        assert!(state.increment_mine_count());
+        assert_eq!(1, state.get_mine_count());
+    }
+
+    #[test]
+    fn tries_to_get_tile() {
+        let mut state = GameState::new();
+        state.board_setup(2, 1, 1).unwrap();
+        state.add_tile(hidden(true));
+        state.add_tile(revealed(1));
+
+        assert_eq!(Ok(&hidden(true)), state.try_get_tile(0));
+        assert_eq!(Ok(&revealed(1)), state.try_get_tile(1));
+        assert_eq!(
+            Err(MinesweeperError::OutOfBounds { index: 2, tiles: 2 }),
+            state.try_get_tile(2)
+        );
+        assert!(state.try_get_tile(usize::MAX).is_err());
+
+        assert_eq!(Some(&revealed(1)), state.tile_at(1));
+        assert_eq!(None, state.tile_at(2));
+        assert_eq!(None, state.tile_at(usize::MAX));
+    }
+
+    #[test]
+    fn represents_tile() {
+        let mut state = GameState::new();
+        state.board.tiles.push(Tile::Hidden {
+            has_mine: false,
+            flagged: true,
+            questioned: false,
+        });
+
+        let state = &state;
+
+        assert_eq!(" F ", state.represent_tile(0));
+        assert_eq!(" ? ", state.represent_tile(1));
+    }
+
+    #[test]
+    fn emits_events_during_a_flood_fill() {
+        let mut state = GameState::new();
+        state.board_setup(5, 1, 1).unwrap();
+        for has_mine in [true, false, false, false, false] {
+            state.add_tile(hidden(has_mine));
+        }
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&events);
+        state.set_event_callback(move |event| log.borrow_mut().push(*event));
+
+        state.reveal(4);
+
+        assert_eq!(
+            vec![
+                GameEvent::Revealed {
+                    index: 4,
+                    tile: revealed(0)
+                },
+                GameEvent::Revealed {
+                    index: 3,
+                    tile: revealed(0)
+                },
+                GameEvent::Revealed {
+                    index: 2,
+                    tile: revealed(0)
+                },
+                GameEvent::Revealed {
+                    index: 1,
+                    tile: revealed(1)
+                },
+                GameEvent::FloodFilled {
+                    index: 4,
+                    revealed: 3
+                },
+                GameEvent::Won {
+                    index: 4,
+                    tile: revealed(0)
+                },
+            ],
+            *events.borrow()
+        );
+
+        // Snapshots don't report to the original's listener
+        let mut copy = state.snapshot();
+        copy.set_game_over(false);
+        copy.set_won(false);
+        copy.toggle_flag(0);
+
+        assert_eq!(6, events.borrow().len());
+    }
+
+    #[test]
+    fn emits_events_for_flags_and_losses() {
+        let mut state = GameState::new();
+        state.board_setup(2, 1, 1).unwrap();
+        state.add_tile(hidden(true));
+        state.add_tile(hidden(false));
+        // Leave the mine under the first clear
+        state.set_first_click_safe(false);
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&events);
+        state.set_event_callback(move |event| log.borrow_mut().push(*event));
+
+        state.toggle_flag(1);
+        state.reveal(0);
+
+        let mine = Tile::Revealed {
+            has_mine: true,
+            hint: 10,
+        };
+        assert_eq!(
+            vec![
+                GameEvent::Flagged {
+                    index: 1,
+                    tile: Tile::Hidden {
+                        has_mine: false,
+                        flagged: true,
+                        questioned: false
+                    }
+                },
+                GameEvent::Revealed {
+                    index: 0,
+                    tile: mine
+                },
+                GameEvent::Exploded {
+                    index: 0,
+                    tile: mine
+                },
+                GameEvent::Lost {
+                    index: 0,
+                    tile: mine
+                },
+            ],
+            *events.borrow()
+        );
+    }
+
+    #[test]
+    fn emits_explosions_that_spare_lives_survive() {
+        let mut state = GameState::from_layout("*.\n..").unwrap();
+        state.set_lives(2);
+        state.set_first_click_safe(false);
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&events);
+        state.set_event_callback(move |event| log.borrow_mut().push(*event));
+
+        state.reveal(0);
+
+        let mine = Tile::Revealed {
+            has_mine: true,
+            hint: 10,
+        };
+        assert_eq!(
+            vec![
+                GameEvent::Revealed {
+                    index: 0,
+                    tile: mine
+                },
+                GameEvent::Exploded {
+                    index: 0,
+                    tile: mine
+                },
+            ],
+            *events.borrow()
+        );
+        assert!(!state.get_game_over());
+    }
+
+    #[test]
+    fn snapshots_are_independent() {
+        let mut state = GameState::new();
+        state.board_setup(3, 1, 1).unwrap();
+        for has_mine in [true, false, false] {
+            state.add_tile(hidden(has_mine));
+        }
+
+        let mut copy = state.snapshot();
+        copy.toggle_flag(0);
+        copy.reveal(2);
+
+        assert!(copy.get_won());
+        assert!(!state.get_won());
+        assert_eq!(0, state.get_turn_count());
+        assert!(!state.can_undo());
+        assert!(state
+            .iter_tiles()
+            .all(|(_, _, tile)| matches!(tile, Tile::Hidden { flagged: false, .. })));
+    }
+
+    #[test]
+    fn marks_wrong_flags_after_a_loss() {
+        let mut state = GameState::new();
+        state.board_setup(4, 1, 1).unwrap();
+        for has_mine in [true, false, false, false] {
+            state.add_tile(hidden(has_mine));
+        }
+        state.set_first_click_safe(false);
+
+        state.toggle_flag(3);
+        assert_eq!(" F ", state.represent_tile(3));
+
+        state.reveal(0);
+
+        assert!(state.get_game_over());
+        assert_eq!(" ✗ ", state.represent_tile(3));
+    }
+
+    #[test]
+    fn pins_the_layout_each_seed_deals() {
+        // Shared seeds have to deal the same board in every build, so this layout can't change
+        let state = engine::new_game(&Difficulty::Easy, Some(42)).unwrap();
+        let mines: Vec<usize> = state
+            .iter_tiles()
+            .enumerate()
+            .filter(|(_, (_, _, tile))| tile.has_mine())
+            .map(|(index, _)| index)
+            .collect();
+
+        assert_eq!(vec![5, 10, 18, 20], mines);
+    }
+
+    #[test]
+    fn reads_seeds_as_numbers_or_text() {
+        let mut input: &[u8] = b"\n  \n12345\nfriday fun\n";
+
+        assert_eq!(Ok(None), input_handler::read_seed(&mut input, "Seed: "));
+        assert_eq!(Ok(None), input_handler::read_seed(&mut input, "Seed: "));
+        assert_eq!(
+            Ok(Some(12345)),
+            input_handler::read_seed(&mut input, "Seed: ")
+        );
+        assert_eq!(
+            Ok(Some(engine::seed_from_text("friday fun"))),
+            input_handler::read_seed(&mut input, "Seed: ")
+        );
+
+        // The published FNV-1a test vector for "a"
+        assert_eq!(0xaf63_dc4c_8601_ec8c, engine::seed_from_text("a"));
+        assert_ne!(
+            engine::seed_from_text("friday fun"),
+            engine::seed_from_text("Friday fun")
+        );
+    }
+
+    #[test]
+    fn derives_daily_seed_from_date() {
+        assert_eq!(19_700_101, engine::daily_seed(0));
+        assert_eq!(20_000_229, engine::daily_seed(11_016));
+        assert_eq!(20_241_231, engine::daily_seed(20_088));
+        assert_eq!(20_250_101, engine::daily_seed(20_089));
+        assert_eq!(20_261_015, engine::daily_seed(20_741));
+    }
+
+    #[test]
+    fn keys_stats_by_difficulty() {
+        let custom = Difficulty::Custom {
+            width: 16,
+            height: 16,
+            mines: 40,
+        };
+
+        assert_eq!("hard", stats::key(&Difficulty::Hard));
+        assert_eq!("custom-16x16x40", stats::key(&custom));
+        assert_eq!(Difficulty::Medium, Difficulty::from_dimensions(8, 8, 14));
+        assert_eq!(custom, Difficulty::from_dimensions(16, 16, 40));
+    }
+
+    #[test]
+    fn records_best_results() {
+        let mut records = stats::Stats::default();
+
+        records.record_game(&Difficulty::Easy, true, 9, Duration::from_secs(40));
+        records.record_game(&Difficulty::Easy, false, 2, Duration::from_secs(5));
+        records.record_game(&Difficulty::Easy, true, 12, Duration::from_secs(30));
+
+        let record = records.get(&Difficulty::Easy).unwrap();
+
+        assert_eq!(3, record.played);
+        assert_eq!(2, record.won);
+        assert_eq!(Some(30), record.best_time);
+        assert_eq!(Some(9), record.fewest_turns);
+        assert_eq!(None, records.get(&Difficulty::Hard));
+    }
+
+    #[test]
+    fn tracks_win_streaks_per_difficulty() {
+        let mut records = stats::Stats::default();
+        let time = Duration::from_secs(10);
+
+        records.record_game(&Difficulty::Easy, true, 5, time);
+        records.record_game(&Difficulty::Easy, true, 5, time);
+        records.record_game(&Difficulty::Hard, false, 5, time);
+
+        let easy = records.get(&Difficulty::Easy).unwrap();
+        assert_eq!((2, 2), (easy.current_streak, easy.best_streak));
+
+        let hard = records.get(&Difficulty::Hard).unwrap();
+        assert_eq!((0, 0), (hard.current_streak, hard.best_streak));
+
+        records.record_game(&Difficulty::Easy, false, 5, time);
+        records.record_game(&Difficulty::Easy, true, 5, time);
+
+        let easy = records.get(&Difficulty::Easy).unwrap();
+        assert_eq!((1, 2), (easy.current_streak, easy.best_streak));
+    }
+
+    #[test]
+    fn reads_stats_from_before_streaks() {
+        let records = stats::Stats::parse("easy 3 2 30 9\n").unwrap();
+        let record = records.get(&Difficulty::Easy).unwrap();
+
+        assert_eq!(2, record.won);
+        assert_eq!((0, 0), (record.current_streak, record.best_streak));
+    }
+
+    #[test]
+    fn totals_stats_across_difficulties() {
+        let mut records = stats::Stats::default();
+        records.record_game(&Difficulty::Easy, true, 9, Duration::from_secs(40));
+        records.record_tiles("easy", 71, 0);
+        records.record_game(&Difficulty::Hard, false, 3, Duration::from_secs(5));
+        records.record_tiles("hard", 12, 1);
+
+        let easy = records.get(&Difficulty::Easy).unwrap();
+        assert_eq!(
+            (40, 71, 0),
+            (easy.total_time, easy.tiles_cleared, easy.mines_hit)
+        );
+
+        let totals = records.totals();
+        assert_eq!((2, 1), (totals.played, totals.won));
+        assert_eq!(Some(22.5), totals.average_time());
+        assert_eq!((83, 1), (totals.tiles_cleared, totals.mines_hit));
+        assert_eq!(None, stats::Record::default().average_time());
+
+        let text = records.serialize();
+        assert_eq!(Some(records), stats::Stats::parse(&text));
+
+        // Files from before totals were kept start them at zero
+        let old = stats::Stats::parse("easy 3 2 30 9 1 2 400\n").unwrap();
+        let record = old.get(&Difficulty::Easy).unwrap();
+        assert_eq!((Some(400), 0), (record.best_score, record.tiles_cleared));
+        assert_eq!(None, stats::Stats::parse("easy 3 2 30 9 1 2 400 10"));
+    }
+
+    #[test]
+    fn round_trips_stats() {
+        let mut records = stats::Stats::default();
+        records.record_game(&Difficulty::Hard, false, 4, Duration::from_secs(12));
+        records.record_game(
+            &Difficulty::Custom {
+                width: 9,
+                height: 9,
+                mines: 10,
+            },
+            true,
+            20,
+            Duration::from_secs(61),
+        );
+
+        let text = records.serialize();
+
+        assert_eq!(Some(records), stats::Stats::parse(&text));
+    }
+
+    #[test]
+    fn starts_fresh_on_corrupt_stats() {
+        assert_eq!(None, stats::Stats::parse("easy 3 two - -"));
+        assert_eq!(
+            stats::Stats::default(),
+            stats::Stats::load(Path::new("/nonexistent/minesweeper/stats.txt"))
+        );
+    }
+
+    #[test]
+    fn round_trips_ascii() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(99)).unwrap();
+        state.set_tile(
+            0,
+            Tile::Revealed {
+                has_mine: false,
+                hint: 2,
+            },
+        );
+        state.set_tile(
+            1,
+            Tile::Hidden {
+                has_mine: false,
+                flagged: true,
+                questioned: false,
+            },
+        );
+
+        let ascii = state.to_ascii();
+        let copy = GameState::from_ascii(&ascii).unwrap();
+
+        assert!(ascii.starts_with("8x8 14 99\n"));
+        assert_eq!(state.board.tiles, copy.board.tiles);
+        assert_eq!(state.get_seed(), copy.get_seed());
+        assert_eq!(13, copy.get_mine_count());
+    }
+
+    #[test]
+    fn rejects_malformed_ascii() {
+        assert!(GameState::from_ascii("").is_err());
+        assert!(GameState::from_ascii("2x2 1 -\n.*\n.").is_err());
+        assert!(GameState::from_ascii("2x2 1 -\n.*\n.?").is_err());
+        assert!(GameState::from_ascii("2x2 2 -\n.*\n..").is_err());
+        assert!(GameState::from_ascii("2x2 1 -\n.*\n..").is_ok());
+        // Oversize headers are turned down before any tiles are counted
+        assert_eq!(
+            Some(ParseError::new("board header", "70000x70000 1 -")),
+            GameState::from_ascii("70000x70000 1 -").err()
+        );
+        assert!(GameState::from_ascii("4294967295x2 1 -").is_err());
+    }
+
+    #[test]
+    fn cycles_flags_through_question_marks() {
+        let mut state = GameState::from_layout("*.*").unwrap();
+        state.set_question_marks(true);
+        state.set_win_condition(WinCondition::FlagMines);
+
+        assert!(state.toggle_flag(0));
+        assert_eq!(1, state.get_mine_count());
+
+        assert!(state.toggle_flag(0));
+        assert_eq!(" ? ", state.represent_tile(0));
+        assert_eq!(" ? ", state.get_tile(0).to_string());
+        // A question mark holds no flag, so it neither counts towards a win nor uses one up
+        assert_eq!(2, state.get_mine_count());
+        assert!(state.toggle_flag(2));
+        assert!(!state.get_won());
+
+        let loaded = GameState::from_save(&state.to_save()).unwrap();
+        assert_eq!(state.board, loaded.board);
+
+        assert!(state.toggle_flag(0));
+        assert_eq!(hidden(true), *state.get_tile(0));
+        assert_eq!(4, state.get_flag_operations());
+        assert!(state.toggle_flag(0));
+        assert!(state.get_won());
+    }
+
+    #[test]
+    fn replays_question_marks() {
+        let mut state = engine::new_game(&Difficulty::Easy, Some(1)).unwrap();
+        state.set_question_marks(true);
+        let safe = (0..state.board.tiles.len())
+            .find(|&index| !state.get_tile(index).has_mine())
+            .unwrap();
+
+        // Flag, question, clear, then flag again
+        for _ in 0..4 {
+            state.toggle_flag(safe);
+        }
+        assert!(matches!(
+            state.get_tile(safe),
+            Tile::Hidden { flagged: true, .. }
+        ));
+
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert!(replay.question_marks);
+
+        let replayed = replay.run().unwrap();
+        assert_eq!(state.board.tiles, replayed.board.tiles);
+        assert_eq!(state.get_mine_count(), replayed.get_mine_count());
+    }
+
+    #[test]
+    fn question_marks_are_off_by_default() {
+        let mut state = GameState::from_layout("*..").unwrap();
+
+        state.toggle_flag(0);
+        state.toggle_flag(0);
+
+        assert_eq!(hidden(true), *state.get_tile(0));
+    }
+
+    #[test]
+    fn checks_moves_against_the_rules() {
+        let mut state = GameState::from_layout("*.\n..").unwrap();
+        let corner = Coord::new(1, 1);
+
+        assert_eq!(
+            Err(MoveError::NothingToUndo),
+            state.check_move(Action::Undo)
+        );
+        assert_eq!(
+            Err(MoveError::OffBoard),
+            state.check_move(Action::Reveal(Coord::new(2, 0)))
+        );
+
+        assert!(state.toggle_flag(1));
+        assert_eq!(
+            Err(MoveError::NoFlagsLeft),
+            state.check_move(Action::Flag(corner))
+        );
+        // Taking a flag back doesn't need one spare
+        assert_eq!(Ok(()), state.check_move(Action::Flag(Coord::new(0, 1))));
+
+        state.reveal(3);
+        assert_eq!(
+            Err(MoveError::NotHidden),
+            state.check_move(Action::Reveal(corner))
+        );
+        // The flag is in the wrong place, which only a chord would trust
+        assert_eq!(Ok(()), state.check_move(Action::Chord(corner)));
+        assert_eq!(
+            Err(MoveError::NotChordable),
+            state.check_move(Action::SafeReveal(corner))
+        );
+
+        state.set_flagging_allowed(false);
+        assert_eq!(
+            Err(MoveError::FlaggingOff),
+            state.check_move(Action::Flag(Coord::new(0, 0)))
+        );
+
+        state.reveal(0);
+        assert_eq!(
+            Err(MoveError::GameOver),
+            state.check_move(Action::Reveal(Coord::new(0, 1)))
+        );
+    }
+
+    fn hidden(has_mine: bool) -> Tile {
+        Tile::Hidden {
+            has_mine,
+            flagged: false,
+            questioned: false,
+        }
+    }
+
+    fn revealed(hint: u32) -> Tile {
+        Tile::Revealed {
+            has_mine: false,
+            hint,
+        }
+    }
+
+    #[test]
+    fn estimates_mine_probabilities() {
+        // A lone 1 with two hidden neighbors splits the odds evenly,
+        // while the rest of the board falls back to the overall density
+        let mut state = GameState::new();
+        state.board_setup(4, 2, 2).unwrap();
+        for tile in [
+            revealed(0),
+            revealed(1),
+            hidden(false),
+            hidden(true),
+            revealed(0),
+            revealed(1),
+            hidden(true),
+            hidden(false),
+        ] {
+            state.add_tile(tile);
+        }
+
+        let probabilities = state.mine_probabilities();
+
+        assert_eq!(None, probabilities[0]);
+        assert_eq!(Some(0.5), probabilities[2]);
+        assert_eq!(Some(0.5), probabilities[6]);
+        assert_eq!(Some(0.5), probabilities[3]);
+    }
+
+    #[test]
+    fn solves_forced_probabilities() {
+        // The 1 in the corner can only be satisfied by its single hidden neighbor,
+        // leaving the far tile with nothing but the overall density to go on
+        let mut state = GameState::new();
+        state.board_setup(3, 1, 1).unwrap();
+        for tile in [revealed(1), hidden(true), hidden(false)] {
+            state.add_tile(tile);
+        }
+
+        let probabilities = state.mine_probabilities();
+
+        assert_eq!(Some(1.0), probabilities[1]);
+        assert_eq!(Some(0.5), probabilities[2]);
+    }
+
+    #[test]
+    fn shows_probabilities_when_enabled() {
+        let mut state = GameState::new();
+        state.board_setup(3, 1, 1).unwrap();
+        for tile in [revealed(1), hidden(true), hidden(false)] {
+            state.add_tile(tile);
+        }
+
+        assert_eq!("     A  B  C  \n   1 1  -  - \n", state.to_string());
+
+        state.set_show_probabilities(true);
+
+        assert_eq!("     A  B  C  \n   1 1  ! 50%\n", state.to_string());
+    }
+
+    #[test]
+    fn replays_recorded_game() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(2024)).unwrap();
+
+        for index in [0, 63, 7, 56, 27, 36, 9, 18] {
+            if !state.reveal(index) {
+                state.toggle_flag(index);
+            }
+        }
+        state.toggle_flag(5);
+
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        let replayed = replay.run().unwrap();
+
+        assert_eq!(Some(2024), state.get_seed());
+        assert_eq!(state.get_moves(), replayed.get_moves());
+        assert_eq!(state.board.tiles, replayed.board.tiles);
+        assert_eq!(state.get_won(), replayed.get_won());
+        assert_eq!(state.get_game_over(), replayed.get_game_over());
+    }
+
+    #[test]
+    fn replays_mines_hit_on_spare_lives() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(2024)).unwrap();
+        state.set_lives(3);
+        state.reveal(0);
+
+        let mines: Vec<usize> = (0..state.board.tiles.len())
+            .filter(|&index| matches!(state.get_tile(index), Tile::Hidden { has_mine: true, .. }))
+            .take(2)
+            .collect();
+        for &index in &mines {
+            state.reveal(index);
+        }
+        assert_eq!((2, 1), (state.mines_hit(), state.get_lives()));
+        assert!(!state.get_game_over());
+
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert_eq!(3, replay.lives);
+
+        let replayed = replay.run().unwrap();
+        assert_eq!(state.board.tiles, replayed.board.tiles);
+        assert_eq!(state.get_lives(), replayed.get_lives());
+        assert_eq!(state.get_game_over(), replayed.get_game_over());
+    }
+
+    #[test]
+    fn records_moves() {
+        let mut state = engine::new_game(&Difficulty::Easy, Some(1)).unwrap();
+        let safe = (0..25)
+            .find(|&index| {
+                matches!(
+                    state.get_tile(index),
+                    Tile::Hidden {
+                        has_mine: false,
+                        ..
+                    }
+                )
+            })
+            .unwrap();
+
+        state.toggle_flag(safe);
+        state.toggle_flag(safe);
+        state.reveal(safe);
+
+        assert_eq!(
+            &[
+                (safe, InputMode::Flag),
+                (safe, InputMode::Flag),
+                (safe, InputMode::Clear)
+            ],
+            state.get_moves()
+        );
+        assert!(!state.reveal(safe));
+    }
+
+    #[test]
+    fn rejects_malformed_replays() {
+        assert!(replay::Replay::parse("5x5 4 - 1\nclear 3").is_err());
+        assert!(replay::Replay::parse("5x5 4 9 1\nprod 3").is_err());
+        assert!(replay::Replay::parse("5x5 4 9 1\nclear three").is_err());
+        assert!(replay::Replay::parse("5x5 4 9 1\nclear 3\nflag 4").is_ok());
+        assert!(replay::Replay::parse("5x5 4 9 1 closing\nclear 3").is_err());
+        assert!(replay::Replay::parse("5x5 4 9 1\nclear 3 soon").is_err());
+        assert!(
+            !replay::Replay::parse("5x5 4 9 1 risky\nclear 3")
+                .unwrap()
+                .first_click_safe
+        );
+        assert!(replay::Replay::parse("5x5 4 9 1\nclear 3 1500\nflag 4 2250").is_ok());
+    }
+
+    #[test]
+    fn replays_keep_move_times() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(2024)).unwrap();
+        state.set_elapsed(Duration::from_millis(1500));
+        state.toggle_flag(0);
+        state.set_elapsed(Duration::from_millis(4250));
+        state.toggle_flag(0);
+
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert_eq!(
+            vec![
+                Some(Duration::from_millis(1500)),
+                Some(Duration::from_millis(4250))
+            ],
+            replay.times
+        );
+        assert_eq!(
+            state.get_move_times(),
+            replay.run().unwrap().get_move_times()
+        );
+
+        // A time attack game lost on the clock is lost in its replay too
+        state.set_time_limit(Some(Duration::from_secs(30)));
+        state.set_elapsed(Duration::from_secs(31));
+        assert!(state.check_time_limit());
+
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert_eq!(Some(Duration::from_secs(30)), replay.time_limit);
+        assert_eq!(Some(Duration::from_secs(31)), replay.elapsed);
+        let replayed = replay.run().unwrap();
+        assert!(replayed.get_timed_out());
+        assert!(replayed.get_game_over());
+
+        // Logs from before times were recorded still play
+        let untimed = replay::Replay::parse("8x8 10 2024 1\nflag 0").unwrap();
+        assert_eq!(vec![None], untimed.times);
+    }
+
+    #[test]
+    fn replays_games_with_an_opening_first_clear() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(77)).unwrap();
+        state.set_first_click_zero(true);
+        state.reveal(27);
+        state.toggle_flag(0);
+
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert!(replay.first_click_zero);
+        assert_eq!(state.board.tiles, replay.run().unwrap().board.tiles);
+    }
+
+    #[test]
+    fn replays_no_guess_games() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(12)).unwrap();
+        state.set_no_guess(true);
+        state.reveal(20);
+
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert!(replay.no_guess);
+        assert_eq!(state.board.tiles, replay.run().unwrap().board.tiles);
+    }
+
+    #[test]
+    fn replays_flag_wins() {
+        let mut state = engine::new_game(&Difficulty::Easy, Some(1)).unwrap();
+        state.set_win_condition(WinCondition::FlagMines);
+        state.reveal(0);
+        for index in 0..state.board.tiles.len() {
+            if !state.get_tile(index).has_mine() {
+                state.reveal(index);
+            }
+        }
+        // Every safe tile is clear, but no mine is flagged
+        assert!(!state.get_won());
+
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert_eq!(WinCondition::FlagMines, replay.win_condition);
+        assert!(!replay.run().unwrap().get_won());
+
+        state.set_win_condition(WinCondition::Either);
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert_eq!(WinCondition::Either, replay.win_condition);
+        assert_eq!(
+            WinCondition::RevealSafe,
+            replay::Replay::parse("5x5 3 1 1").unwrap().win_condition
+        );
+    }
+
+    #[test]
+    fn measures_display_width() {
+        assert_eq!(3, display_width(" X "));
+        assert_eq!(2, display_width("🚩"));
+        assert_eq!(1, display_width("▢"));
+        assert_eq!(2, display_width("🚩\u{fe0f}"));
+    }
+
+    #[test]
+    fn draws_with_theme() {
+        let mut state = GameState::new();
+        state.board_setup(4, 1, 1).unwrap();
+        for tile in [
+            revealed(0),
+            Tile::Hidden {
+                has_mine: true,
+                flagged: true,
+                questioned: false,
+            },
+            hidden(false),
+            Tile::Revealed {
+                has_mine: true,
+                hint: 10,
+            },
+        ] {
+            state.add_tile(tile);
+        }
+
+        assert_eq!("     A  B  C  D  \n   1 0  F  -  X \n", state.to_string());
+
+        state.set_theme(Theme::unicode());
+
+        assert_eq!(" 🚩", state.represent_tile(1));
+        assert_eq!("     A  B  C  D  \n   1    🚩 ▢  💣\n", state.to_string());
+    }
+
+    #[test]
+    fn validates_board_setup() {
+        let mut state = GameState::new();
+
+        assert_eq!(
+            Err(BoardError::ZeroDimension {
+                width: 0,
+                height: 5
+            }),
+            state.board_setup(0, 5, 1)
+        );
+        assert_eq!(
+            Err(BoardError::TooManyMines {
+                mines: 25,
+                tiles: 25
+            }),
+            state.board_setup(5, 5, 25)
+        );
+        for (width, height) in [(53, 5), (5, 100), (70_000, 70_000)] {
+            assert_eq!(
+                Err(BoardError::TooLarge { width, height }),
+                state.board_setup(width, height, 1)
+            );
+        }
+        assert_eq!(0, state.get_width());
+        assert_eq!(Ok(()), state.board_setup(5, 5, 24));
+        assert!(engine::new_game(
+            &Difficulty::Custom {
+                width: 3,
+                height: 3,
+                mines: 9
+            },
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn summarizes_results_as_json() {
+        let mut state = GameState::new();
+        state.board_setup(8, 8, 10).unwrap();
+        state.set_won(true);
+        state.turn_count = 12;
+        state.set_elapsed(Duration::from_millis(34_900));
+
+        assert_eq!(
+            "{\"won\":true,\"turn_count\":12,\"hints_used\":0,\"elapsed_secs\":34,\"width\":8,\"height\":8,\"starting_mines\":10,\"seed\":null}",
+            state.result_summary().to_json()
+        );
+
+        state.set_seed(Some(42));
+
+        assert!(state.result_summary().to_json().ends_with("\"seed\":42}"));
+    }
+
+    #[test]
+    fn iterates_tiles_with_coordinates() {
+        let mut state = GameState::new();
+        state.board_setup(3, 2, 1).unwrap();
+        for index in 0..6 {
+            state.add_tile(hidden(index == 4));
+        }
+
+        let coordinates: Vec<(u32, u32)> = state
+            .iter_tiles()
+            .map(|(column, row, _)| (column, row))
+            .collect();
+
+        assert_eq!(
+            vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)],
+            coordinates
+        );
+
+        for (column, row, tile) in state.iter_tiles_mut() {
+            if column == 0 && row == 1 {
+                *tile = revealed(1);
+            }
+        }
+
+        assert_eq!(&revealed(1), state.get_tile(3));
+    }
+
+    #[test]
+    fn revealed_mines_have_no_hint() {
+        let mine = Tile::Revealed {
+            has_mine: true,
+            hint: 10,
+        };
+
+        assert_eq!(None, mine.hint());
+        assert_eq!(None, hidden(false).hint());
+        assert_eq!(Some(3), revealed(3).hint());
+        assert_eq!(" X ", mine.to_string());
+    }
+
+    #[test]
+    fn renders_part_of_the_board() {
+        let mut state = GameState::new();
+        state.board_setup(3, 3, 1).unwrap();
+        for hint in 0..9 {
+            state.add_tile(revealed(hint % 3));
+        }
+
+        assert_eq!(
+            "     B  C  \n   2 1  2 \n   3 1  2 \n",
+            state.render_window(1..3, 1..3, GridStyle::Plain)
+        );
+    }
+
+    #[test]
+    fn renders_compact_grid() {
+        let mut state = GameState::new();
+        state.board_setup(3, 2, 1).unwrap();
+        for tile in [
+            revealed(1),
+            hidden(true),
+            hidden(false),
+            revealed(0),
+            revealed(1),
+            Tile::Hidden {
+                has_mine: false,
+                flagged: true,
+                questioned: false,
+            },
+        ] {
+            state.add_tile(tile);
+        }
+
+        assert_eq!(
+            "     A  B  C  \n   1 1  -  - \n   2 0  1  F \n",
+            state.to_string()
+        );
+
+        state.set_compact(true);
+
+        assert_eq!("    ABC\n  1 1··\n  2  1⚑\n", state.to_string());
+
+        // Columns past Z stack their letters, each still over its own tile
+        let mut wide = GameState::new();
+        wide.board_setup(28, 1, 1).unwrap();
+        for index in 0..28 {
+            wide.add_tile(hidden(index == 27));
+        }
+        wide.set_compact(true);
+
+        assert_eq!(
+            "      AA\n    YZAB\n  1 ····\n",
+            wide.render_window(24..28, 0..1, GridStyle::Compact)
+        );
+        let lines: Vec<String> = wide.to_string().lines().map(String::from).collect();
+        assert_eq!(3, lines.len());
+        assert_eq!(format!("    {}AA", " ".repeat(26)), lines[0]);
+        assert_eq!(
+            format!("    {}AB", ('A'..='Z').collect::<String>()),
+            lines[1]
+        );
+        assert_eq!((32, 3), grid_size(28, 1, GridStyle::Compact));
+        assert_eq!((30, 11), grid_size(26, 10, GridStyle::Compact));
+    }
 
-            println!();
+    #[test]
+    fn renders_boxed_grid() {
+        let mut state = GameState::new();
+        state.board_setup(2, 2, 1).unwrap();
+        for tile in [revealed(1), hidden(true), revealed(0), revealed(1)] {
+            state.add_tile(tile);
         }
 
-        if state.get_game_over() {
-            // Consider adding end of game stats
-            println!("Game over!");
+        let expected = [
+            "       A   B ",
+            "     ┌───┬───┐",
+            "   1 │ 1 │ - │",
+            "     ├───┼───┤",
+            "   2 │ 0 │ 1 │",
+            "     └───┴───┘",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(expected, state.render_window(0..2, 0..2, GridStyle::Boxed));
+
+        // Wide symbols keep every border lined up
+        state.set_theme(Theme::unicode());
+        state.set_unicode_grid(true);
+
+        for line in state.to_string().lines().skip(1) {
+            assert_eq!(14, display_width(line), "{line}");
         }
 
-        if state.get_won() {
-            println!("Congratulations, you found all of the mines!");
+        // Two letter labels fit their cells past Z, in the boxed grid and the plain one
+        let mut wide = GameState::new();
+        wide.board_setup(28, 1, 1).unwrap();
+        for index in 0..28 {
+            wide.add_tile(hidden(index == 27));
         }
+
+        let expected = [
+            "       Z   AA  AB",
+            "     ┌───┬───┬───┐",
+            "   1 │ - │ - │ - │",
+            "     └───┴───┴───┘",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(expected, wide.render_window(25..28, 0..1, GridStyle::Boxed));
+        assert_eq!(
+            "     Z  AA AB \n   1 -  -  - \n",
+            wide.render_window(25..28, 0..1, GridStyle::Plain)
+        );
     }
-}
 
-// Create a new module to handle input to the program
-pub mod input_handler {
+    #[test]
+    fn scores_3bv_time_and_mistakes() {
+        // Two openings, so a 3BV of 2
+        let mut state = GameState::from_layout("..*..").unwrap();
+        state.reveal(0);
+        state.set_elapsed(Duration::from_secs(10));
+
+        // One opening done, less 20 for the time
+        assert_eq!(80, state.score());
+
+        // A flag on a safe tile is a mistake
+        state.toggle_flag(4);
+        assert_eq!(1, scoring::mistakes(&state));
+        assert_eq!(0, state.score());
+
+        let lenient = scoring::ScoreFormula {
+            per_mistake: 10,
+            ..scoring::ScoreFormula::default()
+        };
+        assert_eq!(70, lenient.score(&state));
 
-    use super::{Difficulty, GameMode};
-    use std::io::{self, Write};
+        state.toggle_flag(4);
+        state.reveal(4);
+        assert!(state.get_won());
+        assert_eq!(200 + 500 - 20, state.score());
+    }
 
-    #[derive(PartialEq, Debug, Clone, Copy)]
-    pub enum InputMode {
-        Clear,
-        Flag,
-        Undo,
+    #[test]
+    fn reads_score_formulas() {
+        let formula = scoring::ScoreFormula::parse("per_3bv 50\n\nwin_bonus  0\n").unwrap();
+
+        assert_eq!(50, formula.per_3bv);
+        assert_eq!(0, formula.win_bonus);
+        assert_eq!(
+            scoring::ScoreFormula::default().per_second,
+            formula.per_second
+        );
+
+        assert_eq!(None, scoring::ScoreFormula::parse("per_turn 5"));
+        assert_eq!(None, scoring::ScoreFormula::parse("per_3bv lots"));
+        assert_eq!(
+            scoring::ScoreFormula::default(),
+            scoring::ScoreFormula::load(Path::new("/nonexistent/minesweeper/scoring.txt"))
+        );
     }
 
-    #[must_use]
-    #[allow(clippy::missing_panics_doc)] // This function is unlikely to panic under normal circumstances
-    pub fn read_input(prompt: &str) -> String {
-        let mut input = String::new();
+    #[test]
+    fn keeps_best_scores() {
+        let mut records = stats::Stats::default();
+        records.record_game(&Difficulty::Easy, true, 5, Duration::from_secs(10));
 
-        loop {
-            print!("{prompt}");
+        records.record_score("easy", 300);
+        records.record_score("easy", 120);
+
+        let record = records.get(&Difficulty::Easy).unwrap();
+        assert_eq!(Some(300), record.best_score);
+        let text = records.serialize();
+        assert_eq!(Some(records), stats::Stats::parse(&text));
+    }
 
-            // # Panics
-            //
-            // This function will panic if flushing the stdout buffer fails.
-            // However, this is unlikely to happen under normal circumstances.
-            io::stdout().flush().unwrap();
+    #[test]
+    fn scores_never_go_negative() {
+        let mut state = GameState::new();
+        state.board_setup(5, 5, 4).unwrap();
+        state.set_elapsed(Duration::from_secs(599));
 
-            // # Panics
-            //
-            // This function will panic if reading from stdin fails.
-            // This could happen if there's an issue with the input stream,
-            // or if the process does not have access to the standard input.
-            io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read line");
+        assert_eq!(0, state.score());
+    }
 
-            if let Some('\n') = input.chars().next_back() {
-                input.pop();
-            }
+    #[test]
+    fn loads_puzzle_layouts() {
+        let state = GameState::from_layout("*..\n.*.\n\n").unwrap();
+
+        assert_eq!(3, state.get_width());
+        assert_eq!(2, state.get_height());
+        assert_eq!(2, state.get_mine_count());
+        assert_eq!(&hidden(true), state.get_tile(4));
+        assert_eq!(2, state.get_hint(1));
+        assert!(state
+            .iter_tiles()
+            .all(|(_, _, tile)| matches!(tile, Tile::Hidden { flagged: false, .. })));
+    }
 
-            if let Some('\r') = input.chars().next_back() {
-                input.pop();
-            }
+    #[test]
+    fn rejects_malformed_layouts() {
+        assert_eq!(
+            Err(ParseError::new("layout row", "..")),
+            GameState::from_layout("*..\n..").map(|_| ())
+        );
+        assert_eq!(
+            Err(ParseError::new("layout tile", "x")),
+            GameState::from_layout("*x.").map(|_| ())
+        );
+        assert!(GameState::from_layout("").is_err());
+        assert!(GameState::from_layout("**\n**").is_err());
 
-            if input.is_empty() {
-                continue;
-            }
+        // Too wide to label every column
+        let widest = ".".repeat(MAX_WIDTH as usize);
+        assert!(GameState::from_layout(&format!("*{widest}")).is_err());
+        assert!(GameState::from_layout(&format!("*{}", &widest[1..])).is_ok());
+    }
 
-            break;
+    #[test]
+    fn parses_coordinates() {
+        assert_eq!(Some((0, 0)), input_handler::parse_column_row("a1"));
+        assert_eq!(Some((2, 11)), input_handler::parse_column_row(" C12\n"));
+        assert_eq!(Some((26, 0)), input_handler::parse_column_row("AA1"));
+        assert_eq!(Some((29, 1)), input_handler::parse_column_row("ad 2"));
+        assert_eq!(Some((701, 0)), input_handler::parse_column_row("ZZ1"));
+        for column in [0, 25, 26, 51, 701, 702] {
+            let label = format!("{}7", column_label(column));
+            assert_eq!(Some((column, 6)), input_handler::parse_column_row(&label));
         }
-        // Might want to make a configuration parameter for case sensitivity that defaults to false
-        input.to_lowercase()
-    }
 
-    #[must_use]
-    pub fn read_as_bool(prompt: &str) -> bool {
-        let input = loop {
-            let input = read_input(prompt);
+        assert_eq!(None, input_handler::parse_column_row(""));
+        assert_eq!(None, input_handler::parse_column_row("a"));
+        assert_eq!(None, input_handler::parse_column_row("a0"));
+        assert_eq!(None, input_handler::parse_column_row("12"));
+        assert_eq!(None, input_handler::parse_column_row("éa1"));
+        assert_eq!(None, input_handler::parse_column_row("zzzzzzzzz1"));
+    }
 
-            match input.trim().to_lowercase().as_str() {
-                "yes" | "y" | "Y" => break true,
-                "no" | "n" | "N" => break false,
-                _ => println!("Invalid input. Please enter either 'yes' or 'no'."),
-            }
-        };
+    #[test]
+    fn reads_input_until_it_runs_out() {
+        let mut input: &[u8] = b"\r\n\nYes\r\n";
 
-        input
+        assert_eq!(
+            Ok(String::from("yes")),
+            input_handler::read_input(&mut input, "")
+        );
+        assert_eq!(
+            Err(MinesweeperError::Input(io::ErrorKind::UnexpectedEof)),
+            input_handler::read_input(&mut input, "")
+        );
     }
 
-    #[must_use]
-    pub fn read_as_int(prompt: &str, min: u32, max: u32) -> u32 {
-        let input = loop {
-            let input = read_input(prompt);
+    #[test]
+    fn loads_layout_files() {
+        let path =
+            std::env::temp_dir().join(format!("minesweeper-layout-{}.txt", std::process::id()));
+        fs::write(&path, ".*.\n...\n").unwrap();
 
-            if let Ok(n) = input.trim().parse::<u32>() {
-                if n >= min && n <= max {
-                    break n;
-                }
+        let state = GameState::from_layout_file(&path).unwrap();
+        assert_eq!(1, state.get_mines());
 
-                println!("Number must be between {min} and {max} inclusive.");
-                continue;
-            }
-            println!("Invalid input. Please enter an integer.");
-        };
-        input
+        fs::write(&path, ".*.\n..\n").unwrap();
+        let error = GameState::from_layout_file(&path).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+        fs::remove_file(&path).unwrap();
+        assert!(GameState::from_layout_file(&path).is_err());
     }
 
-    #[must_use]
-    pub fn read_column_row(prompt: &str, min: u32, width: u32, height: u32) -> (u32, u32) {
-        let (column, row) = loop {
-            let input = read_input(prompt);
-            let mut chars = input.chars();
+    #[test]
+    fn saves_and_loads_games_midway() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(5)).unwrap();
+        state.set_lives(3);
+        state.set_adjacency(Adjacency::Four);
+        state.set_win_condition(WinCondition::Either);
+        state.set_time_limit(Some(Duration::from_secs(90)));
+        state.set_elapsed(Duration::from_millis(12_345));
+        let safe = state
+            .iter_tiles()
+            .position(|(_, _, tile)| !tile.has_mine())
+            .unwrap();
+        let mine = state
+            .iter_tiles()
+            .position(|(_, _, tile)| tile.has_mine())
+            .unwrap();
+        state.toggle_flag(safe + 1);
+        state.reveal(safe);
+        state.reveal(mine);
+
+        let loaded = GameState::from_save(&state.to_save()).unwrap();
+
+        assert_eq!(state.board, loaded.board);
+        assert_eq!(state.get_moves(), loaded.get_moves());
+        assert_eq!(state.get_mine_count(), loaded.get_mine_count());
+        assert_eq!(state.get_turn_count(), loaded.get_turn_count());
+        assert_eq!(state.get_flag_operations(), loaded.get_flag_operations());
+        assert_eq!(2, loaded.get_lives());
+        assert_eq!(Some(5), loaded.get_seed());
+        assert_eq!(Duration::from_millis(12_345), loaded.get_elapsed());
+        assert_eq!(Some(Duration::from_secs(90)), loaded.get_time_limit());
+        assert_eq!(Adjacency::Four, loaded.get_adjacency());
+        assert_eq!(WinCondition::Either, loaded.get_win_condition());
+        assert_eq!(state.to_save(), loaded.to_save());
+    }
 
-            let column_char = chars.next();
-            let column_number = if let Some(c) = column_char {
-                (c.to_ascii_lowercase() as u32) - ('a' as u32)
-            } else {
-                println!("Invalid input. Please enter a valid column and row.");
-                continue;
-            };
+    #[test]
+    fn saves_hints_used() {
+        let mut state = GameState::from_layout("*..\n...").unwrap();
+        state.hints_used = 2;
+
+        let save = state.to_save();
+        assert!(save.contains("turns 0 0 2 0 0\n"));
+        assert_eq!(2, GameState::from_save(&save).unwrap().get_hints_used());
+
+        // Saves from before hints, clicks, or undos were counted still load
+        let old = save.replace("turns 0 0 2 0 0", "turns 0 0");
+        assert_eq!(0, GameState::from_save(&old).unwrap().get_hints_used());
+        let old = save.replace("turns 0 0 2 0 0", "turns 0 0 2");
+        assert_eq!(2, GameState::from_save(&old).unwrap().get_hints_used());
+        let bad = save.replace("turns 0 0 2 0 0", "turns 0 0 2 0 0 2");
+        assert!(GameState::from_save(&bad).is_err());
+    }
 
-            let row_number = chars.as_str().parse::<u32>();
-            match row_number {
-                Ok(n) => {
-                    if column_number >= min - 1 && column_number < width && n >= min && n <= height
-                    {
-                        break (column_number, n - 1);
-                    }
-                    println!("Column and row must be within valid bounds.");
-                }
-                Err(_) => println!("Invalid input. Please enter a valid column and row."),
-            }
-        };
+    #[test]
+    fn rejects_damaged_saves() {
+        let save = GameState::from_layout("*..\n...").unwrap().to_save();
+        assert!(GameState::from_save(&save).is_ok());
+
+        for (from, to) in [
+            ("minesweeper save", "minesweeper"),
+            ("size 3x2", "size 3"),
+            ("mines 1 1", "mines 2 2"),
+            ("status playing", "status paused"),
+            ("options", "options sideways"),
+            ("*..", "*.?"),
+            ("*..", "*."),
+        ] {
+            assert!(
+                GameState::from_save(&save.replacen(from, to, 1)).is_err(),
+                "{to}"
+            );
+        }
 
-        (column, row)
+        // Cut off partway through the board
+        assert!(GameState::from_save(&save[..save.find("...").unwrap()]).is_err());
     }
 
-    #[must_use]
-    pub fn read_input_mode(prompt: &str) -> InputMode {
-        let input_mode = loop {
-            let input = read_input(prompt);
-            let lower_input = input.trim().to_lowercase();
+    #[test]
+    fn saves_games_to_files() {
+        let path =
+            std::env::temp_dir().join(format!("minesweeper-save-{}.txt", std::process::id()));
+        let mut state = GameState::from_layout("*..\n...").unwrap();
+        state.reveal(5);
 
-            match lower_input.as_str() {
-                "clear" | "c" => break InputMode::Clear,
-                "flag" | "f" => break InputMode::Flag,
-                "undo" | "u" => break InputMode::Undo,
-                _ => println!("Invalid input. Please enter a valid input mode."),
-            }
-        };
+        state.save_to(&path).unwrap();
+        assert_eq!(state.board, GameState::load_from(&path).unwrap().board);
+
+        fs::write(&path, "minesweeper save\n").unwrap();
+        let error = GameState::load_from(&path).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
 
-        input_mode
+        fs::remove_file(&path).unwrap();
+        assert!(GameState::load_from(&path).is_err());
     }
 
-    #[must_use]
-    pub fn read_game_mode(prompt: &str) -> GameMode {
-        let game_mode = loop {
-            let input = read_input(prompt);
-            let lower_input = input.trim().to_lowercase();
+    #[test]
+    fn parses_layout_arg() {
+        let parsed = args(&["--layout", "puzzle.txt"]).unwrap();
 
-            match lower_input.as_str() {
-                "play" | "p" => break GameMode::Play,
-                "configure" | "c" => break GameMode::Config,
-                "quit" | "q" => break GameMode::Quit,
-                _ => println!("Invalid input. Please select a menu option."),
-            }
-        };
+        assert_eq!(Some(std::path::PathBuf::from("puzzle.txt")), parsed.layout);
+    }
 
-        game_mode
+    #[test]
+    fn undo_and_redo_restore_tiles_exactly() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(5)).unwrap();
+        let safe = state
+            .iter_tiles()
+            .position(|(_, _, tile)| !tile.has_mine())
+            .unwrap();
+        let fresh = state.board.tiles.clone();
+
+        state.toggle_flag(63);
+        let flagged = state.board.tiles.clone();
+        state.reveal(safe);
+        let cleared = state.board.tiles.clone();
+
+        assert!(state.undo());
+        assert_eq!(flagged, state.board.tiles);
+        assert_eq!(&[(63, InputMode::Flag)], state.get_moves());
+        assert_eq!(0, state.get_turn_count());
+
+        assert!(state.undo());
+        assert_eq!(fresh, state.board.tiles);
+        assert!(!state.undo());
+
+        assert!(state.redo());
+        assert!(state.redo());
+        assert_eq!(cleared, state.board.tiles);
+        assert_eq!(1, state.get_turn_count());
+        assert!(!state.redo());
     }
 
-    #[must_use]
-    pub fn read_difficulty(prompt: &str) -> Difficulty {
-        let difficulty = loop {
-            let input = read_input(prompt);
-            let lower_input = input.trim().to_lowercase();
+    #[test]
+    fn fresh_moves_clear_the_redo_stack() {
+        let mut state = engine::new_game(&Difficulty::Easy, Some(3)).unwrap();
 
-            match lower_input.as_str() {
-                "easy" | "e" => break Difficulty::Easy,
-                "medium" | "m" => break Difficulty::Medium,
-                "hard" | "h" => break Difficulty::Hard,
-                _ => println!("Invalid input. Please select a difficulty."),
-            }
-        };
+        state.toggle_flag(0);
+        state.undo();
+        assert!(state.can_redo());
 
-        difficulty
+        state.toggle_flag(1);
+
+        assert!(!state.can_redo());
+        assert!(!state.redo());
+        assert_eq!(&[(1, InputMode::Flag)], state.get_moves());
     }
 
-    #[allow(clippy::missing_panics_doc)]
-    pub fn enter_to_continue() {
-        let mut input = String::new();
-        print!("Press enter to continue... ");
+    #[test]
+    fn limits_undo_history() {
+        let mut state = engine::new_game(&Difficulty::Easy, Some(3)).unwrap();
+        state.set_history_limit(2);
+
+        for _ in 0..3 {
+            state.toggle_flag(0);
+        }
 
-        // # Panics
-        //
-        // This function will panic if flushing the stdout buffer fails.
-        // However, this is unlikely to happen under normal circumstances.
-        io::stdout().flush().unwrap();
+        assert!(state.undo());
+        assert!(state.undo());
+        assert!(!state.undo());
+        assert_eq!(&[(0, InputMode::Flag)], state.get_moves());
 
-        // # Panics
-        //
-        // This function will panic if reading from stdin fails.
-        // This could happen if there's an issue with the input stream,
-        // or if the process does not have access to the standard input.
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
+        state.set_history_limit(0);
+        state.toggle_flag(4);
+        assert!(!state.can_undo());
     }
-}
 
-#[cfg(test)]
-mod test {
-    // Open entire root module to our test module
-    use super::*;
+    #[test]
+    fn applies_actions_and_reports_changed_tiles() {
+        let mut state = GameState::from_layout("*..\n...\n...").unwrap();
+
+        let flagged = state.apply(Action::Flag(Coord::new(0, 0)));
+        assert!(flagged.applied);
+        assert_eq!(vec![0], flagged.changed);
+
+        // A zero in the corner floods everything but the mine
+        let revealed = state.apply(Action::Reveal(Coord::new(2, 2)));
+        assert!(revealed.applied);
+        assert_eq!((1..9).collect::<Vec<_>>(), revealed.changed);
+        assert!(revealed.game_won);
+
+        let undone = state.apply(Action::Undo);
+        assert!(undone.applied);
+        assert!(!undone.game_won);
+        assert_eq!(revealed.changed, undone.changed);
+    }
 
     #[test]
-    fn gets_game_over() {
-        let state = GameState::new();
+    fn rejects_actions_that_cant_be_made() {
+        let mut state = GameState::from_layout("*..\n...").unwrap();
 
-        assert_eq!(state.game_over, state.get_game_over());
+        let off_board = state.apply(Action::Reveal(Coord::new(2, 0)));
+        assert_eq!(ActionResult::default(), off_board);
+
+        state.apply(Action::Reveal(Coord::new(0, 1)));
+        let again = state.apply(Action::Flag(Coord::new(0, 1)));
+        assert!(!again.applied);
+        assert!(again.changed.is_empty());
+
+        // Nothing around the 1 is flagged yet
+        assert!(!state.apply(Action::Chord(Coord::new(0, 1))).applied);
+        assert!(!state.apply(Action::Redo).applied);
     }
 
     #[test]
-    fn gets_height() {
-        let state = GameState::new();
+    fn restarts_the_same_board() {
+        let mut state = GameState::from_layout("*..\n...").unwrap();
+        let fresh = state.board.tiles.clone();
+        state.set_lives(2);
+        state.set_first_click_safe(false);
+
+        state.apply(Action::Flag(Coord::new(0, 2)));
+        state.apply(Action::Reveal(Coord::new(0, 0)));
+        assert_eq!(1, state.get_lives());
+
+        let restarted = state.apply(Action::Restart);
+
+        assert!(restarted.applied);
+        assert_eq!(vec![0, 2], restarted.changed);
+        assert_eq!(fresh, state.board.tiles);
+        assert_eq!(2, state.get_lives());
+        assert_eq!(1, state.get_mine_count());
+        assert!(state.get_moves().is_empty());
+        assert!(!state.can_undo());
+    }
 
-        assert_eq!(state.board_height, state.get_height());
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_tiles_by_state() {
+        assert_eq!(
+            r#"{"state":"hidden","has_mine":true,"flagged":false,"questioned":false}"#,
+            serde_json::to_string(&hidden(true)).unwrap()
+        );
+        // From before question marks
+        assert_eq!(
+            hidden(true),
+            serde_json::from_str(r#"{"state":"hidden","has_mine":true,"flagged":false}"#).unwrap()
+        );
+        assert_eq!(
+            revealed(3),
+            serde_json::from_str(r#"{"state":"revealed","has_mine":false,"hint":3}"#).unwrap()
+        );
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn gets_width() {
-        let state = GameState::new();
+    fn round_trips_games_through_serde() {
+        let mut state = engine::new_game(&Difficulty::Medium, Some(5)).unwrap();
+        state.set_adjacency(Adjacency::Four);
+        state.set_lives(3);
+        state.set_time_limit(Some(Duration::from_secs(90)));
+        let safe = state
+            .iter_tiles()
+            .position(|(_, _, tile)| !tile.has_mine())
+            .unwrap();
+        state.toggle_flag(63);
+        state.reveal(safe);
+
+        let json = serde_json::to_string(&state).unwrap();
+        let loaded: GameState = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(state.board, loaded.board);
+        assert_eq!(state.get_moves(), loaded.get_moves());
+        assert_eq!(state.get_mine_count(), loaded.get_mine_count());
+        assert_eq!(state.get_turn_count(), loaded.get_turn_count());
+        assert_eq!(state.get_time_limit(), loaded.get_time_limit());
+        assert_eq!(state.get_seed(), loaded.get_seed());
+        assert_eq!(3, loaded.get_lives());
+
+        // Rebuilt rather than saved
+        assert_eq!(state.neighbors(9), loaded.neighbors(9));
+        assert_eq!(state.get_hint(9), loaded.get_hint(9));
+        assert!(!loaded.can_undo());
+    }
 
-        assert_eq!(state.board_width, state.get_width());
+    #[test]
+    fn counts_clicks_for_efficiency() {
+        let mut state = GameState::from_layout("*....\n.....\n....*").unwrap();
+        assert_eq!(None, state.efficiency());
+
+        // A wasted click on a revealed tile isn't applied, so it isn't counted
+        state.apply(Action::Reveal(Coord { col: 2, row: 1 }));
+        state.apply(Action::Reveal(Coord { col: 2, row: 1 }));
+        assert_eq!(1, state.get_clicks());
+        state.apply(Action::Hint);
+        assert_eq!(1, state.get_clicks());
+        state.apply(Action::Undo);
+        assert_eq!(1, state.get_clicks());
+
+        let efficiency = state.efficiency().unwrap();
+        assert!((efficiency - f64::from(crate::engine::solved_3bv(&state))).abs() < f64::EPSILON);
+
+        let save = state.to_save();
+        assert_eq!(1, GameState::from_save(&save).unwrap().get_clicks());
+
+        // Replays keep the count, since chords are logged as the clears they made
+        let mut state = crate::engine::new_game(&Difficulty::Easy, Some(42)).unwrap();
+        state.apply(Action::Reveal(Coord { col: 0, row: 0 }));
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert_eq!(Some(1), replay.clicks);
+        assert_eq!(1, replay.run().unwrap().get_clicks());
+        assert!(replay::Replay::parse("3x3 1 5 1\n")
+            .unwrap()
+            .clicks
+            .is_none());
+        assert!(replay::Replay::parse("3x3 1 5 1 clicks=x\n").is_err());
     }
 
     #[test]
-    fn gets_tile() {
-        let mut state = GameState::new();
+    fn ranks_leaderboard_entries() {
+        use crate::leaderboard::{Entry, Leaderboard, Ranking, TOP_ENTRIES};
 
-        state.tiles.push(Tile::Hidden {
-            has_mine: false,
-            flagged: false,
-        });
+        let entry = |name: &str, value| Entry {
+            name: name.to_string(),
+            value,
+        };
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(Some(1), leaderboard.place("easy", Ranking::Time, 9_000));
 
-        assert_eq!(&state.tiles[0], state.get_tile(0));
-    }
+        assert_eq!(
+            Some(1),
+            leaderboard.insert("easy", Ranking::Time, entry("Ann", 9_000))
+        );
+        assert_eq!(
+            Some(1),
+            leaderboard.insert("easy", Ranking::Time, entry("Bo", 8_000))
+        );
+        // Ties go to whoever set the time first
+        assert_eq!(
+            Some(3),
+            leaderboard.insert("easy", Ranking::Time, entry("Cy", 9_000))
+        );
+        assert_eq!(
+            Some(1),
+            leaderboard.insert("easy", Ranking::Score, entry("Cy", 700))
+        );
+        assert_eq!(
+            Some(1),
+            leaderboard.insert("easy", Ranking::Score, entry("Di", 900))
+        );
 
-    #[test]
-    fn gets_selected() {
-        let mut state = GameState::new();
+        let names: Vec<&str> = leaderboard
+            .get("easy", Ranking::Time)
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(vec!["Bo", "Ann", "Cy"], names);
+        assert_eq!("Di", leaderboard.get("easy", Ranking::Score)[0].name);
+        assert!(leaderboard.get("hard", Ranking::Time).is_empty());
+
+        // Only the top entries are kept
+        for _ in 0..TOP_ENTRIES {
+            leaderboard.insert("easy", Ranking::Time, entry("Ed", 1_000));
+        }
+        assert_eq!(TOP_ENTRIES, leaderboard.get("easy", Ranking::Time).len());
+        assert_eq!(None, leaderboard.place("easy", Ranking::Time, 1_000));
 
-        state.selected_tile = Some(1);
+        let parsed = Leaderboard::parse(&leaderboard.serialize()).unwrap();
+        assert_eq!(leaderboard, parsed);
+        assert!(Leaderboard::parse("easy speed 10 Ann").is_none());
+        assert!(Leaderboard::parse("easy time 10").is_none());
+    }
 
-        assert_eq!(1, state.get_selected());
+    #[test]
+    fn cleans_leaderboard_names() {
+        use crate::leaderboard::{clean_name, Entry, Leaderboard, Ranking, MAX_NAME_LENGTH};
+
+        assert_eq!("Ann Lee", clean_name("  Ann   Lee \n"));
+        assert_eq!("Anonymous", clean_name("   "));
+        assert_eq!(MAX_NAME_LENGTH, clean_name(&"x".repeat(50)).len());
+
+        // Names with spaces survive the file
+        let mut leaderboard = Leaderboard::default();
+        let entry = Entry {
+            name: clean_name("Ann Lee"),
+            value: 5,
+        };
+        leaderboard.insert("daily-easy", Ranking::Score, entry);
+        let parsed = Leaderboard::parse(&leaderboard.serialize()).unwrap();
+        assert_eq!("Ann Lee", parsed.get("daily-easy", Ranking::Score)[0].name);
     }
 
     #[test]
-    fn gets_input_mode() {
-        let mut state = GameState::new();
+    fn limits_undos_and_adds_their_penalty() {
+        let mut state = GameState::from_layout("*..\n...\n..*").unwrap();
+        state.set_first_click_safe(false);
+        state.set_undo_limit(Some(1));
+        assert_eq!(Some(1), state.undos_left());
+
+        // A fatal mine can be taken back
+        state.apply(Action::Reveal(Coord { col: 0, row: 0 }));
+        assert!(state.get_game_over());
+        assert_eq!(Ok(()), state.check_move(Action::Undo));
+        assert!(state.undo());
+        assert!(!state.get_game_over());
+        assert_eq!(UNDO_PENALTY, state.get_elapsed());
+
+        state.apply(Action::Reveal(Coord { col: 1, row: 0 }));
+        assert_eq!(
+            Err(crate::MoveError::NoUndosLeft),
+            state.check_move(Action::Undo)
+        );
+        assert!(!state.undo());
 
-        state.input_mode = InputMode::Clear;
+        let loaded = GameState::from_save(&state.to_save()).unwrap();
+        assert_eq!(
+            (Some(1), 1),
+            (loaded.get_undo_limit(), loaded.get_undos_used())
+        );
 
-        assert_eq!(InputMode::Clear, state.get_input_mode());
+        // Each game starts with its undos back
+        state.restart();
+        assert_eq!(Some(1), state.undos_left());
     }
 
     #[test]
-    fn gets_won() {
-        let mut state = GameState::new();
-
-        state.game_won = true;
+    fn practice_games_show_their_mines() {
+        let mut state = GameState::from_layout("*.\n..").unwrap();
+        assert_eq!(tile_cell(state.theme.hidden), state.represent_tile(0));
+
+        state.set_practice(true);
+        let dimmed = state.represent_tile(0);
+        assert!(dimmed.contains(state.theme.mine));
+        assert_ne!(tile_cell(state.theme.mine), dimmed);
+        assert_eq!(tile_cell(state.theme.hidden), state.represent_tile(1));
+
+        // Flags still show over practice mines
+        state.toggle_flag(0);
+        assert_eq!(tile_cell(state.theme.flag), state.represent_tile(0));
+
+        let loaded = GameState::from_save(&state.to_save()).unwrap();
+        assert!(loaded.get_practice());
+    }
 
-        assert_eq!(true, state.get_won());
+    #[test]
+    fn campaign_levels_get_harder() {
+        let boards: Vec<(u32, u32, u32)> = (0..campaign::LEVELS.len())
+            .map(|level| {
+                let progress = campaign::Progress {
+                    level,
+                    ..campaign::Progress::default()
+                };
+                progress.difficulty().unwrap().dimensions()
+            })
+            .collect();
+
+        for pair in boards.windows(2) {
+            let ((width, height, mines), (next_width, next_height, next_mines)) =
+                (pair[0], pair[1]);
+            assert!(next_width * next_height > width * height);
+            assert!(next_mines > mines);
+        }
     }
 
     #[test]
-    fn gets_mines() {
-        let mut state = GameState::new();
+    fn carries_campaign_progress_between_levels() {
+        let mut progress = campaign::Progress::default();
+        assert_eq!(campaign::STARTING_LIVES, progress.lives);
 
-        state.starting_mines = 6;
+        progress.finish_level(true, 2, 800);
+        progress.finish_level(true, 2, 1_200);
+        assert_eq!(
+            (2, 2, 2_000),
+            (progress.level, progress.lives, progress.score)
+        );
 
-        assert_eq!(6, state.get_mines());
-    }
+        let text = progress.serialize();
+        assert_eq!(Some(progress), campaign::Progress::parse(&text));
+        assert_eq!(None, campaign::Progress::parse("99 3 0"));
+        assert_eq!(
+            campaign::Progress::default(),
+            campaign::Progress::load(Path::new("/nonexistent/minesweeper/campaign.txt"))
+        );
 
-    #[test]
-    fn gets_game_mode() {
-        let mut state = GameState::new();
+        // Quitting mid-level keeps the level but not the lives lost on it
+        progress.leave_level(1);
+        assert_eq!(
+            (2, 1, 2_000),
+            (progress.level, progress.lives, progress.score)
+        );
+        progress.leave_level(3);
+        assert_eq!(1, progress.lives);
 
-        state.game_mode = GameMode::Play;
+        // Running out of lives starts over
+        progress.finish_level(false, 0, 300);
+        assert_eq!(campaign::Progress::default(), progress);
 
-        assert_eq!(GameMode::Play, state.get_game_mode());
+        progress.level = campaign::LEVELS.len();
+        assert!(progress.is_complete());
+        assert_eq!(None, progress.difficulty());
     }
 
     #[test]
-    fn sets_game_over() {
-        let mut state = GameState::new();
-        state.set_game_over(true);
+    fn caps_flags_below_the_mine_count() {
+        let mut state = GameState::from_layout("*.*\n...\n*.*").unwrap();
+        assert_eq!(4, state.flags_left());
+
+        state.set_flag_limit(Some(2));
+        assert!(state.toggle_flag(0));
+        assert!(state.toggle_flag(1));
+        assert_eq!(0, state.flags_left());
+        assert_eq!(
+            Err(crate::MoveError::NoFlagsLeft),
+            state.check_move(Action::Flag(Coord { col: 2, row: 0 }))
+        );
+        assert!(!state.toggle_flag(2));
 
-        assert_eq!(true, state.game_over)
-    }
+        // Taking a flag back frees it up again
+        assert!(state.toggle_flag(1));
+        assert_eq!(1, state.flags_left());
 
-    #[test]
-    fn sets_width() {
-        let mut state = GameState::new();
-        state.set_width(5);
+        let loaded = GameState::from_save(&state.to_save()).unwrap();
+        assert_eq!(Some(2), loaded.get_flag_limit());
+        assert_eq!(1, loaded.flags_left());
 
-        assert_eq!(5, state.board_width)
+        // Saves from before flags or undos were limited still load
+        let old = state.to_save().replace("limit - - 2", "limit -");
+        assert_eq!(None, GameState::from_save(&old).unwrap().get_flag_limit());
     }
 
     #[test]
-    fn sets_height() {
-        let mut state = GameState::new();
-        state.set_height(5);
+    fn stacks_several_mines_on_a_tile() {
+        let mut state = GameState::from_layout("*..\n...\n..*").unwrap();
+        state.set_mines_per_tile(5);
+        assert_eq!(3, state.get_mines_per_tile());
+        assert_eq!(2, state.total_mines());
+
+        state.apply(Action::Reveal(Coord { col: 1, row: 1 }));
+        for index in [0, 8] {
+            assert!((1..=3).contains(&state.mines_at(index)));
+        }
+        assert_eq!(0, state.mines_at(4));
+        // Hints count every mine in a stack
+        assert_eq!(state.total_mines(), state.get_hint(4));
+        assert_eq!(
+            Err(crate::MoveError::NotWithStackedMines),
+            state.check_move(Action::Hint)
+        );
 
-        assert_eq!(5, state.board_height)
+        let loaded = GameState::from_save(&state.to_save()).unwrap();
+        assert_eq!(3, loaded.get_mines_per_tile());
+        assert_eq!(state.total_mines(), loaded.total_mines());
+        assert_eq!(state.get_hint(4), loaded.get_hint(4));
+
+        // Undoing the first clear takes the stacks back with it
+        assert!(state.undo());
+        assert_eq!(2, state.total_mines());
+
+        // The solver counts a mine to a tile, so it stays out of the way when called directly too
+        let mut state = GameState::from_layout("*..").unwrap();
+        state.set_win_condition(WinCondition::FlagMines);
+        state.reveal(2);
+        state.set_mines_per_tile(2);
+        assert_eq!(None, state.hint());
+        assert!(!state.auto_solve_step());
+        assert_eq!(hidden(true), *state.get_tile(0));
+
+        state.set_mines_per_tile(1);
+        assert_eq!(Some(0), state.hint());
     }
 
     #[test]
-    fn sets_tile() {
-        let mut state = GameState::new();
-        state.tiles.push(Tile::Hidden {
-            has_mine: (false),
-            flagged: false,
-        });
-        state.set_tile(
-            0,
-            Tile::Revealed {
-                has_mine: (false),
-                hint: (0),
-            },
+    fn chords_beside_stacked_mines() {
+        let mut state = GameState::from_layout("*..").unwrap();
+        state.set_mines_per_tile(3);
+        state.mine_counts = vec![2, 0, 0];
+        engine::compute_hints(&mut state);
+        state.apply(Action::Reveal(Coord { col: 1, row: 0 }));
+        assert_eq!(2, state.get_hint(1));
+
+        // One flag isn't enough for a 2, so the chord and the clear beside it are held back
+        assert!(state.toggle_flag(0));
+        assert_eq!(1, state.flags_at(0));
+        assert_eq!(
+            Err(crate::MoveError::NotChordable),
+            state.check_move(Action::Chord(Coord { col: 1, row: 0 }))
         );
+        assert!(engine::is_risky_clear(&state, 2));
 
+        // Flagging again marks the second mine, without using up another flag
+        assert!(state.toggle_flag(0));
+        assert_eq!(2, state.flags_at(0));
+        assert_eq!(0, state.get_mine_count());
         assert_eq!(
-            state.tiles[0],
-            Tile::Revealed {
-                has_mine: (false),
-                hint: (0)
-            }
-        )
+            format!(" {}2", Theme::default().flag),
+            state.represent_tile(0)
+        );
+        assert!(!engine::is_risky_clear(&state, 2));
+        assert_eq!(
+            2,
+            GameState::from_save(&state.to_save()).unwrap().flags_at(0)
+        );
+
+        // Past the most a tile can hold, the flag comes off
+        assert!(state.toggle_flag(0));
+        assert!(state.toggle_flag(0));
+        assert_eq!(0, state.flags_at(0));
+        assert_eq!(1, state.get_mine_count());
+        assert!(state.toggle_flag(0));
+        assert_eq!(1, state.flags_at(0));
+
+        assert!(state.toggle_flag(0));
+        assert!(state.apply(Action::Chord(Coord { col: 1, row: 0 })).applied);
+        assert!(state.get_won());
     }
 
     #[test]
-    fn sets_selected() {
+    fn renders_triangle_boards() {
         let mut state = GameState::new();
-        state.set_selected(0);
-
-        assert_eq!(state.selected_tile.unwrap(), 0)
+        state.board_setup(3, 2, 0).unwrap();
+        for _ in 0..6 {
+            state.add_tile(hidden(false));
+        }
+        state.set_adjacency(Adjacency::Triangle);
+        assert_eq!(GridStyle::Triangle, state.grid_style());
+
+        let expected = [
+            "       A   B   C ",
+            "   1 / - \\ - / - \\",
+            "   2 \\ - / - \\ - /",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(expected, state.to_string());
+        assert_eq!((18, 3), grid_size(3, 2, GridStyle::Triangle));
+
+        let loaded = GameState::from_save(&state.to_save()).unwrap();
+        assert_eq!(Adjacency::Triangle, loaded.get_adjacency());
+
+        let state = args(&["--triangles", "-s", "1"])
+            .unwrap()
+            .game_state()
+            .unwrap()
+            .unwrap();
+        assert_eq!(Adjacency::Triangle, state.get_adjacency());
     }
 
     #[test]
-    fn sets_input_mode() {
-        let mut state = GameState::new();
-        state.set_input_mode(InputMode::Flag);
+    fn rejects_wrapping_triangles_on_odd_boards() {
+        assert_eq!(
+            Err(cli::ArgError::InvalidBoard(BoardError::UnevenWrap {
+                width: 5,
+                height: 6
+            })),
+            args(&["--triangles", "--wrap", "-w", "5", "-h", "6", "-m", "3"])
+        );
 
-        assert_eq!(state.input_mode, InputMode::Flag)
+        let state = args(&["--triangles", "--wrap", "-w", "6", "-h", "6", "-m", "3"])
+            .unwrap()
+            .game_state()
+            .unwrap()
+            .unwrap();
+        assert!(state.get_wrap());
     }
 
     #[test]
-    fn sets_won() {
-        let mut state = GameState::new();
-        state.set_won(true);
-
-        assert_eq!(state.game_won, true)
+    fn plays_wrapping_boards_from_the_command_line() {
+        let state = args(&["--wrap", "-s", "1"])
+            .unwrap()
+            .game_state()
+            .unwrap()
+            .unwrap();
+        assert!(state.get_wrap());
+        assert!((0..state.board.tiles.len()).all(|index| state.neighbors(index).len() == 8));
+
+        // Replays and restarts keep the edges joined up
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert!(replay.new_game().unwrap().get_wrap());
+        assert!(game_loop::Settings::default()
+            .restart(&state)
+            .unwrap()
+            .get_wrap());
     }
 
     #[test]
-    fn sets_mines() {
-        let mut state = GameState::new();
-        state.set_mines(6);
+    fn cuts_boards_to_shape() {
+        let rows = |shape: Shape| -> Vec<usize> {
+            (0..5)
+                .map(|row| {
+                    (0..5)
+                        .filter(|&col| shape.contains(5, 5, Coord::new(row, col)))
+                        .count()
+                })
+                .collect()
+        };
+        assert_eq!(vec![1, 3, 5, 3, 1], rows(Shape::Diamond));
+        assert_eq!(vec![1, 1, 5, 1, 1], rows(Shape::Cross));
+        assert_eq!(vec![5, 5, 4, 5, 5], rows(Shape::Donut));
+        assert_eq!(Ok(Shape::Donut), "o".parse());
+
+        let board = Difficulty::Custom {
+            width: 5,
+            height: 5,
+            mines: 4,
+        };
+        let state = engine::new_shaped_game(&board, Some(7), Shape::Diamond).unwrap();
+        let inactive: Vec<usize> = (0..25)
+            .filter(|&index| !state.get_tile(index).is_active())
+            .collect();
+        assert_eq!(12, inactive.len());
+        assert_eq!(13, state.remaining_safe() + 4);
+        assert_eq!("   ", state.represent_tile(0));
+        assert_eq!(
+            Err(crate::MoveError::OffBoard),
+            state.check_move(Action::Reveal(Coord { col: 0, row: 0 }))
+        );
+
+        // Saves and replays come back in the same shape
+        let loaded = GameState::from_save(&state.to_save()).unwrap();
+        assert_eq!(Shape::Diamond, loaded.get_shape());
+        assert_eq!(state.board.tiles, loaded.board.tiles);
+        let replay = replay::Replay::parse(&state.export_moves()).unwrap();
+        assert_eq!(state.board.tiles, replay.new_game().unwrap().board.tiles);
+
+        // The mines have to fit inside the shape
+        let crowded = Difficulty::Custom {
+            width: 5,
+            height: 5,
+            mines: 13,
+        };
+        assert_eq!(
+            Err(BoardError::TooManyMines {
+                mines: 13,
+                tiles: 13
+            }),
+            engine::new_shaped_game(&crowded, Some(7), Shape::Diamond).map(|_| ())
+        );
 
-        assert_eq!(state.starting_mines, 6)
+        let state = args(&["--shape", "cross", "-s", "1"])
+            .unwrap()
+            .game_state()
+            .unwrap()
+            .unwrap();
+        assert_eq!(Shape::Cross, state.get_shape());
+        assert!(args(&["--shape", "star"]).is_err());
     }
 
     #[test]
-    fn sets_game_mode() {
-        let mut state = GameState::new();
-        state.set_game_mode(GameMode::Play);
-
-        assert_eq!(state.game_mode, GameMode::Play)
+    fn wins_without_the_tiles_outside_the_shape() {
+        let mut state = GameState::from_layout("#.#\n.*.\n#.#").unwrap();
+        assert_eq!(1, state.get_hint(1));
+        assert!(GameState::from_layout("#*#\n#.#").is_ok());
+        assert!(GameState::from_layout("#*#\n###").is_err());
+
+        for col in [0, 2] {
+            state.apply(Action::Reveal(Coord { col, row: 1 }));
+        }
+        state.apply(Action::Reveal(Coord { col: 1, row: 0 }));
+        assert!(!state.get_won());
+        state.apply(Action::Reveal(Coord { col: 1, row: 2 }));
+        assert!(state.get_won());
+        assert!(!state.get_tile(0).is_active());
     }
 }
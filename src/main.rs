@@ -1,8 +1,50 @@
 // Set clippy to pedantic
 #![warn(clippy::pedantic)]
 
-use minesweeper::{self, game_loop};
+use minesweeper::{self, cli, game_loop, replay::Replay, GameState};
+use std::{env, fs, process};
 
 fn main() {
-    game_loop::play();
+    let args = match cli::Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}\n{}", cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Some(path) = &args.replay {
+        let replay = fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|log| Replay::parse(&log).map_err(|error| error.to_string()));
+
+        match replay {
+            Ok(replay) => game_loop::play_replay(&replay),
+            Err(error) => {
+                eprintln!("Unable to load replay {}: {error}", path.display());
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = &args.layout {
+        match GameState::from_layout_file(path) {
+            Ok(state) => game_loop::play_from(state, args.json),
+            Err(error) => {
+                eprintln!("Unable to load layout {}: {error}", path.display());
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    match args.game_state() {
+        Ok(Some(state)) => game_loop::play_from(state, args.json),
+        Ok(None) => game_loop::play(args.json),
+        Err(error) => {
+            eprintln!("{error}\n{}", cli::USAGE);
+            process::exit(2);
+        }
+    }
 }